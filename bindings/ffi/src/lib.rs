@@ -31,7 +31,9 @@ pub unsafe extern "C" fn gatos_free(s: *mut libc::c_char) {
 }
 
 // Internal helpers to deduplicate parsing and encoding logic across FFI entrypoints.
-use gatos_ledger_core::{compute_content_id, CommitCore, Hash};
+use gatos_ledger_core::{
+    compute_content_id, compute_explorer_root_hex, jcs, Blake3Digest, CommitCore, Hash,
+};
 
 /// # Safety
 /// If `has` is true, `ptr` must be non-null and point to at least 32 readable bytes.
@@ -56,7 +58,7 @@ unsafe fn parse_hash(ptr: *const u8) -> Result<Hash, ()> {
 
 fn compute_and_encode(core: &CommitCore) -> *mut libc::c_char {
     compute_content_id(core).map_or(std::ptr::null_mut(), |id| {
-        let s = hex::encode(id);
+        let s = Blake3Digest::new(id).to_hex();
         std::ffi::CString::new(s).map_or(std::ptr::null_mut(), std::ffi::CString::into_raw)
     })
 }
@@ -161,6 +163,76 @@ pub unsafe extern "C" fn gatos_compute_content_id_hex_v2(
     compute_and_encode(&core)
 }
 
+/// Compute the canonical content id of a JSON artifact (job manifest,
+/// Proof-of-Consensus envelope, opaque pointer): `blake3(jcs(json))`, where
+/// `jcs` is RFC 8785 canonical JSON with NFC-normalized strings. Returns a
+/// lowercase hex string (caller must free via `gatos_ffi_free_string`), or
+/// NULL if the input is not valid UTF-8 JSON or contains duplicate keys.
+///
+/// # Safety
+/// `json_ptr` must point to `json_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gatos_content_id_json_hex(
+    json_ptr: *const u8,
+    json_len: usize,
+) -> *mut libc::c_char {
+    if json_ptr.is_null() {
+        return std::ptr::null_mut();
+    }
+    let bytes = core::slice::from_raw_parts(json_ptr, json_len);
+    let Ok(text) = core::str::from_utf8(bytes) else {
+        return std::ptr::null_mut();
+    };
+    match jcs::parse(text).and_then(|v| jcs::content_id_json(&v)) {
+        Ok(id) => std::ffi::CString::new(Blake3Digest::new(id).to_hex())
+            .map_or(std::ptr::null_mut(), std::ffi::CString::into_raw),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Compute the SPEC §15.1 `Explorer-Root` for an export from NUL-terminated
+/// lowercase hex digests and the extractor version string. Pass NULL for
+/// `fold_root_hex` on raw ledger exports. Returns lowercase hex (caller must
+/// free via `gatos_ffi_free_string`), or NULL on malformed input.
+///
+/// # Safety
+/// `ledger_head_hex`, `policy_root_hex` and `version` must be valid
+/// NUL-terminated strings; `fold_root_hex` must be NULL or one as well.
+#[no_mangle]
+pub unsafe extern "C" fn gatos_compute_explorer_root_hex(
+    ledger_head_hex: *const libc::c_char,
+    policy_root_hex: *const libc::c_char,
+    fold_root_hex: *const libc::c_char,
+    version: *const libc::c_char,
+) -> *mut libc::c_char {
+    unsafe fn c_str<'a>(p: *const libc::c_char) -> Option<&'a str> {
+        if p.is_null() {
+            return None;
+        }
+        std::ffi::CStr::from_ptr(p).to_str().ok()
+    }
+    let (Some(head), Some(policy), Some(version)) = (
+        c_str(ledger_head_hex),
+        c_str(policy_root_hex),
+        c_str(version),
+    ) else {
+        return std::ptr::null_mut();
+    };
+    let fold = if fold_root_hex.is_null() {
+        None
+    } else {
+        match c_str(fold_root_hex) {
+            Some(f) => Some(f),
+            None => return std::ptr::null_mut(),
+        }
+    };
+    match compute_explorer_root_hex(head, policy, fold, version) {
+        Ok(root) => std::ffi::CString::new(Blake3Digest::new(root).to_hex())
+            .map_or(std::ptr::null_mut(), std::ffi::CString::into_raw),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,4 +244,44 @@ mod tests {
         assert!(!p.is_null());
         unsafe { gatos_ffi_free_string(p) };
     }
+
+    #[test]
+    fn content_id_json_ignores_key_order_and_whitespace() {
+        let hex_of = |json: &str| unsafe {
+            let p = gatos_content_id_json_hex(json.as_ptr(), json.len());
+            assert!(!p.is_null());
+            let s = std::ffi::CStr::from_ptr(p).to_str().unwrap().to_owned();
+            gatos_ffi_free_string(p);
+            s
+        };
+        assert_eq!(
+            hex_of(r#"{"b": 1, "a": [true]}"#),
+            hex_of(r#"{"a":[true],"b":1}"#)
+        );
+        let dup = r#"{"a":1,"a":2}"#;
+        assert!(unsafe { gatos_content_id_json_hex(dup.as_ptr(), dup.len()) }.is_null());
+    }
+
+    #[test]
+    fn explorer_root_matches_spec_example() {
+        let c = |s: &str| std::ffi::CString::new(s).unwrap();
+        let head = c("1111111111111111111111111111111111111111");
+        let policy = c("2222222222222222222222222222222222222222");
+        let fold = c("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+        let version = c("v1.2.3+linux-x86_64");
+        unsafe {
+            let p = gatos_compute_explorer_root_hex(
+                head.as_ptr(),
+                policy.as_ptr(),
+                fold.as_ptr(),
+                version.as_ptr(),
+            );
+            assert!(!p.is_null());
+            assert_eq!(
+                std::ffi::CStr::from_ptr(p).to_str().unwrap(),
+                "e4cbbcc02698608bb59ed39d715ffd6e74c2865f473daf7cb861f9da33565d9d"
+            );
+            gatos_ffi_free_string(p);
+        }
+    }
 }
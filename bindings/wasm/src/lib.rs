@@ -1,3 +1,4 @@
+use gatos_ledger_core::Blake3Digest;
 use wasm_bindgen::prelude::*;
 
 /// Returns a static greeting without heap allocation for native Rust callers.
@@ -30,7 +31,7 @@ pub fn compute_commit_id_wasm(
     }
     let core = validate_and_build_core(parent, tree, String::new(), 0)?;
     gatos_ledger_core::compute_content_id(&core)
-        .map(hex::encode)
+        .map(|id| Blake3Digest::new(id).to_hex())
         .map_err(|_| JsValue::from_str("serialize failure"))
 }
 
@@ -47,10 +48,45 @@ pub fn compute_content_id_wasm_v2(
 ) -> Result<String, JsValue> {
     let core = validate_and_build_core(parent, tree, message.to_string(), timestamp)?;
     gatos_ledger_core::compute_content_id(&core)
-        .map(hex::encode)
+        .map(|id| Blake3Digest::new(id).to_hex())
         .map_err(|_| JsValue::from_str("serialize failure"))
 }
 
+/// Compute the canonical content id of a JSON artifact: `blake3(jcs(json))`
+/// per RFC 8785 with NFC-normalized strings. Returns lowercase hex.
+#[wasm_bindgen]
+/// # Errors
+/// Returns `Err(JsValue)` when the input is not valid JSON or has duplicate keys.
+pub fn content_id_json_wasm(json: &str) -> Result<String, JsValue> {
+    use gatos_ledger_core::jcs;
+    jcs::parse(json)
+        .and_then(|v| jcs::content_id_json(&v))
+        .map(|id| Blake3Digest::new(id).to_hex())
+        .map_err(|_| JsValue::from_str("invalid canonical json"))
+}
+
+/// Compute the SPEC §15.1 `Explorer-Root` from lowercase hex digests and the
+/// extractor version. Omit `fold_root` for raw ledger exports. Returns
+/// lowercase hex.
+#[wasm_bindgen]
+/// # Errors
+/// Returns `Err(JsValue)` when a digest is not lowercase hex.
+pub fn compute_explorer_root_wasm(
+    ledger_head: &str,
+    policy_root: &str,
+    fold_root: Option<String>,
+    extractor_version: &str,
+) -> Result<String, JsValue> {
+    gatos_ledger_core::compute_explorer_root_hex(
+        ledger_head,
+        policy_root,
+        fold_root.as_deref(),
+        extractor_version,
+    )
+    .map(|root| Blake3Digest::new(root).to_hex())
+    .map_err(|_| JsValue::from_str("invalid explorer root input"))
+}
+
 fn validate_and_build_core(
     parent: Option<Vec<u8>>,
     tree: &[u8],
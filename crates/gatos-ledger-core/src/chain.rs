@@ -0,0 +1,313 @@
+//! Ancestry and chain-integrity verification.
+//!
+//! [`verify_chain`] walks `CommitCore::parent` links from a head commit
+//! through an [`ObjectStore`] and records every integrity problem it finds in
+//! a [`ChainReport`]. Backend failures abort the walk with a [`StoreError`];
+//! problems with the history itself (missing parents, tampered objects,
+//! clock regressions, wrong root) are findings, not errors, so callers such
+//! as `git gatos verify` and the push gate can report them all at once.
+
+use alloc::vec::Vec;
+
+use crate::codec::decode_commit_object;
+use crate::{commit_object_id, Hash, ObjectStore, StoreError};
+
+/// Where a chain is expected to end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainAnchor {
+    /// Any root commit (no parent) is acceptable.
+    AnyGenesis,
+    /// The walk must reach this root commit.
+    Genesis(Hash),
+    /// The walk stops successfully on reaching this already-verified
+    /// checkpoint commit; it is not loaded or re-checked.
+    Checkpoint(Hash),
+}
+
+/// Verification settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainPolicy {
+    /// Where the chain must end.
+    pub anchor: ChainAnchor,
+    /// Require `child.timestamp >= parent.timestamp` for every link.
+    pub monotonic_timestamps: bool,
+    /// Stop with [`ChainIssue::DepthExceeded`] after this many commits.
+    pub max_depth: Option<usize>,
+}
+
+impl Default for ChainPolicy {
+    fn default() -> Self {
+        Self {
+            anchor: ChainAnchor::AnyGenesis,
+            monotonic_timestamps: true,
+            max_depth: None,
+        }
+    }
+}
+
+/// A single integrity finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainIssue {
+    /// The head id is not in the store.
+    MissingHead(Hash),
+    /// `child` names a parent that is not in the store.
+    MissingParent { child: Hash, parent: Hash },
+    /// Bytes stored under `id` are not a canonical commit.
+    Undecodable(Hash),
+    /// The commit stored under `expected` hashes to `actual`.
+    IdMismatch { expected: Hash, actual: Hash },
+    /// `child` is timestamped before its `parent`.
+    TimestampRegression {
+        child: Hash,
+        child_ts: u64,
+        parent: Hash,
+        parent_ts: u64,
+    },
+    /// The chain reached a root commit other than the expected one.
+    UnexpectedGenesis { expected: Option<Hash>, found: Hash },
+    /// The walk hit `max_depth` before reaching the anchor.
+    DepthExceeded(usize),
+}
+
+/// Outcome of [`verify_chain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainReport {
+    /// Commit the walk started from.
+    pub head: Hash,
+    /// Commits loaded and checked, head first.
+    pub verified: Vec<Hash>,
+    /// The anchor the walk ended on (the root commit or the checkpoint), if
+    /// it ended cleanly.
+    pub anchor: Option<Hash>,
+    /// Findings in walk order; empty for a valid chain.
+    pub issues: Vec<ChainIssue>,
+}
+
+impl ChainReport {
+    /// `true` when the chain reached the expected anchor with no findings.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty() && self.anchor.is_some()
+    }
+}
+
+/// Walk the chain ending at `head` and check it against `policy`.
+///
+/// # Errors
+/// Returns a [`StoreError`] only if the backend itself fails; integrity
+/// problems are reported in [`ChainReport::issues`].
+pub fn verify_chain<S: ObjectStore + ?Sized>(
+    store: &S,
+    head: &Hash,
+    policy: &ChainPolicy,
+) -> Result<ChainReport, StoreError> {
+    let mut report = ChainReport {
+        head: *head,
+        verified: Vec::new(),
+        anchor: None,
+        issues: Vec::new(),
+    };
+    let mut current = *head;
+    // (child id, child timestamp) of the link being followed.
+    let mut child: Option<(Hash, u64)> = None;
+    loop {
+        if policy.anchor == ChainAnchor::Checkpoint(current) {
+            report.anchor = Some(current);
+            break;
+        }
+        if policy
+            .max_depth
+            .is_some_and(|max| report.verified.len() >= max)
+        {
+            report
+                .issues
+                .push(ChainIssue::DepthExceeded(report.verified.len()));
+            break;
+        }
+        let Some(bytes) = store.get_object(&commit_object_id(&current))? else {
+            report.issues.push(match child {
+                None => ChainIssue::MissingHead(current),
+                Some((child, _)) => ChainIssue::MissingParent {
+                    child,
+                    parent: current,
+                },
+            });
+            break;
+        };
+        let Ok((commit, actual)) = decode_commit_object(&bytes) else {
+            report.issues.push(ChainIssue::Undecodable(current));
+            break;
+        };
+        if actual != current {
+            report.issues.push(ChainIssue::IdMismatch {
+                expected: current,
+                actual,
+            });
+            break;
+        }
+        if let Some((child_id, child_ts)) = child {
+            if policy.monotonic_timestamps && child_ts < commit.core.timestamp {
+                report.issues.push(ChainIssue::TimestampRegression {
+                    child: child_id,
+                    child_ts,
+                    parent: current,
+                    parent_ts: commit.core.timestamp,
+                });
+            }
+        }
+        report.verified.push(current);
+        match commit.core.parent {
+            Some(parent) => {
+                child = Some((current, commit.core.timestamp));
+                current = parent;
+            }
+            None => {
+                match policy.anchor {
+                    ChainAnchor::AnyGenesis => report.anchor = Some(current),
+                    ChainAnchor::Genesis(expected) if expected == current => {
+                        report.anchor = Some(current);
+                    }
+                    ChainAnchor::Genesis(expected) => {
+                        report.issues.push(ChainIssue::UnexpectedGenesis {
+                            expected: Some(expected),
+                            found: current,
+                        });
+                    }
+                    ChainAnchor::Checkpoint(_) => {
+                        report.issues.push(ChainIssue::UnexpectedGenesis {
+                            expected: None,
+                            found: current,
+                        });
+                    }
+                }
+                break;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_commit, put_commit, Commit, CommitCore, MemoryStore};
+    use smallvec::SmallVec;
+    use std::string::ToString;
+
+    fn commit(parent: Option<Hash>, timestamp: u64) -> Commit {
+        Commit {
+            core: CommitCore {
+                parent,
+                tree: [0; 32],
+                message: "m".to_string(),
+                timestamp,
+            },
+            sigs: SmallVec::new(),
+        }
+    }
+
+    /// Store a linear chain with the given timestamps; returns ids root first.
+    fn chain(store: &mut MemoryStore, timestamps: &[u64]) -> Vec<Hash> {
+        let mut ids: Vec<Hash> = Vec::new();
+        for &ts in timestamps {
+            let id = put_commit(store, &commit(ids.last().copied(), ts)).unwrap();
+            ids.push(id);
+        }
+        ids
+    }
+
+    #[test]
+    fn valid_chain_to_genesis_and_checkpoint() {
+        let mut store = MemoryStore::new();
+        let ids = chain(&mut store, &[1, 2, 3]);
+        let report = verify_chain(&store, &ids[2], &ChainPolicy::default()).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.verified, [ids[2], ids[1], ids[0]]);
+
+        let policy = ChainPolicy {
+            anchor: ChainAnchor::Checkpoint(ids[1]),
+            ..ChainPolicy::default()
+        };
+        let report = verify_chain(&store, &ids[2], &policy).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.verified, [ids[2]]);
+        assert_eq!(report.anchor, Some(ids[1]));
+    }
+
+    #[test]
+    fn reports_wrong_genesis_and_missing_parent() {
+        let mut store = MemoryStore::new();
+        let ids = chain(&mut store, &[1, 2]);
+        let policy = ChainPolicy {
+            anchor: ChainAnchor::Genesis([9; 32]),
+            ..ChainPolicy::default()
+        };
+        let report = verify_chain(&store, &ids[1], &policy).unwrap();
+        assert_eq!(
+            report.issues,
+            [ChainIssue::UnexpectedGenesis {
+                expected: Some([9; 32]),
+                found: ids[0],
+            }]
+        );
+
+        let orphan = put_commit(&mut store, &commit(Some([7; 32]), 5)).unwrap();
+        let report = verify_chain(&store, &orphan, &ChainPolicy::default()).unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.issues,
+            [ChainIssue::MissingParent {
+                child: orphan,
+                parent: [7; 32],
+            }]
+        );
+        let report = verify_chain(&store, &[8; 32], &ChainPolicy::default()).unwrap();
+        assert_eq!(report.issues, [ChainIssue::MissingHead([8; 32])]);
+    }
+
+    #[test]
+    fn timestamp_regression_is_configurable() {
+        let mut store = MemoryStore::new();
+        let ids = chain(&mut store, &[10, 5]);
+        let report = verify_chain(&store, &ids[1], &ChainPolicy::default()).unwrap();
+        assert_eq!(
+            report.issues,
+            [ChainIssue::TimestampRegression {
+                child: ids[1],
+                child_ts: 5,
+                parent: ids[0],
+                parent_ts: 10,
+            }]
+        );
+        let lenient = ChainPolicy {
+            monotonic_timestamps: false,
+            ..ChainPolicy::default()
+        };
+        assert!(verify_chain(&store, &ids[1], &lenient).unwrap().is_valid());
+    }
+
+    #[test]
+    fn detects_tampered_object_and_depth_limit() {
+        let mut store = crate::testing::MapStore::default();
+        let root = commit(None, 1);
+        let bogus = [3; 32];
+        // Bypass the invariant to simulate a tampered backend.
+        store
+            .0
+            .insert(commit_object_id(&bogus), encode_commit(&root).unwrap());
+        let report = verify_chain(&store, &bogus, &ChainPolicy::default()).unwrap();
+        assert!(matches!(
+            report.issues[..],
+            [ChainIssue::IdMismatch { expected, .. }] if expected == bogus
+        ));
+
+        let mut store = MemoryStore::new();
+        let ids = chain(&mut store, &[1, 2, 3]);
+        let policy = ChainPolicy {
+            max_depth: Some(2),
+            ..ChainPolicy::default()
+        };
+        let report = verify_chain(&store, &ids[2], &policy).unwrap();
+        assert_eq!(report.issues, [ChainIssue::DepthExceeded(2)]);
+    }
+}
@@ -0,0 +1,209 @@
+//! CIDv1 content identifiers (multiformats) for DAG-CBOR objects.
+//!
+//! Binary form: `varint(1) || varint(codec) || varint(hash_code) ||
+//! varint(digest_len) || digest`. The text form is multibase base32
+//! (lowercase, unpadded, `b` prefix), matching IPFS/IPLD tooling.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+/// Multicodec code for DAG-CBOR.
+pub const DAG_CBOR: u64 = 0x71;
+/// Multihash code for BLAKE3 (256-bit default output).
+pub const BLAKE3: u64 = 0x1e;
+/// Multihash code for SHA2-256.
+pub const SHA2_256: u64 = 0x12;
+
+/// Errors produced while parsing a [`Cid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CidError {
+    /// Missing or unsupported multibase prefix (only base32 `b` is accepted).
+    Multibase,
+    /// Invalid base32 character or padding.
+    Base32,
+    /// Truncated or over-long varint.
+    Varint,
+    /// CID version other than 1.
+    Version,
+    /// Digest length does not match the declared length.
+    Length,
+}
+
+/// A CIDv1: content codec plus a multihash.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cid {
+    codec: u64,
+    hash_code: u64,
+    digest: Vec<u8>,
+}
+
+impl Cid {
+    /// Build a CIDv1 from its parts.
+    #[must_use]
+    pub fn new_v1(codec: u64, hash_code: u64, digest: &[u8]) -> Self {
+        Self {
+            codec,
+            hash_code,
+            digest: digest.to_vec(),
+        }
+    }
+
+    /// `cidv1(dag-cbor, blake3(bytes))`.
+    #[must_use]
+    pub fn dag_cbor_blake3(bytes: &[u8]) -> Self {
+        Self::new_v1(DAG_CBOR, BLAKE3, blake3::hash(bytes).as_bytes())
+    }
+
+    #[must_use]
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    #[must_use]
+    pub fn hash_code(&self) -> u64 {
+        self.hash_code
+    }
+
+    #[must_use]
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Binary CID bytes.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + self.digest.len());
+        put_varint(&mut out, 1);
+        put_varint(&mut out, self.codec);
+        put_varint(&mut out, self.hash_code);
+        put_varint(&mut out, self.digest.len() as u64);
+        out.extend_from_slice(&self.digest);
+        out
+    }
+
+    /// Parse binary CID bytes. The input must contain exactly one CID.
+    ///
+    /// # Errors
+    /// Returns a [`CidError`] if the bytes are not a well-formed CIDv1.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CidError> {
+        let mut rest = bytes;
+        if take_varint(&mut rest)? != 1 {
+            return Err(CidError::Version);
+        }
+        let codec = take_varint(&mut rest)?;
+        let hash_code = take_varint(&mut rest)?;
+        let len = take_varint(&mut rest)?;
+        if usize::try_from(len).ok() != Some(rest.len()) {
+            return Err(CidError::Length);
+        }
+        Ok(Self::new_v1(codec, hash_code, rest))
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("b")?;
+        f.write_str(&base32_encode(&self.to_bytes()))
+    }
+}
+
+impl FromStr for Cid {
+    type Err = CidError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let body = s.strip_prefix('b').ok_or(CidError::Multibase)?;
+        Self::from_bytes(&base32_decode(body)?)
+    }
+}
+
+pub(crate) fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// Unsigned LEB128 as used by multiformats; rejects non-minimal encodings.
+pub(crate) fn take_varint(rest: &mut &[u8]) -> Result<u64, CidError> {
+    let mut value = 0u64;
+    for (i, &b) in rest.iter().enumerate().take(9) {
+        value |= u64::from(b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
+            if b == 0 && i > 0 {
+                return Err(CidError::Varint);
+            }
+            *rest = &rest[i + 1..];
+            return Ok(value);
+        }
+    }
+    Err(CidError::Varint)
+}
+
+const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &b in bytes {
+        acc = (acc << 8) | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((acc >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((acc << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(s: &str) -> Result<Vec<u8>, CidError> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for c in s.bytes() {
+        let v = ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or(CidError::Base32)? as u32;
+        acc = (acc << 5) | v;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    // Leftover bits must be zero padding from the encoder.
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return Err(CidError::Base32);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn text_roundtrip() {
+        let cid = Cid::dag_cbor_blake3(b"hello");
+        let text = cid.to_string();
+        // 0x01 0x71 0x1e 0x20 => "bafyr4i..."
+        assert!(text.starts_with("bafyr4i"), "{text}");
+        assert_eq!(text.parse::<Cid>().unwrap(), cid);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert_eq!("zabc".parse::<Cid>(), Err(CidError::Multibase));
+        assert_eq!("bAFY".parse::<Cid>(), Err(CidError::Base32));
+        let mut bytes = Cid::dag_cbor_blake3(b"x").to_bytes();
+        bytes.pop();
+        assert_eq!(Cid::from_bytes(&bytes), Err(CidError::Length));
+        assert_eq!(Cid::from_bytes(&[0x00]), Err(CidError::Version));
+    }
+}
@@ -0,0 +1,396 @@
+//! Canonical commit codec and `ObjectStore` persistence helpers.
+//!
+//! A commit object is the bincode (`config::standard()`) encoding of the
+//! core in its versioned form (see [`versioning`](crate::versioning))
+//! immediately followed by the length-prefixed signature list:
+//!
+//! ```text
+//! commit object = varint(core version) || core in that version's layout || sigs
+//! ```
+//!
+//! Older versions are migrated to the current [`CommitCore`](crate::CommitCore) on decode; their
+//! ids stay those of the layout they were written in. The object is addressed by [`commit_object_id`] of the commit content id
+//! (ADR-0001) rather than by the hash of the whole object, so attaching
+//! signatures never changes where a commit lives.
+//!
+//! Commit addresses are a separate address space: they are BLAKE3 key
+//! derivations, which no plain `blake3(data)` id can equal. In particular the
+//! bare core bytes (whose hash *is* the content id) can never be stored where
+//! [`get_commit`] looks.
+//!
+//! Decoding is strict. Trailing bytes and non-minimal encodings are rejected
+//! so that every commit has exactly one valid byte representation, and length
+//! prefixes may not claim more than [`DECODE_LIMIT`](crate::DECODE_LIMIT).
+
+use alloc::vec::Vec;
+
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{config, decode_from_slice, encode_to_vec, Decode, Encode};
+use smallvec::SmallVec;
+
+use crate::{
+    compute_content_id, decode_config, Commit, Hash, ObjectStore, Signature, StoreError,
+    VersionedCommitCore,
+};
+
+/// BLAKE3 key-derivation context for commit object addresses.
+const COMMIT_OBJECT_CONTEXT: &str = "gatos-ledger 2025-01 commit object";
+
+impl Encode for Commit {
+    /// Writes the core tagged with the current format version.
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&VersionedCommitCore::from(self.core.clone()), encoder)?;
+        self.sigs.as_slice().encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for Commit {
+    /// Reads a core of any known format version, migrated to the current one.
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let core = <VersionedCommitCore as Decode<Context>>::decode(decoder)?;
+        let sigs: Vec<Signature> = Vec::decode(decoder)?;
+        Ok(Self {
+            core: core.into_current(),
+            sigs: SmallVec::from_vec(sigs),
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(Commit);
+
+/// Encode a full commit (core plus signatures) into its canonical bytes.
+///
+/// # Errors
+/// Returns an error if serialization fails under the canonical configuration.
+pub fn encode_commit(commit: &Commit) -> Result<Vec<u8>, EncodeError> {
+    encode_to_vec(commit, config::standard())
+}
+
+/// Decode a full commit from its canonical bytes.
+///
+/// # Errors
+/// Returns an error if the bytes are malformed, contain trailing data, or are
+/// not the canonical encoding of the decoded value.
+pub fn decode_commit(bytes: &[u8]) -> Result<Commit, DecodeError> {
+    decode_commit_object(bytes).map(|(commit, _)| commit)
+}
+
+/// Decode a commit object together with its content id, computed under the
+/// layout version the core was written in.
+pub(crate) fn decode_commit_object(bytes: &[u8]) -> Result<(Commit, Hash), DecodeError> {
+    type Stored = (VersionedCommitCore, Vec<Signature>);
+    let ((core, sigs), read): (Stored, usize) = decode_from_slice(bytes, decode_config())?;
+    if read != bytes.len() {
+        return Err(DecodeError::Other("trailing bytes after commit"));
+    }
+    // bincode accepts over-long varints; re-encoding pins the single valid form.
+    let canonical = encode_to_vec((&core, &sigs), config::standard())
+        .map_err(|_| DecodeError::Other("commit does not re-encode canonically"))?;
+    if canonical != bytes {
+        return Err(DecodeError::Other("non-canonical commit encoding"));
+    }
+    let content_id = core
+        .content_id()
+        .map_err(|_| DecodeError::Other("commit does not re-encode canonically"))?;
+    let commit = Commit {
+        core: core.into_current(),
+        sigs: SmallVec::from_vec(sigs),
+    };
+    Ok((commit, content_id))
+}
+
+/// Object id under which the commit with content id `content_id` is stored.
+#[must_use]
+pub fn commit_object_id(content_id: &Hash) -> Hash {
+    blake3::derive_key(COMMIT_OBJECT_CONTEXT, content_id)
+}
+
+/// Returns `true` when `data` may be stored under `id`.
+///
+/// Plain objects are addressed by `blake3(data)`. Canonical commit objects are
+/// addressed by [`commit_object_id`] of their content id (see the module
+/// docs). Backends SHOULD use this check to enforce the [`ObjectStore`]
+/// id/content invariant.
+#[must_use]
+pub fn object_id_matches(id: &Hash, data: &[u8]) -> bool {
+    if blake3::hash(data).as_bytes() == id {
+        return true;
+    }
+    decode_commit_object(data).is_ok_and(|(_, content_id)| &commit_object_id(&content_id) == id)
+}
+
+/// [`object_id_matches`] as a `Result`, for use at the top of
+/// [`ObjectStore::put_object`] implementations.
+///
+/// # Errors
+/// Returns [`StoreError::Corruption`] with `actual` set to `blake3(data)` if
+/// `data` may not be stored under `id`.
+pub fn verify_object_id(id: &Hash, data: &[u8]) -> Result<(), StoreError> {
+    if object_id_matches(id, data) {
+        Ok(())
+    } else {
+        Err(StoreError::corruption(id, data))
+    }
+}
+
+/// Persist a commit in `store` and return its content id.
+///
+/// Every signature set of one core shares that core's address, so the
+/// signatures already stored there are kept: the stored commit becomes the
+/// union, existing signatures first. Each signature not yet stored must
+/// verify over the content id, so junk cannot accumulate under a commit.
+///
+/// The read-merge-write is not atomic. Two writers adding different
+/// signatures to the same commit concurrently can each overwrite the other's
+/// addition; callers that share a store serialize their `put_commit`s.
+///
+/// # Errors
+/// Returns [`StoreError::Signature`] for a new signature that does not
+/// verify, [`StoreError::Corruption`] if the object already stored under the
+/// commit's address is not a valid commit (it is left in place as evidence),
+/// [`StoreError::Invariant`] if the commit cannot be serialized, or any error
+/// reported by the backend.
+pub fn put_commit<S: ObjectStore + ?Sized>(
+    store: &mut S,
+    commit: &Commit,
+) -> Result<Hash, StoreError> {
+    let id =
+        compute_content_id(&commit.core).map_err(|_| StoreError::Invariant("commit serializes"))?;
+    let stored = get_commit(store, &id)?;
+    let exists = stored.is_some();
+    let mut merged = stored.unwrap_or_else(|| Commit {
+        core: commit.core.clone(),
+        sigs: SmallVec::new(),
+    });
+    let before = merged.sigs.len();
+    for sig in &commit.sigs {
+        if merged.sigs.contains(sig) {
+            continue;
+        }
+        sig.verify(&id).map_err(|error| StoreError::Signature {
+            signer: sig.signer,
+            error,
+        })?;
+        merged.sigs.push(sig.clone());
+    }
+    if exists && merged.sigs.len() == before {
+        return Ok(id);
+    }
+    let bytes = encode_commit(&merged).map_err(|_| StoreError::Invariant("commit serializes"))?;
+    store.put_object(&commit_object_id(&id), &bytes)?;
+    Ok(id)
+}
+
+/// Load the commit with content id `id`.
+///
+/// Returns `Ok(None)` if no object is stored under `id`.
+///
+/// # Errors
+/// Returns [`StoreError::Corruption`] if the stored bytes are not a canonical
+/// commit (`actual` is the hash of the bytes) or the decoded core does not
+/// hash back to `id` (`actual` is the core's content id), or any error
+/// reported by the backend.
+pub fn get_commit<S: ObjectStore + ?Sized>(
+    store: &S,
+    id: &Hash,
+) -> Result<Option<Commit>, StoreError> {
+    let Some(bytes) = store.get_object(&commit_object_id(id))? else {
+        return Ok(None);
+    };
+    let (commit, content_id) =
+        decode_commit_object(&bytes).map_err(|_| StoreError::corruption(id, &bytes))?;
+    if &content_id != id {
+        return Err(StoreError::Corruption {
+            expected: *id,
+            actual: content_id,
+        });
+    }
+    Ok(Some(commit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MapStore;
+    use crate::CommitCore;
+    use smallvec::smallvec;
+    use std::string::ToString;
+
+    fn signed_commit() -> Commit {
+        let mut commit = Commit {
+            core: CommitCore {
+                parent: None,
+                tree: [0x22; 32],
+                message: "init".to_string(),
+                timestamp: 1_725_000_000,
+            },
+            sigs: SmallVec::new(),
+        };
+        commit.sign(&[0x0A; 32]).unwrap();
+        commit
+    }
+
+    #[test]
+    fn commit_roundtrip_and_store() {
+        let commit = signed_commit();
+        let bytes = encode_commit(&commit).unwrap();
+        assert_eq!(decode_commit(&bytes).unwrap(), commit);
+
+        let mut store = MapStore::default();
+        let id = put_commit(&mut store, &commit).unwrap();
+        assert_eq!(id, compute_content_id(&commit.core).unwrap());
+        assert_eq!(get_commit(&store, &id).unwrap(), Some(commit));
+        assert_eq!(get_commit(&store, &[0u8; 32]).unwrap(), None);
+    }
+
+    #[test]
+    fn decode_rejects_trailing_bytes() {
+        let mut bytes = encode_commit(&signed_commit()).unwrap();
+        bytes.push(0);
+        assert!(decode_commit(&bytes).is_err());
+    }
+
+    #[test]
+    fn get_commit_detects_wrong_id() {
+        let commit = signed_commit();
+        let bytes = encode_commit(&commit).unwrap();
+        let bogus = [0x77; 32];
+        let mut store = MapStore::default();
+        // Bypass the invariant to simulate a tampered backend.
+        store.0.insert(commit_object_id(&bogus), bytes);
+        assert_eq!(
+            get_commit(&store, &bogus),
+            Err(StoreError::Corruption {
+                expected: bogus,
+                actual: compute_content_id(&commit.core).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn put_commit_keeps_stored_signatures() {
+        let signed = signed_commit();
+        let unsigned = Commit {
+            core: signed.core.clone(),
+            sigs: SmallVec::new(),
+        };
+        let mut store = MapStore::default();
+        let id = put_commit(&mut store, &signed).unwrap();
+        assert_eq!(put_commit(&mut store, &unsigned).unwrap(), id);
+        assert_eq!(get_commit(&store, &id).unwrap(), Some(signed.clone()));
+
+        let mut cosigned = unsigned.clone();
+        cosigned.sign(&[0x0C; 32]).unwrap();
+        put_commit(&mut store, &cosigned).unwrap();
+        let stored = get_commit(&store, &id).unwrap().unwrap();
+        assert_eq!(
+            stored.sigs[..],
+            [signed.sigs[0].clone(), cosigned.sigs[0].clone()]
+        );
+
+        // Signatures that do not verify are refused, not merged.
+        let mut forged = unsigned;
+        forged.sigs.push(Signature {
+            signer: [0xCC; 32],
+            sig: [0xDD; 64],
+        });
+        assert!(matches!(
+            put_commit(&mut store, &forged),
+            Err(StoreError::Signature { signer, .. }) if signer == [0xCC; 32]
+        ));
+        assert_eq!(get_commit(&store, &id).unwrap(), Some(stored));
+    }
+
+    #[test]
+    fn put_commit_keeps_corrupt_objects_as_evidence() {
+        let commit = signed_commit();
+        let id = compute_content_id(&commit.core).unwrap();
+        let mut store = MapStore::default();
+        // Bypass the invariant to simulate a tampered backend.
+        store.0.insert(commit_object_id(&id), b"tampered".to_vec());
+        assert_eq!(
+            put_commit(&mut store, &commit),
+            Err(StoreError::corruption(&id, b"tampered"))
+        );
+        assert_eq!(store.0[&commit_object_id(&id)], b"tampered");
+    }
+
+    #[test]
+    fn commit_address_rejects_plain_objects() {
+        let commit = signed_commit();
+        let core = encode_to_vec(&commit.core, config::standard()).unwrap();
+        let mut store = MapStore::default();
+        let id = put_commit(&mut store, &commit).unwrap();
+        assert_eq!(blake3::hash(&core).as_bytes(), &id);
+
+        // The bare core is a plain object under the content id, which is not
+        // where the commit lives.
+        store.put_object(&id, &core).unwrap();
+        assert_eq!(get_commit(&store, &id).unwrap(), Some(commit));
+        let address = commit_object_id(&id);
+        assert!(!object_id_matches(&address, &core));
+        assert_eq!(
+            store.put_object(&address, &core),
+            Err(StoreError::corruption(&address, &core))
+        );
+    }
+
+    #[test]
+    fn oversized_length_prefixes_are_rejected() {
+        let core = VersionedCommitCore::from(signed_commit().core)
+            .encode()
+            .unwrap();
+        for count in [u64::MAX / 2, 1 << 40] {
+            // varint(count) as a 0xfd-tagged u64, then one byte of "data".
+            let mut object = core.clone();
+            object.push(0xfd);
+            object.extend_from_slice(&count.to_le_bytes());
+            object.push(0);
+            assert!(decode_commit(&object).is_err());
+            assert!(!object_id_matches(&[0; 32], &object));
+        }
+    }
+
+    #[test]
+    fn reads_version_1_commit_objects() {
+        let commit = Commit {
+            sigs: smallvec![Signature {
+                signer: [0xAA; 32],
+                sig: [0xBB; 64],
+            }],
+            ..signed_commit()
+        };
+        // varint(1) || v1 core || one signature; frozen, never re-derived.
+        let v1_object = [
+            &[0x01, 0x00][..],
+            &[0x22; 32],
+            &[0x04],
+            b"init",
+            &[0xfc, 0x40, 0x69, 0xd1, 0x66],
+            &[0x01],
+            &[0xAA; 32],
+            &[0xBB; 64],
+        ]
+        .concat();
+        assert_eq!(decode_commit(&v1_object).unwrap(), commit);
+        if crate::CURRENT_COMMIT_CORE_VERSION == 1 {
+            assert_eq!(encode_commit(&commit).unwrap(), v1_object);
+        }
+
+        let id = compute_content_id(&commit.core).unwrap();
+        let mut store = MapStore::default();
+        store
+            .put_object(&commit_object_id(&id), &v1_object)
+            .unwrap();
+        assert_eq!(get_commit(&store, &id).unwrap(), Some(commit));
+
+        let mut unknown = v1_object;
+        unknown[0] = 0x02;
+        assert!(matches!(
+            decode_commit(&unknown),
+            Err(DecodeError::UnexpectedVariant { found: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,508 @@
+//! Deterministic DAG-CBOR encoding (SPEC §4.1).
+//!
+//! Implements the IPLD DAG-CBOR profile of RFC 8949 deterministic encoding:
+//!
+//! - integers and lengths use the shortest header form;
+//! - no indefinite-length items;
+//! - map keys are text strings, sorted by their encoded bytes (length first,
+//!   then bytewise), without duplicates;
+//! - floats are always 64-bit and never NaN or infinite;
+//! - the only tag is 42 (CID link), whose content is a byte string holding a
+//!   `0x00` multibase-identity prefix followed by the binary CID;
+//! - simple values are limited to `false`, `true` and `null`.
+//!
+//! [`decode`] rejects any input that [`encode`] would not produce, so a value
+//! has exactly one accepted byte form and `Event-CID`s are reproducible from
+//! any language.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::cid::Cid;
+
+/// Maximum nesting depth accepted by [`decode`].
+pub const MAX_DEPTH: usize = 64;
+
+const TAG_CID: u64 = 42;
+
+/// A DAG-CBOR data model value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Integer in `-2^64 ..= 2^64 - 1` (CBOR major types 0 and 1).
+    Integer(i128),
+    Float(f64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    /// Map with text keys. `BTreeMap` order is irrelevant; the encoder sorts
+    /// keys canonically.
+    Map(BTreeMap<String, Value>),
+    Link(Cid),
+}
+
+/// Errors produced by the DAG-CBOR encoder and decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CborError {
+    /// Input ended in the middle of an item.
+    Eof,
+    /// Bytes remain after the top-level item.
+    TrailingBytes,
+    /// Well-formed CBOR that is not in canonical DAG-CBOR form.
+    NonCanonical(&'static str),
+    /// CBOR features outside the DAG-CBOR data model.
+    Unsupported(&'static str),
+    /// Text string is not valid UTF-8.
+    InvalidUtf8,
+    /// Integer outside the CBOR range, or a NaN/infinite float.
+    OutOfRange,
+    /// Nesting deeper than [`MAX_DEPTH`].
+    TooDeep,
+}
+
+/// Encode `value` as canonical DAG-CBOR.
+///
+/// # Errors
+/// Returns [`CborError::OutOfRange`] for integers outside the CBOR range and
+/// for NaN or infinite floats.
+pub fn encode(value: &Value) -> Result<Vec<u8>, CborError> {
+    let mut out = Vec::new();
+    encode_into(value, &mut out)?;
+    Ok(out)
+}
+
+fn header(out: &mut Vec<u8>, major: u8, n: u64) {
+    let m = major << 5;
+    if n < 24 {
+        out.push(m | n as u8);
+    } else if let Ok(n) = u8::try_from(n) {
+        out.extend_from_slice(&[m | 24, n]);
+    } else if let Ok(n) = u16::try_from(n) {
+        out.push(m | 25);
+        out.extend_from_slice(&n.to_be_bytes());
+    } else if let Ok(n) = u32::try_from(n) {
+        out.push(m | 26);
+        out.extend_from_slice(&n.to_be_bytes());
+    } else {
+        out.push(m | 27);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+/// Canonical key order: length first, then bytewise (equivalent to ordering
+/// by encoded bytes for text keys).
+fn key_cmp(a: &str, b: &str) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.as_bytes().cmp(b.as_bytes()))
+}
+
+fn encode_into(value: &Value, out: &mut Vec<u8>) -> Result<(), CborError> {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Integer(i) => {
+            if let Ok(u) = u64::try_from(*i) {
+                header(out, 0, u);
+            } else {
+                let n = u64::try_from(-1 - *i).map_err(|_| CborError::OutOfRange)?;
+                header(out, 1, n);
+            }
+        }
+        Value::Float(f) => {
+            if !f.is_finite() {
+                return Err(CborError::OutOfRange);
+            }
+            out.push(0xfb);
+            out.extend_from_slice(&f.to_bits().to_be_bytes());
+        }
+        Value::Bytes(b) => {
+            header(out, 2, b.len() as u64);
+            out.extend_from_slice(b);
+        }
+        Value::Text(s) => {
+            header(out, 3, s.len() as u64);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            header(out, 4, items.len() as u64);
+            for item in items {
+                encode_into(item, out)?;
+            }
+        }
+        Value::Map(map) => {
+            header(out, 5, map.len() as u64);
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| key_cmp(a.0, b.0));
+            for (k, v) in entries {
+                header(out, 3, k.len() as u64);
+                out.extend_from_slice(k.as_bytes());
+                encode_into(v, out)?;
+            }
+        }
+        Value::Link(cid) => {
+            header(out, 6, TAG_CID);
+            let bytes = cid.to_bytes();
+            header(out, 2, bytes.len() as u64 + 1);
+            out.push(0x00);
+            out.extend_from_slice(&bytes);
+        }
+    }
+    Ok(())
+}
+
+/// Decode canonical DAG-CBOR bytes holding exactly one value.
+///
+/// # Errors
+/// Returns a [`CborError`] if `bytes` is truncated, has trailing data, uses
+/// features outside DAG-CBOR, or is not in canonical form.
+pub fn decode(bytes: &[u8]) -> Result<Value, CborError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let value = reader.value(0)?;
+    if reader.pos != bytes.len() {
+        return Err(CborError::TrailingBytes);
+    }
+    Ok(value)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CborError> {
+        let end = self.pos.checked_add(n).ok_or(CborError::Eof)?;
+        let slice = self.bytes.get(self.pos..end).ok_or(CborError::Eof)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, CborError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read an item header, returning `(major, argument)` and enforcing the
+    /// shortest encoding of the argument.
+    fn header(&mut self) -> Result<(u8, u64), CborError> {
+        let initial = self.byte()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        if major == 7 {
+            // Simple values and floats are handled by the caller.
+            return Ok((major, u64::from(info)));
+        }
+        let (n, min) = match info {
+            0..=23 => return Ok((major, u64::from(info))),
+            24 => (u64::from(self.byte()?), 24),
+            25 => (u64::from(u16::from_be_bytes(self.array()?)), 0x100),
+            26 => (u64::from(u32::from_be_bytes(self.array()?)), 0x1_0000),
+            27 => (u64::from_be_bytes(self.array()?), 0x1_0000_0000),
+            31 => return Err(CborError::NonCanonical("indefinite length")),
+            _ => return Err(CborError::Unsupported("reserved additional info")),
+        };
+        if n < min {
+            return Err(CborError::NonCanonical("non-minimal integer"));
+        }
+        Ok((major, n))
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CborError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn len(&mut self, n: u64) -> Result<usize, CborError> {
+        // Any length beyond the remaining input is necessarily truncated.
+        usize::try_from(n)
+            .ok()
+            .filter(|&n| n <= self.bytes.len() - self.pos)
+            .ok_or(CborError::Eof)
+    }
+
+    fn text(&mut self, n: u64) -> Result<String, CborError> {
+        let n = self.len(n)?;
+        let raw = self.take(n)?;
+        core::str::from_utf8(raw)
+            .map(String::from)
+            .map_err(|_| CborError::InvalidUtf8)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, CborError> {
+        if depth > MAX_DEPTH {
+            return Err(CborError::TooDeep);
+        }
+        let (major, n) = self.header()?;
+        Ok(match major {
+            0 => Value::Integer(i128::from(n)),
+            1 => Value::Integer(-1 - i128::from(n)),
+            2 => {
+                let n = self.len(n)?;
+                Value::Bytes(self.take(n)?.to_vec())
+            }
+            3 => Value::Text(self.text(n)?),
+            4 => {
+                let n = self.len(n)?;
+                let mut items = Vec::with_capacity(n);
+                for _ in 0..n {
+                    items.push(self.value(depth + 1)?);
+                }
+                Value::Array(items)
+            }
+            5 => {
+                let n = self.len(n)?;
+                let mut map = BTreeMap::new();
+                let mut prev: Option<String> = None;
+                for _ in 0..n {
+                    let (kmajor, klen) = self.header()?;
+                    if kmajor != 3 {
+                        return Err(CborError::Unsupported("non-text map key"));
+                    }
+                    let key = self.text(klen)?;
+                    if let Some(p) = &prev {
+                        match key_cmp(p, &key) {
+                            Ordering::Less => {}
+                            Ordering::Equal => {
+                                return Err(CborError::NonCanonical("duplicate map key"))
+                            }
+                            Ordering::Greater => {
+                                return Err(CborError::NonCanonical("unsorted map keys"))
+                            }
+                        }
+                    }
+                    let v = self.value(depth + 1)?;
+                    prev = Some(key.clone());
+                    map.insert(key, v);
+                }
+                Value::Map(map)
+            }
+            6 => {
+                if n != TAG_CID {
+                    return Err(CborError::Unsupported("tag other than 42"));
+                }
+                let (bmajor, blen) = self.header()?;
+                if bmajor != 2 {
+                    return Err(CborError::Unsupported("CID link is not a byte string"));
+                }
+                let blen = self.len(blen)?;
+                let raw = self.take(blen)?;
+                let Some((&0x00, cid)) = raw.split_first() else {
+                    return Err(CborError::Unsupported("CID link without identity prefix"));
+                };
+                Value::Link(Cid::from_bytes(cid).map_err(|_| CborError::Unsupported("bad CID"))?)
+            }
+            _ => match n {
+                20 => Value::Bool(false),
+                21 => Value::Bool(true),
+                22 => Value::Null,
+                27 => {
+                    let f = f64::from_bits(u64::from_be_bytes(self.array()?));
+                    if !f.is_finite() {
+                        return Err(CborError::OutOfRange);
+                    }
+                    Value::Float(f)
+                }
+                25 | 26 => return Err(CborError::NonCanonical("float narrower than 64 bits")),
+                31 => return Err(CborError::NonCanonical("indefinite length")),
+                _ => return Err(CborError::Unsupported("simple value")),
+            },
+        })
+    }
+}
+
+/// `Event-CID = cidv1(dag-cbor, blake3(canonical_bytes))` for an envelope
+/// already encoded with [`encode`].
+#[must_use]
+pub fn event_cid(canonical_bytes: &[u8]) -> Cid {
+    Cid::dag_cbor_blake3(canonical_bytes)
+}
+
+/// Encode `value` canonically and return its bytes together with its
+/// `Event-CID`.
+///
+/// # Errors
+/// See [`encode`].
+pub fn encode_with_cid(value: &Value) -> Result<(Vec<u8>, Cid), CborError> {
+    let bytes = encode(value)?;
+    let cid = event_cid(&bytes);
+    Ok((bytes, cid))
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::Text(String::from(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Self::Integer(i128::from(i))
+    }
+}
+
+impl From<u64> for Value {
+    fn from(u: u64) -> Self {
+        Self::Integer(i128::from(u))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Self::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use std::string::ToString;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| std::format!("{b:02x}")).collect()
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn map(entries: &[(&str, Value)]) -> Value {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn rfc8949_appendix_a_examples() {
+        let cases: &[(Value, &str)] = &[
+            (Value::Integer(0), "00"),
+            (Value::Integer(23), "17"),
+            (Value::Integer(24), "1818"),
+            (Value::Integer(1000), "1903e8"),
+            (Value::Integer(1_000_000_000_000), "1b000000e8d4a51000"),
+            (Value::Integer(u64::MAX.into()), "1bffffffffffffffff"),
+            (Value::Integer(-(1i128 << 64)), "3bffffffffffffffff"),
+            (Value::Integer(-1000), "3903e7"),
+            (Value::Float(1.1), "fb3ff199999999999a"),
+            (Value::Bool(true), "f5"),
+            (Value::Null, "f6"),
+            (Value::Bytes(vec![1, 2, 3, 4]), "4401020304"),
+            (Value::from("\u{00fc}"), "62c3bc"),
+            (Value::from(vec![1i64, 2, 3]), "83010203"),
+        ];
+        for (value, expected) in cases {
+            let bytes = encode(value).unwrap();
+            assert_eq!(hex(&bytes), *expected, "{value:?}");
+            assert_eq!(&decode(&bytes).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn map_keys_sort_length_first() {
+        let v = map(&[("bb", 1i64.into()), ("a", 2i64.into()), ("c", 3i64.into())]);
+        assert_eq!(hex(&encode(&v).unwrap()), "a361610261630362626201");
+    }
+
+    #[test]
+    fn rejects_non_canonical_input() {
+        let cases: &[(&str, CborError)] = &[
+            ("1817", CborError::NonCanonical("non-minimal integer")),
+            ("9f01ff", CborError::NonCanonical("indefinite length")),
+            (
+                "a2616202616101",
+                CborError::NonCanonical("unsorted map keys"),
+            ),
+            (
+                "a2616101616102",
+                CborError::NonCanonical("duplicate map key"),
+            ),
+            ("a10102", CborError::Unsupported("non-text map key")),
+            (
+                "f93c00",
+                CborError::NonCanonical("float narrower than 64 bits"),
+            ),
+            ("fb7ff8000000000000", CborError::OutOfRange),
+            ("c11a514b67b0", CborError::Unsupported("tag other than 42")),
+            ("f7", CborError::Unsupported("simple value")),
+            ("0000", CborError::TrailingBytes),
+            ("62c3", CborError::Eof),
+            ("61ff", CborError::InvalidUtf8),
+        ];
+        for (input, err) in cases {
+            assert_eq!(decode(&unhex(input)), Err(*err), "{input}");
+        }
+    }
+
+    #[test]
+    fn cid_links_roundtrip() {
+        let link = Value::Link(Cid::dag_cbor_blake3(b"parent"));
+        let bytes = encode(&link).unwrap();
+        assert_eq!(hex(&bytes[..5]), "d82a582500");
+        assert_eq!(decode(&bytes).unwrap(), link);
+    }
+
+    // Golden vectors from ADR-0001 "Canonicalization Test Vectors".
+    #[test]
+    fn adr_0001_event_cid_vectors() {
+        let empty = encode(&Value::Map(BTreeMap::new())).unwrap();
+        assert_eq!(hex(&empty), "a0");
+        assert_eq!(
+            event_cid(&empty).to_string(),
+            "bafyr4ia7stf7ge5tzyrsk6tskhva7sk2erkw5jqr4t4pi5pfjglrxlw3ai"
+        );
+
+        let policy_root = std::format!("sha256:{}", "0".repeat(64));
+        let envelope = map(&[
+            ("type", "event.append".into()),
+            ("ulid", "01HF4Z5Q3C7D6XJ9K8W2M1N0PR".into()),
+            ("actor", "user:alice".into()),
+            ("payload", map(&[("n", 1i64.into())])),
+            ("policy_root", policy_root.into()),
+        ]);
+        let (bytes, cid) = encode_with_cid(&envelope).unwrap();
+        assert_eq!(
+            hex(&bytes),
+            concat!(
+                "a564747970656c6576656e742e617070656e6464756c6964781a30314846345a35513343",
+                "374436584a394b3857324d314e305052656163746f726a757365723a616c696365677061",
+                "796c6f6164a1616e016b706f6c6963795f726f6f7478477368613235363a303030303030",
+                "303030303030303030303030303030303030303030303030303030303030303030303030",
+                "30303030303030303030303030303030303030303030",
+            )
+        );
+        assert_eq!(
+            cid.to_string(),
+            "bafyr4idw3cmelju74jwjodidk57kaquj7xie2tpwiv2npge5obcbia45tm"
+        );
+        assert_eq!(decode(&bytes).unwrap(), envelope);
+    }
+
+    #[test]
+    fn depth_limit() {
+        let mut bytes = vec![0x81; MAX_DEPTH + 1];
+        bytes.push(0x00);
+        assert_eq!(decode(&bytes), Err(CborError::TooDeep));
+    }
+}
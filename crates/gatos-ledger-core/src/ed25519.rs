@@ -0,0 +1,181 @@
+//! Ed25519 (RFC 8032) signing and verification over commit content ids.
+//!
+//! Signatures are detached attestations over the 32-byte content id returned
+//! by [`compute_content_id`]; they never feed back into the id itself
+//! (ADR-0001). Verification uses the strict RFC 8032 checks, rejecting
+//! small-order keys and malleable signatures.
+
+use alloc::vec::Vec;
+
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+
+use crate::{compute_content_id, Commit, Hash, PubKey, Signature};
+
+/// Ed25519 secret key seed (RFC 8032 §5.1.5 private key).
+pub type SecretKey = [u8; 32];
+
+/// Reasons an Ed25519 signature fails verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The signer bytes are not a valid Ed25519 public key.
+    MalformedKey,
+    /// The signature does not verify under the signer key.
+    BadSignature,
+}
+
+/// Derive the Ed25519 public key for `secret`.
+#[must_use]
+pub fn ed25519_public_key(secret: &SecretKey) -> PubKey {
+    SigningKey::from_bytes(secret).verifying_key().to_bytes()
+}
+
+/// Sign arbitrary `message` bytes with `secret`.
+#[must_use]
+pub fn ed25519_sign(secret: &SecretKey, message: &[u8]) -> [u8; 64] {
+    SigningKey::from_bytes(secret).sign(message).to_bytes()
+}
+
+/// Verify `sig` over `message` under public key `signer`.
+///
+/// # Errors
+/// Returns [`VerifyError::MalformedKey`] if `signer` is not a valid point, or
+/// [`VerifyError::BadSignature`] if the signature does not verify.
+pub fn ed25519_verify(signer: &PubKey, message: &[u8], sig: &[u8; 64]) -> Result<(), VerifyError> {
+    let key = VerifyingKey::from_bytes(signer).map_err(|_| VerifyError::MalformedKey)?;
+    let sig = ed25519_dalek::Signature::from_bytes(sig);
+    key.verify_strict(message, &sig)
+        .map_err(|_| VerifyError::BadSignature)
+}
+
+impl Signature {
+    /// Sign a commit content id with `secret`.
+    #[must_use]
+    pub fn sign(secret: &SecretKey, content_id: &Hash) -> Self {
+        Self {
+            signer: ed25519_public_key(secret),
+            sig: ed25519_sign(secret, content_id),
+        }
+    }
+
+    /// Verify this signature over `content_id`.
+    ///
+    /// # Errors
+    /// See [`ed25519_verify`].
+    pub fn verify(&self, content_id: &Hash) -> Result<(), VerifyError> {
+        ed25519_verify(&self.signer, content_id, &self.sig)
+    }
+}
+
+/// Outcome of checking every signature attached to a commit.
+///
+/// Signers are listed in the order their signatures appear in
+/// [`Commit::sigs`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignatureReport {
+    /// Signers whose signature verifies over the content id.
+    pub valid: Vec<PubKey>,
+    /// Signers whose signature (or key) failed verification.
+    pub invalid: Vec<(PubKey, VerifyError)>,
+}
+
+impl SignatureReport {
+    /// Returns `true` when at least one signature is present and all verify.
+    #[must_use]
+    pub fn all_valid(&self) -> bool {
+        !self.valid.is_empty() && self.invalid.is_empty()
+    }
+}
+
+impl Commit {
+    /// Sign this commit's content id with `secret` and attach the signature.
+    ///
+    /// # Errors
+    /// Returns an error if the core cannot be serialized.
+    pub fn sign(&mut self, secret: &SecretKey) -> Result<(), bincode::error::EncodeError> {
+        let id = compute_content_id(&self.core)?;
+        self.sigs.push(Signature::sign(secret, &id));
+        Ok(())
+    }
+
+    /// Verify every attached signature against this commit's content id.
+    ///
+    /// # Errors
+    /// Returns an error if the core cannot be serialized.
+    pub fn verify_signatures(&self) -> Result<SignatureReport, bincode::error::EncodeError> {
+        let id = compute_content_id(&self.core)?;
+        let mut report = SignatureReport::default();
+        for sig in &self.sigs {
+            match sig.verify(&id) {
+                Ok(()) => report.valid.push(sig.signer),
+                Err(e) => report.invalid.push((sig.signer, e)),
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommitCore;
+    use smallvec::SmallVec;
+    use std::string::ToString;
+
+    // RFC 8032 §7.1, TEST 1 (empty message).
+    const RFC_SECRET: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const RFC_PUBLIC: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const RFC_SIG: &str = concat!(
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555f",
+        "b8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+    );
+
+    fn unhex<const N: usize>(s: &str) -> [u8; N] {
+        let mut out = [0u8; N];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn rfc8032_test_vector_1() {
+        let secret: SecretKey = unhex(RFC_SECRET);
+        let public: PubKey = unhex(RFC_PUBLIC);
+        let sig: [u8; 64] = unhex(RFC_SIG);
+        assert_eq!(ed25519_public_key(&secret), public);
+        assert_eq!(ed25519_sign(&secret, b""), sig);
+        assert_eq!(ed25519_verify(&public, b"", &sig), Ok(()));
+        assert_eq!(
+            ed25519_verify(&public, b"x", &sig),
+            Err(VerifyError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn commit_signatures_report_valid_and_invalid_signers() {
+        let mut commit = Commit {
+            core: CommitCore {
+                parent: None,
+                tree: [0x22; 32],
+                message: "signed".to_string(),
+                timestamp: 1_725_000_000,
+            },
+            sigs: SmallVec::new(),
+        };
+        commit.sign(&[1; 32]).unwrap();
+        commit.sign(&[2; 32]).unwrap();
+        // Valid signature, but over a different content id.
+        commit.sigs.push(Signature::sign(&[3; 32], &[0u8; 32]));
+
+        let report = commit.verify_signatures().unwrap();
+        assert_eq!(
+            report.valid,
+            [ed25519_public_key(&[1; 32]), ed25519_public_key(&[2; 32])]
+        );
+        assert_eq!(
+            report.invalid,
+            [(ed25519_public_key(&[3; 32]), VerifyError::BadSignature)]
+        );
+        assert!(!report.all_valid());
+    }
+}
@@ -0,0 +1,473 @@
+//! Typed event envelope (SPEC §4.1).
+//!
+//! The canonical bytes of an envelope are its DAG-CBOR encoding with `sig`
+//! omitted and optional fields (`caps`, `sig_alg`, `ts`) present only when
+//! set. Those bytes are what gets signed and what `Event-CID` addresses.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::cid::Cid;
+use crate::dag_cbor::{self, CborError, Value};
+use crate::ulid::Ulid;
+use crate::{ed25519_public_key, ed25519_sign, ed25519_verify, SecretKey, VerifyError};
+
+/// Signature algorithm identifiers accepted in `sig_alg`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigAlg {
+    /// RFC 8032. Required for v1 verifiers.
+    Ed25519,
+    /// SEC 1 / RFC 6979 / FIPS 186-4. Recognized, but only usable when
+    /// enabled by policy; the core does not verify it.
+    EcdsaP256,
+}
+
+impl SigAlg {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ed25519",
+            Self::EcdsaP256 => "ecdsa-p256",
+        }
+    }
+
+    /// Infer the algorithm from public key material.
+    ///
+    /// Ed25519 keys are 32 bytes; P-256 keys are SEC 1 points (33-byte
+    /// compressed with a `0x02`/`0x03` prefix, or 65-byte uncompressed with a
+    /// `0x04` prefix).
+    #[must_use]
+    pub fn infer(signer: &[u8]) -> Option<Self> {
+        match (signer.len(), signer.first()) {
+            (32, _) => Some(Self::Ed25519),
+            (33, Some(0x02 | 0x03)) | (65, Some(0x04)) => Some(Self::EcdsaP256),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SigAlg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SigAlg {
+    type Err = EnvelopeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ed25519" => Ok(Self::Ed25519),
+            "ecdsa-p256" => Ok(Self::EcdsaP256),
+            other => Err(EnvelopeError::UnknownAlgorithm(other.to_string())),
+        }
+    }
+}
+
+/// Errors produced while decoding, signing or verifying an envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// Canonical DAG-CBOR encoding or decoding failed.
+    Cbor(CborError),
+    /// A field is missing, has the wrong type, or is not allowed.
+    Field(&'static str),
+    /// `sig_alg` names an algorithm outside the SPEC §4.1 table.
+    UnknownAlgorithm(String),
+    /// The envelope carries no signature.
+    MissingSignature,
+    /// The algorithm cannot be inferred from the signer key material.
+    UnrecognizedKey,
+    /// Declared `sig_alg` disagrees with the algorithm inferred from the key.
+    AlgorithmMismatch { declared: SigAlg, inferred: SigAlg },
+    /// A recognized algorithm that the core cannot verify or sign with.
+    UnsupportedAlgorithm(SigAlg),
+    /// The signature did not verify.
+    Signature(VerifyError),
+}
+
+impl From<CborError> for EnvelopeError {
+    fn from(e: CborError) -> Self {
+        Self::Cbor(e)
+    }
+}
+
+/// Detached envelope signature. Carried in commit trailers (`Sig`), never in
+/// the canonical bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeSig {
+    /// Signer public key material; its shape determines the algorithm when
+    /// `sig_alg` is absent.
+    pub signer: Vec<u8>,
+    pub sig: Vec<u8>,
+}
+
+/// A GATOS event envelope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventEnvelope {
+    /// Event type, e.g. `event.append` (wire name `type`).
+    pub event_type: String,
+    /// Client-supplied idempotency key.
+    pub ulid: Ulid,
+    /// `user:<name>`, `agent:<name>` or `service:<name>`.
+    pub actor: String,
+    pub caps: Option<Vec<String>>,
+    pub payload: BTreeMap<String, Value>,
+    /// Policy commit governing evaluation.
+    pub policy_root: String,
+    pub sig_alg: Option<SigAlg>,
+    /// RFC 3339 UTC timestamp (`YYYY-MM-DDTHH:MM:SSZ`, see [`rfc3339_utc_seconds`]).
+    pub ts: Option<String>,
+    pub sig: Option<EnvelopeSig>,
+}
+
+impl EventEnvelope {
+    /// The envelope as a DAG-CBOR map, without `sig`.
+    #[must_use]
+    pub fn to_value(&self) -> Value {
+        let mut m = BTreeMap::new();
+        m.insert("type".to_string(), Value::from(self.event_type.as_str()));
+        m.insert("ulid".to_string(), Value::Text(self.ulid.to_string()));
+        m.insert("actor".to_string(), Value::from(self.actor.as_str()));
+        if let Some(caps) = &self.caps {
+            m.insert("caps".to_string(), Value::from(caps.clone()));
+        }
+        m.insert("payload".to_string(), Value::Map(self.payload.clone()));
+        m.insert(
+            "policy_root".to_string(),
+            Value::from(self.policy_root.as_str()),
+        );
+        if let Some(alg) = self.sig_alg {
+            m.insert("sig_alg".to_string(), Value::from(alg.as_str()));
+        }
+        if let Some(ts) = &self.ts {
+            m.insert("ts".to_string(), Value::from(ts.as_str()));
+        }
+        Value::Map(m)
+    }
+
+    /// Parse an envelope from a DAG-CBOR map. Unknown fields are rejected so
+    /// that re-encoding reproduces the same canonical bytes.
+    ///
+    /// # Errors
+    /// Returns [`EnvelopeError::Field`] for missing, mistyped or unknown
+    /// fields and [`EnvelopeError::UnknownAlgorithm`] for unknown `sig_alg`.
+    pub fn from_value(value: Value) -> Result<Self, EnvelopeError> {
+        let Value::Map(mut m) = value else {
+            return Err(EnvelopeError::Field("envelope"));
+        };
+        let mut text = |key: &'static str| match m.remove(key) {
+            Some(Value::Text(s)) => Ok(Some(s)),
+            None => Ok(None),
+            Some(_) => Err(EnvelopeError::Field(key)),
+        };
+        let event_type = text("type")?.ok_or(EnvelopeError::Field("type"))?;
+        let ulid = text("ulid")?
+            .and_then(|s| s.parse::<Ulid>().ok())
+            .ok_or(EnvelopeError::Field("ulid"))?;
+        let actor = text("actor")?.ok_or(EnvelopeError::Field("actor"))?;
+        let policy_root = text("policy_root")?.ok_or(EnvelopeError::Field("policy_root"))?;
+        let sig_alg = text("sig_alg")?.map(|s| s.parse()).transpose()?;
+        let ts = text("ts")?;
+        if ts
+            .as_deref()
+            .is_some_and(|ts| rfc3339_utc_seconds(ts).is_none())
+        {
+            return Err(EnvelopeError::Field("ts"));
+        }
+        let caps = match m.remove("caps") {
+            None => None,
+            Some(Value::Array(items)) => Some(
+                items
+                    .into_iter()
+                    .map(|v| match v {
+                        Value::Text(s) => Ok(s),
+                        _ => Err(EnvelopeError::Field("caps")),
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            Some(_) => return Err(EnvelopeError::Field("caps")),
+        };
+        let Some(Value::Map(payload)) = m.remove("payload") else {
+            return Err(EnvelopeError::Field("payload"));
+        };
+        if !m.is_empty() {
+            return Err(EnvelopeError::Field("unknown field"));
+        }
+        Ok(Self {
+            event_type,
+            ulid,
+            actor,
+            caps,
+            payload,
+            policy_root,
+            sig_alg,
+            ts,
+            sig: None,
+        })
+    }
+
+    /// Canonical DAG-CBOR bytes with `sig` omitted.
+    ///
+    /// # Errors
+    /// Returns an error if the payload holds values outside the DAG-CBOR
+    /// range (see [`dag_cbor::encode`]).
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>, CborError> {
+        dag_cbor::encode(&self.to_value())
+    }
+
+    /// Decode an envelope from canonical bytes. The result has no `sig`.
+    ///
+    /// # Errors
+    /// Returns an error if the bytes are not canonical DAG-CBOR or do not
+    /// describe a valid envelope.
+    pub fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        Self::from_value(dag_cbor::decode(bytes)?)
+    }
+
+    /// `Event-CID = cidv1(dag-cbor, blake3(canonical_bytes))`.
+    ///
+    /// # Errors
+    /// See [`EventEnvelope::canonical_bytes`].
+    pub fn event_cid(&self) -> Result<Cid, CborError> {
+        Ok(dag_cbor::event_cid(&self.canonical_bytes()?))
+    }
+
+    /// Sign the canonical bytes with Ed25519 and attach the signature.
+    ///
+    /// # Errors
+    /// Returns [`EnvelopeError::AlgorithmMismatch`] if `sig_alg` declares an
+    /// algorithm other than Ed25519, or a CBOR error if encoding fails.
+    pub fn sign_ed25519(&mut self, secret: &SecretKey) -> Result<(), EnvelopeError> {
+        if let Some(declared) = self.sig_alg.filter(|a| *a != SigAlg::Ed25519) {
+            return Err(EnvelopeError::AlgorithmMismatch {
+                declared,
+                inferred: SigAlg::Ed25519,
+            });
+        }
+        let bytes = self.canonical_bytes()?;
+        self.sig = Some(EnvelopeSig {
+            signer: ed25519_public_key(secret).to_vec(),
+            sig: ed25519_sign(secret, &bytes).to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Verify the attached signature over the canonical bytes and return the
+    /// algorithm used.
+    ///
+    /// The algorithm is inferred from the signer key material. When `sig_alg`
+    /// is present it must agree with the inferred algorithm.
+    ///
+    /// # Errors
+    /// Returns [`EnvelopeError::MissingSignature`],
+    /// [`EnvelopeError::UnrecognizedKey`], [`EnvelopeError::AlgorithmMismatch`],
+    /// [`EnvelopeError::UnsupportedAlgorithm`] or [`EnvelopeError::Signature`].
+    pub fn verify(&self) -> Result<SigAlg, EnvelopeError> {
+        let sig = self.sig.as_ref().ok_or(EnvelopeError::MissingSignature)?;
+        let inferred = SigAlg::infer(&sig.signer).ok_or(EnvelopeError::UnrecognizedKey)?;
+        if let Some(declared) = self.sig_alg.filter(|d| *d != inferred) {
+            return Err(EnvelopeError::AlgorithmMismatch { declared, inferred });
+        }
+        match inferred {
+            SigAlg::Ed25519 => {
+                let signer: [u8; 32] = sig
+                    .signer
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| EnvelopeError::UnrecognizedKey)?;
+                let raw: [u8; 64] = sig
+                    .sig
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| EnvelopeError::Signature(VerifyError::BadSignature))?;
+                ed25519_verify(&signer, &self.canonical_bytes()?, &raw)
+                    .map_err(EnvelopeError::Signature)?;
+                Ok(inferred)
+            }
+            other => Err(EnvelopeError::UnsupportedAlgorithm(other)),
+        }
+    }
+}
+
+/// Unix seconds of a SPEC §4.1 `ts`, which must be `YYYY-MM-DDTHH:MM:SSZ`
+/// naming a real instant: a valid calendar date (proleptic Gregorian), hours
+/// up to 23, minutes up to 59, and seconds up to 60 for a leap second (which
+/// counts as the next minute's first second). `None` for anything else.
+#[must_use]
+pub fn rfc3339_utc_seconds(ts: &str) -> Option<i64> {
+    let b = ts.as_bytes();
+    let shape = b.len() == 20
+        && b.iter().enumerate().all(|(i, c)| match i {
+            4 | 7 => *c == b'-',
+            10 => *c == b'T',
+            13 | 16 => *c == b':',
+            19 => *c == b'Z',
+            _ => c.is_ascii_digit(),
+        });
+    if !shape {
+        return None;
+    }
+    let num = |r: core::ops::Range<usize>| ts[r].parse::<i64>().ok();
+    let (y, m, d) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hh, mm, ss) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    let leap = y % 4 == 0 && (y % 100 != 0 || y % 400 == 0);
+    let days_in_month = match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&d) || hh > 23 || mm > 59 || ss > 60 {
+        return None;
+    }
+    // Days from civil (Howard Hinnant).
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hh * 3_600 + mm * 60 + ss)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn envelope() -> EventEnvelope {
+        let mut payload = BTreeMap::new();
+        payload.insert("n".to_string(), Value::Integer(1));
+        EventEnvelope {
+            event_type: "event.append".to_string(),
+            ulid: "01HF4Z5Q3C7D6XJ9K8W2M1N0PR".parse().unwrap(),
+            actor: "user:alice".to_string(),
+            caps: None,
+            payload,
+            policy_root: std::format!("sha256:{}", "0".repeat(64)),
+            sig_alg: None,
+            ts: None,
+            sig: None,
+        }
+    }
+
+    #[test]
+    fn canonical_bytes_match_adr_vector_and_roundtrip() {
+        let env = envelope();
+        assert_eq!(
+            env.event_cid().unwrap().to_string(),
+            "bafyr4idw3cmelju74jwjodidk57kaquj7xie2tpwiv2npge5obcbia45tm"
+        );
+        let bytes = env.canonical_bytes().unwrap();
+        assert_eq!(EventEnvelope::from_canonical_bytes(&bytes).unwrap(), env);
+    }
+
+    #[test]
+    fn sign_and_verify_with_and_without_sig_alg() {
+        let mut env = envelope();
+        env.sign_ed25519(&[7; 32]).unwrap();
+        assert_eq!(env.verify(), Ok(SigAlg::Ed25519));
+
+        let mut declared = envelope();
+        declared.sig_alg = Some(SigAlg::Ed25519);
+        declared.ts = Some("2025-11-08T12:00:00Z".to_string());
+        declared.sign_ed25519(&[7; 32]).unwrap();
+        assert_eq!(declared.verify(), Ok(SigAlg::Ed25519));
+
+        // Signature over different canonical bytes fails.
+        declared.ts = None;
+        assert_eq!(
+            declared.verify(),
+            Err(EnvelopeError::Signature(VerifyError::BadSignature))
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_and_unknown_algorithms() {
+        let mut env = envelope();
+        env.sign_ed25519(&[7; 32]).unwrap();
+        env.sig_alg = Some(SigAlg::EcdsaP256);
+        assert_eq!(
+            env.verify(),
+            Err(EnvelopeError::AlgorithmMismatch {
+                declared: SigAlg::EcdsaP256,
+                inferred: SigAlg::Ed25519,
+            })
+        );
+
+        let mut p256 = envelope();
+        p256.sig = Some(EnvelopeSig {
+            signer: vec![0x04; 65],
+            sig: vec![0; 64],
+        });
+        assert_eq!(
+            p256.verify(),
+            Err(EnvelopeError::UnsupportedAlgorithm(SigAlg::EcdsaP256))
+        );
+
+        let mut value = envelope().to_value();
+        if let Value::Map(m) = &mut value {
+            m.insert("sig_alg".to_string(), Value::from("rsa"));
+        }
+        assert_eq!(
+            EventEnvelope::from_value(value),
+            Err(EnvelopeError::UnknownAlgorithm("rsa".to_string()))
+        );
+        assert_eq!(envelope().verify(), Err(EnvelopeError::MissingSignature));
+    }
+
+    #[test]
+    fn timestamps_name_real_instants() {
+        for (ts, secs) in [
+            ("1970-01-01T00:00:00Z", 0),
+            ("2024-01-02T03:04:05Z", 1_704_164_645),
+            ("2024-02-29T00:00:00Z", 1_709_164_800),
+            ("2016-12-31T23:59:60Z", 1_483_228_800),
+        ] {
+            assert_eq!(rfc3339_utc_seconds(ts), Some(secs), "{ts}");
+        }
+        for ts in [
+            "9999-99-99T99:99:99Z",
+            "2024-02-31T00:00:00Z",
+            "2023-02-29T00:00:00Z",
+            "2024-04-31T00:00:00Z",
+            "2024-00-10T00:00:00Z",
+            "2024-01-00T00:00:00Z",
+            "2024-01-01T24:00:00Z",
+            "2024-01-01T00:60:00Z",
+            "2024-01-01T00:00:61Z",
+            "2024-01-02T03:04:05.250Z",
+            "2024-01-02T03:04:05+01:00",
+        ] {
+            assert_eq!(rfc3339_utc_seconds(ts), None, "{ts}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_fields() {
+        for ts in ["2025-11-08 12:00:00", "9999-99-99T99:99:99Z"] {
+            let mut value = envelope().to_value();
+            if let Value::Map(m) = &mut value {
+                m.insert("ts".to_string(), Value::from(ts));
+            }
+            assert_eq!(
+                EventEnvelope::from_value(value),
+                Err(EnvelopeError::Field("ts"))
+            );
+        }
+
+        let mut value = envelope().to_value();
+        if let Value::Map(m) = &mut value {
+            m.insert("extra".to_string(), Value::Null);
+        }
+        assert_eq!(
+            EventEnvelope::from_value(value),
+            Err(EnvelopeError::Field("unknown field"))
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Storage errors shared by every [`ObjectStore`](crate::ObjectStore) backend.
+//!
+//! [`StoreError`] keeps enough context to act on a failure without parsing
+//! strings: corruption carries the expected and actual hashes, a lost
+//! compare-and-swap carries the ref and the ids involved, and backend
+//! failures keep a [`BackendErrorKind`] plus, with the `std` feature, the
+//! original error as a [`source`](std::error::Error::source).
+//!
+//! [`StoreError::is_retryable`] separates transient failures (a CAS race, a
+//! held lock) that a retry loop per SPEC §4.2 may repeat from fatal ones.
+
+use alloc::string::String;
+use core::fmt;
+
+use crate::ids::hex_encode;
+use crate::{Blake3Digest, CommitOid, Ed25519Key, Hash, PubKey, VerifyError};
+
+#[cfg(feature = "std")]
+type Source = std::sync::Arc<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Errors produced by storage backends implementing
+/// [`ObjectStore`](crate::ObjectStore).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreError {
+    /// An object required by the operation is absent.
+    NotFound(Hash),
+    /// Bytes do not hash to the id they are stored or requested under.
+    /// `actual` is the hash of the offending bytes under the same algorithm
+    /// as `expected` (BLAKE3 unless addressed by a [`Multihash`](crate::Multihash)).
+    Corruption { expected: Hash, actual: Hash },
+    /// A compare-and-swap update of `reference` lost a race: it no longer
+    /// points at `old` (`None`: it was expected not to exist), so `new` was
+    /// not written.
+    Conflict {
+        reference: String,
+        old: Option<CommitOid>,
+        new: CommitOid,
+    },
+    /// The backend refused access to the underlying storage.
+    PermissionDenied(String),
+    /// Underlying backend I/O or system error.
+    Backend(BackendError),
+    /// Operation unsupported by this backend.
+    Unsupported,
+    /// Fixed-capacity backend has no room for the object.
+    CapacityExceeded,
+    /// A signature offered for storage does not verify over its commit.
+    Signature { signer: PubKey, error: VerifyError },
+    /// Internal invariant violation; names the invariant.
+    Invariant(&'static str),
+}
+
+impl StoreError {
+    /// Corruption error for `bytes` found under `expected`.
+    #[must_use]
+    pub fn corruption(expected: &Hash, bytes: &[u8]) -> Self {
+        Self::Corruption {
+            expected: *expected,
+            actual: blake3::hash(bytes).into(),
+        }
+    }
+
+    /// `true` if repeating the operation may succeed: a lost CAS race or a
+    /// transient backend condition. Every other error is fatal.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Conflict { .. } => true,
+            Self::Backend(e) => e.kind.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "object {} not found", Blake3Digest::new(*id)),
+            Self::Corruption { expected, actual } => write!(
+                f,
+                "corrupt object: expected digest {}, found bytes hashing to {}",
+                hex_encode(expected),
+                hex_encode(actual)
+            ),
+            Self::Conflict {
+                reference,
+                old: Some(old),
+                new,
+            } => write!(f, "{reference} moved away from {old}; {new} not written"),
+            Self::Conflict {
+                reference,
+                old: None,
+                new,
+            } => write!(f, "{reference} already exists; {new} not written"),
+            Self::PermissionDenied(what) => write!(f, "permission denied: {what}"),
+            Self::Backend(e) => fmt::Display::fmt(e, f),
+            Self::Unsupported => f.write_str("operation not supported by this store"),
+            Self::CapacityExceeded => f.write_str("store capacity exceeded"),
+            Self::Signature { signer, error } => write!(
+                f,
+                "signature by {} does not verify: {error:?}",
+                Ed25519Key::new(*signer)
+            ),
+            Self::Invariant(what) => write!(f, "invariant violated: {what}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Backend(e) => e.source.as_deref().map(|s| s as _),
+            _ => None,
+        }
+    }
+}
+
+impl From<BackendError> for StoreError {
+    fn from(e: BackendError) -> Self {
+        Self::Backend(e)
+    }
+}
+
+/// Broad cause of a [`BackendError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendErrorKind {
+    /// Filesystem or OS failure.
+    Io,
+    /// A lock on the object or ref is held by another writer.
+    Locked,
+    /// The operation timed out or was interrupted.
+    Interrupted,
+    /// Anything else the backend reports.
+    Other,
+}
+
+impl BackendErrorKind {
+    /// `true` for conditions expected to clear on their own.
+    #[must_use]
+    pub fn is_transient(self) -> bool {
+        matches!(self, Self::Locked | Self::Interrupted)
+    }
+}
+
+/// A backend failure with its message and, under `std`, its cause.
+///
+/// Equality compares kind and message only.
+#[derive(Debug, Clone)]
+pub struct BackendError {
+    pub kind: BackendErrorKind,
+    pub message: String,
+    #[cfg(feature = "std")]
+    source: Option<Source>,
+}
+
+impl BackendError {
+    #[must_use]
+    pub fn new(kind: BackendErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            #[cfg(feature = "std")]
+            source: None,
+        }
+    }
+
+    /// Wrap `source`, taking its `Display` output as the message.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn from_source<E>(kind: BackendErrorKind, source: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        use std::string::ToString;
+        Self {
+            kind,
+            message: source.to_string(),
+            source: Some(std::sync::Arc::new(source)),
+        }
+    }
+}
+
+impl PartialEq for BackendError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.message == other.message
+    }
+}
+
+impl Eq for BackendError {}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            BackendErrorKind::Io => "I/O error",
+            BackendErrorKind::Locked => "locked",
+            BackendErrorKind::Interrupted => "interrupted",
+            BackendErrorKind::Other => "backend error",
+        };
+        write!(f, "{kind}: {}", self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BackendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_deref().map(|s| s as _)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn classification_and_display() {
+        let oid: CommitOid = "11".repeat(20).parse().unwrap();
+        let conflict = StoreError::Conflict {
+            reference: "refs/gatos/journal/ns/alice".to_string(),
+            old: None,
+            new: oid,
+        };
+        assert!(conflict.is_retryable());
+        assert!(conflict.to_string().contains("already exists"));
+        let locked = StoreError::Backend(BackendError::new(BackendErrorKind::Locked, "index.lock"));
+        assert!(locked.is_retryable());
+        let io = StoreError::Backend(BackendError::new(BackendErrorKind::Io, "disk full"));
+        assert!(!io.is_retryable());
+        assert_eq!(io.to_string(), "I/O error: disk full");
+        let corrupt = StoreError::corruption(&[0; 32], b"x");
+        assert!(!corrupt.is_retryable());
+        assert!(matches!(corrupt, StoreError::Corruption { actual, .. }
+            if actual == *blake3::hash(b"x").as_bytes()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn backend_source_is_chained() {
+        use std::error::Error;
+        let cause = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "nope");
+        let err = StoreError::from(BackendError::from_source(BackendErrorKind::Io, cause));
+        assert_eq!(err.source().unwrap().to_string(), "nope");
+        assert_eq!(err.clone(), err);
+    }
+}
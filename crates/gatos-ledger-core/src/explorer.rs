@@ -0,0 +1,135 @@
+//! `Explorer-Root` checksum for exported views (SPEC §15.1).
+//!
+//! The checksum is `blake3` over a canonical concatenation of BE32
+//! length-prefixed fields: `ledger_head`, `policy_root`, `fold_root` (derived
+//! exports only) and the UTF-8 `extractor_version`. Raw ledger exports omit
+//! `fold_root` entirely, prefix included.
+
+use alloc::vec::Vec;
+
+use crate::ids::{hex_decode, IdError};
+use crate::Hash;
+
+/// Errors produced while computing an `Explorer-Root`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplorerRootError {
+    /// A digest field is not lowercase hex.
+    Hex(&'static str),
+    /// A field is longer than `u32::MAX` bytes and cannot be length-prefixed.
+    TooLong(&'static str),
+}
+
+/// Canonical serialization that `Explorer-Root` hashes.
+///
+/// # Errors
+/// Returns [`ExplorerRootError::TooLong`] if a field does not fit a BE32 prefix.
+pub fn explorer_root_preimage(
+    ledger_head: &[u8],
+    policy_root: &[u8],
+    fold_root: Option<&[u8]>,
+    extractor_version: &str,
+) -> Result<Vec<u8>, ExplorerRootError> {
+    let fields = [
+        Some(("ledger_head", ledger_head)),
+        Some(("policy_root", policy_root)),
+        fold_root.map(|f| ("fold_root", f)),
+        Some(("extractor_version", extractor_version.as_bytes())),
+    ];
+    let mut out = Vec::new();
+    for (name, bytes) in fields.into_iter().flatten() {
+        let len = u32::try_from(bytes.len()).map_err(|_| ExplorerRootError::TooLong(name))?;
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(bytes);
+    }
+    Ok(out)
+}
+
+/// Compute `Explorer-Root` from raw digest bytes. Pass `fold_root = None` for
+/// raw ledger exports.
+///
+/// # Errors
+/// Returns [`ExplorerRootError::TooLong`] if a field does not fit a BE32 prefix.
+pub fn compute_explorer_root(
+    ledger_head: &[u8],
+    policy_root: &[u8],
+    fold_root: Option<&[u8]>,
+    extractor_version: &str,
+) -> Result<Hash, ExplorerRootError> {
+    let bytes = explorer_root_preimage(ledger_head, policy_root, fold_root, extractor_version)?;
+    Ok(blake3::hash(&bytes).into())
+}
+
+/// Compute `Explorer-Root` from the lowercase hex digests as they appear in
+/// export manifests and trailers.
+///
+/// # Errors
+/// Returns [`ExplorerRootError::Hex`] naming the first field that is not
+/// lowercase hex, or [`ExplorerRootError::TooLong`].
+pub fn compute_explorer_root_hex(
+    ledger_head: &str,
+    policy_root: &str,
+    fold_root: Option<&str>,
+    extractor_version: &str,
+) -> Result<Hash, ExplorerRootError> {
+    let decode = |name, s| hex_decode(s).map_err(|_: IdError| ExplorerRootError::Hex(name));
+    let ledger_head = decode("ledger_head", ledger_head)?;
+    let policy_root = decode("policy_root", policy_root)?;
+    let fold_root = fold_root.map(|f| decode("fold_root", f)).transpose()?;
+    compute_explorer_root(
+        &ledger_head,
+        &policy_root,
+        fold_root.as_deref(),
+        extractor_version,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAD: &str = "1111111111111111111111111111111111111111";
+    const POLICY: &str = "2222222222222222222222222222222222222222";
+    const FOLD: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const VERSION: &str = "v1.2.3+linux-x86_64";
+
+    /// SPEC §15.1 worked example (derived export).
+    #[test]
+    fn spec_worked_example() {
+        let head = hex_decode(HEAD).unwrap();
+        let policy = hex_decode(POLICY).unwrap();
+        let fold = hex_decode(FOLD).unwrap();
+        let pre = explorer_root_preimage(&head, &policy, Some(&fold), VERSION).unwrap();
+        let expected_pre = concat!(
+            "00000014111111111111111111111111111111111111111100000014222222222222222222222222",
+            "222222222222222200000020aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaa0000001376312e322e332b6c696e75782d7838365f3634"
+        );
+        assert_eq!(pre, hex_decode(expected_pre).unwrap());
+
+        let root = compute_explorer_root_hex(HEAD, POLICY, Some(FOLD), VERSION).unwrap();
+        assert_eq!(
+            root.as_slice(),
+            hex_decode("e4cbbcc02698608bb59ed39d715ffd6e74c2865f473daf7cb861f9da33565d9d").unwrap()
+        );
+    }
+
+    #[test]
+    fn raw_export_omits_fold_root() {
+        let head = hex_decode(HEAD).unwrap();
+        let policy = hex_decode(POLICY).unwrap();
+        let pre = explorer_root_preimage(&head, &policy, None, VERSION).unwrap();
+        assert_eq!(pre.len(), 3 * 4 + 20 + 20 + VERSION.len());
+        assert_eq!(
+            compute_explorer_root_hex(HEAD, POLICY, None, VERSION).unwrap(),
+            *blake3::hash(&pre).as_bytes()
+        );
+    }
+
+    #[test]
+    fn rejects_uppercase_hex() {
+        assert_eq!(
+            compute_explorer_root_hex(HEAD, POLICY, Some("AA"), VERSION),
+            Err(ExplorerRootError::Hex("fold_root"))
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Prefixed digest and key identifiers.
+//!
+//! Trailers, schemas and manifests never carry bare hashes: digests are
+//! written `blake3:<hex>` or `sha256:<hex>`, and signer keys `ed25519:<key>`
+//! where `<key>` is either 64 lowercase hex digits or standard padded base64.
+//! Parsing is strict (prefix required, lowercase hex only, exact length) so a
+//! malformed value is rejected here rather than by each consumer.
+//!
+//! The text form is used for `Display`/`FromStr` and serde; bincode encodes
+//! the raw 32 bytes, so these types are drop-in replacements for [`Hash`] and
+//! [`PubKey`] inside canonical structures.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use bincode::{Decode, Encode};
+
+use crate::{Hash, PubKey};
+
+/// Errors produced while parsing a prefixed identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdError {
+    /// No `<alg>:` prefix present.
+    MissingPrefix,
+    /// A prefix was present but names a different algorithm.
+    WrongAlgorithm,
+    /// Body is not lowercase hex.
+    Hex,
+    /// Body is not standard padded base64.
+    Base64,
+    /// Body decodes to the wrong number of bytes.
+    Length,
+}
+
+impl fmt::Display for IdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingPrefix => "missing algorithm prefix",
+            Self::WrongAlgorithm => "unexpected algorithm prefix",
+            Self::Hex => "invalid lowercase hex",
+            Self::Base64 => "invalid base64",
+            Self::Length => "wrong digest length",
+        })
+    }
+}
+
+macro_rules! prefixed_digest {
+    ($(#[$doc:meta])* $name:ident, $prefix:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+        pub struct $name(pub Hash);
+
+        impl $name {
+            /// Algorithm prefix, without the trailing `:`.
+            pub const PREFIX: &'static str = $prefix;
+
+            #[must_use]
+            pub const fn new(bytes: Hash) -> Self {
+                Self(bytes)
+            }
+
+            #[must_use]
+            pub const fn as_bytes(&self) -> &Hash {
+                &self.0
+            }
+
+            /// Bare lowercase hex, without the prefix.
+            #[must_use]
+            pub fn to_hex(&self) -> String {
+                hex_encode(&self.0)
+            }
+        }
+
+        impl From<Hash> for $name {
+            fn from(bytes: Hash) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl From<$name> for Hash {
+            fn from(d: $name) -> Self {
+                d.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}:{}", Self::PREFIX, self.to_hex())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = IdError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                hex_decode_32(strip_prefix(s, Self::PREFIX)?).map(Self)
+            }
+        }
+
+        impl_serde_via_str!($name, concat!("a `", $prefix, ":<hex>` digest"));
+    };
+}
+
+macro_rules! impl_serde_via_str {
+    ($name:ident, $expecting:expr) => {
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct V;
+                impl serde::de::Visitor<'_> for V {
+                    type Value = $name;
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str($expecting)
+                    }
+                    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<$name, E> {
+                        v.parse().map_err(E::custom)
+                    }
+                }
+                deserializer.deserialize_str(V)
+            }
+        }
+    };
+}
+pub(crate) use impl_serde_via_str;
+
+prefixed_digest!(
+    /// A BLAKE3-256 digest, written `blake3:<64 hex>`.
+    Blake3Digest,
+    "blake3"
+);
+
+prefixed_digest!(
+    /// A SHA-256 digest, written `sha256:<64 hex>`.
+    Sha256Digest,
+    "sha256"
+);
+
+/// An Ed25519 public key identifier, written `ed25519:<key>`.
+///
+/// Both 64-digit lowercase hex and 44-character padded base64 bodies are
+/// accepted; `Display` always emits hex so the canonical form is unique.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+pub struct Ed25519Key(pub PubKey);
+
+impl Ed25519Key {
+    /// Algorithm prefix, without the trailing `:`.
+    pub const PREFIX: &'static str = "ed25519";
+
+    #[must_use]
+    pub const fn new(bytes: PubKey) -> Self {
+        Self(bytes)
+    }
+
+    #[must_use]
+    pub const fn as_bytes(&self) -> &PubKey {
+        &self.0
+    }
+
+    /// Bare lowercase hex, without the prefix.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        hex_encode(&self.0)
+    }
+
+    /// `ed25519:<base64>` form, for tools that exchange keys as base64.
+    #[must_use]
+    pub fn to_base64_string(&self) -> String {
+        let mut out = String::from("ed25519:");
+        base64_encode(&self.0, &mut out);
+        out
+    }
+}
+
+impl From<PubKey> for Ed25519Key {
+    fn from(bytes: PubKey) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for Ed25519Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", Self::PREFIX, self.to_hex())
+    }
+}
+
+impl FromStr for Ed25519Key {
+    type Err = IdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let body = strip_prefix(s, Self::PREFIX)?;
+        if body.len() == 64 {
+            hex_decode_32(body).map(Self)
+        } else {
+            let bytes = base64_decode(body)?;
+            bytes.try_into().map(Self).map_err(|_| IdError::Length)
+        }
+    }
+}
+
+impl_serde_via_str!(Ed25519Key, "an `ed25519:<hex|base64>` key");
+
+fn strip_prefix<'a>(s: &'a str, prefix: &str) -> Result<&'a str, IdError> {
+    let (alg, body) = s.split_once(':').ok_or(IdError::MissingPrefix)?;
+    if alg != prefix {
+        return Err(IdError::WrongAlgorithm);
+    }
+    Ok(body)
+}
+
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(DIGITS[usize::from(b >> 4)] as char);
+        out.push(DIGITS[usize::from(b & 0xf)] as char);
+    }
+    out
+}
+
+pub(crate) fn hex_decode_32(s: &str) -> Result<[u8; 32], IdError> {
+    hex_decode(s)?.try_into().map_err(|_| IdError::Length)
+}
+
+/// Strict lowercase hex of any even length.
+pub(crate) fn hex_decode(s: &str) -> Result<Vec<u8>, IdError> {
+    fn nibble(c: u8) -> Result<u8, IdError> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            _ => Err(IdError::Hex),
+        }
+    }
+    let s = s.as_bytes();
+    if !s.chunks_exact(2).remainder().is_empty() {
+        return Err(IdError::Hex);
+    }
+    s.chunks_exact(2)
+        .map(|pair| Ok((nibble(pair[0])? << 4) | nibble(pair[1])?))
+        .collect()
+}
+
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8], out: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(B64[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
+
+/// Strict standard base64: padding required, no whitespace, zero spare bits.
+fn base64_decode(s: &str) -> Result<Vec<u8>, IdError> {
+    let s = s.as_bytes();
+    if s.is_empty() || !s.chunks_exact(4).remainder().is_empty() {
+        return Err(IdError::Base64);
+    }
+    let pad = s.iter().rev().take_while(|&&c| c == b'=').count();
+    if pad > 2 {
+        return Err(IdError::Base64);
+    }
+    let body = &s[..s.len() - pad];
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &c in body {
+        let v = B64.iter().position(|&a| a == c).ok_or(IdError::Base64)? as u32;
+        acc = (acc << 6) | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if acc & ((1 << bits) - 1) != 0 {
+        return Err(IdError::Base64);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    const KEY_HEX: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    #[test]
+    fn digest_roundtrip_and_strictness() {
+        let d = Blake3Digest::new([0xab; 32]);
+        let text = d.to_string();
+        assert_eq!(text, std::format!("blake3:{}", "ab".repeat(32)));
+        assert_eq!(text.parse::<Blake3Digest>(), Ok(d));
+
+        let upper = std::format!("blake3:{}", "AB".repeat(32));
+        assert_eq!(upper.parse::<Blake3Digest>(), Err(IdError::Hex));
+        assert_eq!(
+            "ab".repeat(32).parse::<Blake3Digest>(),
+            Err(IdError::MissingPrefix)
+        );
+        let sha = std::format!("sha256:{}", "ab".repeat(32));
+        assert_eq!(sha.parse::<Blake3Digest>(), Err(IdError::WrongAlgorithm));
+        assert!(sha.parse::<Sha256Digest>().is_ok());
+        assert_eq!("blake3:abcd".parse::<Blake3Digest>(), Err(IdError::Length));
+    }
+
+    #[test]
+    fn key_accepts_hex_and_base64() {
+        let hex_form: Ed25519Key = std::format!("ed25519:{KEY_HEX}").parse().unwrap();
+        let b64 = hex_form.to_base64_string();
+        assert_eq!(b64, "ed25519:11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=");
+        assert_eq!(b64.parse::<Ed25519Key>(), Ok(hex_form));
+        assert_eq!(hex_form.to_string(), std::format!("ed25519:{KEY_HEX}"));
+
+        // Non-zero spare bits and missing padding are rejected.
+        assert_eq!(
+            "ed25519:11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURp=".parse::<Ed25519Key>(),
+            Err(IdError::Base64)
+        );
+        assert_eq!(
+            "ed25519:11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo".parse::<Ed25519Key>(),
+            Err(IdError::Base64)
+        );
+        assert_eq!("ed25519:AAAA".parse::<Ed25519Key>(), Err(IdError::Length));
+    }
+
+    #[test]
+    fn bincode_uses_raw_bytes() {
+        let d = Sha256Digest::new([7; 32]);
+        let bytes = bincode::encode_to_vec(d, bincode::config::standard()).unwrap();
+        assert_eq!(bytes, [7u8; 32]);
+        let (back, _): (Sha256Digest, _) =
+            bincode::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+        assert_eq!(back, d);
+    }
+}
@@ -0,0 +1,527 @@
+//! RFC 8785 JSON Canonicalization Scheme (JCS).
+//!
+//! ADR-0002 and ADR-0003 hash job manifests, Proof-of-Consensus envelopes and
+//! opaque pointers over canonical JSON. This module produces those bytes:
+//!
+//! - input is strict RFC 8259 JSON; duplicate object keys are rejected;
+//! - strings (keys and values) are normalized to Unicode NFC, and keys that
+//!   collide after normalization are rejected as duplicates;
+//! - object keys are sorted by UTF-16 code units (RFC 8785 §3.2.3);
+//! - numbers are IEEE 754 doubles printed as ECMAScript `Number::toString`
+//!   (RFC 8785 §3.2.2.3); NaN and infinities cannot be represented;
+//! - no insignificant whitespace; strings use the minimal JCS escapes.
+//!
+//! NFC normalization is a GATOS addition on top of RFC 8785 so that visually
+//! identical text authored on different platforms hashes identically.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::Write;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::Hash;
+
+/// Maximum nesting depth accepted by [`parse`].
+pub const MAX_DEPTH: usize = 128;
+
+/// A JSON value (I-JSON data model).
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
+}
+
+/// Errors produced while parsing or canonicalizing JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JcsError {
+    /// Malformed JSON at the given byte offset.
+    Syntax(usize),
+    /// An object contains the same key twice (after NFC normalization).
+    DuplicateKey(String),
+    /// A number is not a finite IEEE 754 double.
+    NumberOutOfRange,
+    /// A `\u` escape encodes an unpaired surrogate.
+    LoneSurrogate(usize),
+    /// Nesting deeper than [`MAX_DEPTH`].
+    TooDeep,
+}
+
+/// Parse strict RFC 8259 JSON text, rejecting duplicate object keys.
+///
+/// # Errors
+/// Returns a [`JcsError`] describing the first problem found.
+pub fn parse(text: &str) -> Result<JsonValue, JcsError> {
+    let mut p = Parser {
+        s: text.as_bytes(),
+        pos: 0,
+    };
+    p.ws();
+    let v = p.value(0)?;
+    p.ws();
+    if p.pos != p.s.len() {
+        return Err(JcsError::Syntax(p.pos));
+    }
+    Ok(v)
+}
+
+/// Canonical JCS bytes for `value`.
+///
+/// # Errors
+/// Returns [`JcsError::NumberOutOfRange`] for non-finite numbers and
+/// [`JcsError::DuplicateKey`] when two keys normalize to the same NFC form.
+pub fn canonicalize(value: &JsonValue) -> Result<Vec<u8>, JcsError> {
+    let mut out = String::new();
+    write_value(value, &mut out)?;
+    Ok(out.into_bytes())
+}
+
+/// Parse `text` and return its canonical JCS bytes.
+///
+/// # Errors
+/// See [`parse`] and [`canonicalize`].
+pub fn canonicalize_str(text: &str) -> Result<Vec<u8>, JcsError> {
+    canonicalize(&parse(text)?)
+}
+
+/// `content_id = blake3(jcs(value))`, the canonical id of a JSON artifact.
+///
+/// # Errors
+/// See [`canonicalize`].
+pub fn content_id_json(value: &JsonValue) -> Result<Hash, JcsError> {
+    Ok(blake3::hash(&canonicalize(value)?).into())
+}
+
+fn utf16_cmp(a: &str, b: &str) -> Ordering {
+    a.encode_utf16().cmp(b.encode_utf16())
+}
+
+fn write_value(value: &JsonValue, out: &mut String) -> Result<(), JcsError> {
+    match value {
+        JsonValue::Null => out.push_str("null"),
+        JsonValue::Bool(true) => out.push_str("true"),
+        JsonValue::Bool(false) => out.push_str("false"),
+        JsonValue::Number(n) => write_number(*n, out)?,
+        JsonValue::String(s) => write_string(&s.nfc().collect::<String>(), out),
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        JsonValue::Object(map) => {
+            let mut entries: Vec<(String, &JsonValue)> =
+                map.iter().map(|(k, v)| (k.nfc().collect(), v)).collect();
+            entries.sort_by(|a, b| utf16_cmp(&a.0, &b.0));
+            if let Some(w) = entries.windows(2).find(|w| w[0].0 == w[1].0) {
+                return Err(JcsError::DuplicateKey(w[0].0.clone()));
+            }
+            out.push('{');
+            for (i, (k, v)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(k, out);
+                out.push(':');
+                write_value(v, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{09}' => out.push_str("\\t"),
+            '\u{0a}' => out.push_str("\\n"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\u{0d}' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => {
+                out.push_str("\\u00");
+                out.push(HEX[(c as usize) >> 4] as char);
+                out.push(HEX[(c as usize) & 0xf] as char);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// ECMAScript `Number::toString` (ECMA-262 §6.1.6.1.20) for finite doubles.
+fn write_number(n: f64, out: &mut String) -> Result<(), JcsError> {
+    if !n.is_finite() {
+        return Err(JcsError::NumberOutOfRange);
+    }
+    if n == 0.0 {
+        // Covers -0, which serializes as "0".
+        out.push('0');
+        return Ok(());
+    }
+    if n < 0.0 {
+        out.push('-');
+    }
+    // Shortest round-trip digits from ryu, re-laid out per ECMAScript.
+    let mut buf = ryu::Buffer::new();
+    let repr = buf.format_finite(n.abs());
+    let (mantissa, exp) = match repr.split_once('e') {
+        Some((m, e)) => (m, e.parse::<i32>().map_err(|_| JcsError::NumberOutOfRange)?),
+        None => (repr, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut digits: Vec<u8> = int.bytes().chain(frac.bytes()).collect();
+    // Decimal point position relative to the start of `digits`.
+    let mut point = int.len() as i32 + exp;
+    while digits.first() == Some(&b'0') {
+        digits.remove(0);
+        point -= 1;
+    }
+    while digits.last() == Some(&b'0') {
+        digits.pop();
+    }
+    let k = digits.len() as i32;
+    let digits = core::str::from_utf8(&digits).map_err(|_| JcsError::NumberOutOfRange)?;
+    if k <= point && point <= 21 {
+        out.push_str(digits);
+        (0..point - k).for_each(|_| out.push('0'));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        (0..-point).for_each(|_| out.push('0'));
+        out.push_str(digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let e = point - 1;
+        out.push('e');
+        out.push(if e < 0 { '-' } else { '+' });
+        // Writing to a `String` cannot fail.
+        let _ = write!(out, "{}", e.unsigned_abs());
+    }
+    Ok(())
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn ws(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.s.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, lit: &[u8]) -> Result<(), JcsError> {
+        if self.s[self.pos..].starts_with(lit) {
+            self.pos += lit.len();
+            Ok(())
+        } else {
+            Err(JcsError::Syntax(self.pos))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue, JcsError> {
+        if depth > MAX_DEPTH {
+            return Err(JcsError::TooDeep);
+        }
+        match self.s.get(self.pos) {
+            Some(b'n') => self.eat(b"null").map(|()| JsonValue::Null),
+            Some(b't') => self.eat(b"true").map(|()| JsonValue::Bool(true)),
+            Some(b'f') => self.eat(b"false").map(|()| JsonValue::Bool(false)),
+            Some(b'"') => self.string().map(JsonValue::String),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.ws();
+                if self.s.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                loop {
+                    self.ws();
+                    items.push(self.value(depth + 1)?);
+                    self.ws();
+                    match self.s.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Array(items));
+                        }
+                        _ => return Err(JcsError::Syntax(self.pos)),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut map = BTreeMap::new();
+                self.ws();
+                if self.s.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(map));
+                }
+                loop {
+                    self.ws();
+                    if self.s.get(self.pos) != Some(&b'"') {
+                        return Err(JcsError::Syntax(self.pos));
+                    }
+                    let key = self.string()?;
+                    self.ws();
+                    self.eat(b":")?;
+                    self.ws();
+                    let value = self.value(depth + 1)?;
+                    if map.contains_key(&key) {
+                        return Err(JcsError::DuplicateKey(key));
+                    }
+                    map.insert(key, value);
+                    self.ws();
+                    match self.s.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Object(map));
+                        }
+                        _ => return Err(JcsError::Syntax(self.pos)),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(JcsError::Syntax(self.pos)),
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.s.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<JsonValue, JcsError> {
+        let start = self.pos;
+        if self.s.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+        let int_start = self.pos;
+        match self.digits() {
+            0 => return Err(JcsError::Syntax(self.pos)),
+            n if n > 1 && self.s[int_start] == b'0' => return Err(JcsError::Syntax(int_start)),
+            _ => {}
+        }
+        if self.s.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            if self.digits() == 0 {
+                return Err(JcsError::Syntax(self.pos));
+            }
+        }
+        if let Some(b'e' | b'E') = self.s.get(self.pos) {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.s.get(self.pos) {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(JcsError::Syntax(self.pos));
+            }
+        }
+        // The slice is ASCII by construction.
+        let text =
+            core::str::from_utf8(&self.s[start..self.pos]).map_err(|_| JcsError::Syntax(start))?;
+        let n: f64 = text.parse().map_err(|_| JcsError::Syntax(start))?;
+        if !n.is_finite() {
+            return Err(JcsError::NumberOutOfRange);
+        }
+        Ok(JsonValue::Number(n))
+    }
+
+    fn hex4(&mut self) -> Result<u16, JcsError> {
+        let raw = self
+            .s
+            .get(self.pos..self.pos + 4)
+            .filter(|raw| raw.iter().all(u8::is_ascii_hexdigit))
+            .ok_or(JcsError::Syntax(self.pos))?;
+        // Four ASCII hex digits; `from_str_radix` alone would also take a sign.
+        let text = core::str::from_utf8(raw).map_err(|_| JcsError::Syntax(self.pos))?;
+        let v = u16::from_str_radix(text, 16).map_err(|_| JcsError::Syntax(self.pos))?;
+        self.pos += 4;
+        Ok(v)
+    }
+
+    fn string(&mut self) -> Result<String, JcsError> {
+        self.pos += 1; // opening quote
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(&b) = self.s.get(self.pos) {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // Input is `&str`, and we only split at ASCII bytes.
+            out.push_str(
+                core::str::from_utf8(&self.s[start..self.pos])
+                    .map_err(|_| JcsError::Syntax(start))?,
+            );
+            match self.s.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let esc = *self.s.get(self.pos).ok_or(JcsError::Syntax(self.pos))?;
+                    self.pos += 1;
+                    match esc {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{08}'),
+                        b'f' => out.push('\u{0c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => out.push(self.unicode_escape()?),
+                        _ => return Err(JcsError::Syntax(self.pos - 1)),
+                    }
+                }
+                _ => return Err(JcsError::Syntax(self.pos)),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, JcsError> {
+        let at = self.pos;
+        let hi = self.hex4()?;
+        let code = match hi {
+            0xd800..=0xdbff => {
+                if !self.s[self.pos..].starts_with(b"\\u") {
+                    return Err(JcsError::LoneSurrogate(at));
+                }
+                self.pos += 2;
+                let lo = self.hex4()?;
+                if !(0xdc00..=0xdfff).contains(&lo) {
+                    return Err(JcsError::LoneSurrogate(at));
+                }
+                0x10000 + ((u32::from(hi) - 0xd800) << 10) + (u32::from(lo) - 0xdc00)
+            }
+            0xdc00..=0xdfff => return Err(JcsError::LoneSurrogate(at)),
+            _ => u32::from(hi),
+        };
+        char::from_u32(code).ok_or(JcsError::LoneSurrogate(at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    fn jcs(text: &str) -> String {
+        String::from_utf8(canonicalize_str(text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn rfc8785_section_3_2_4_example() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            jcs(input),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn rfc8785_appendix_b_numbers() {
+        let cases: &[(u64, &str)] = &[
+            (0x0000_0000_0000_0000, "0"),
+            (0x8000_0000_0000_0000, "0"),
+            (0x0000_0000_0000_0001, "5e-324"),
+            (0x8000_0000_0000_0001, "-5e-324"),
+            (0x7fef_ffff_ffff_ffff, "1.7976931348623157e+308"),
+            (0x4340_0000_0000_0000, "9007199254740992"),
+            (0xc3e0_0000_0000_0000, "-9223372036854776000"),
+            (0x44b5_2d02_c7e1_4af6, "1e+23"),
+            (0x44b5_2d02_c7e1_4af5, "9.999999999999997e+22"),
+            (0x444b_1ae4_d6e2_ef50, "1e+21"),
+            (0x444b_1ae4_d6e2_ef4f, "999999999999999900000"),
+            (0x3eb0_c6f7_a0b5_ed8d, "0.000001"),
+            (0x3eb0_c6f7_a0b5_ed8c, "9.999999999999997e-7"),
+            (0x41b3_de43_5555_5555, "333333333.3333333"),
+        ];
+        for (bits, expected) in cases {
+            let mut out = String::new();
+            write_number(f64::from_bits(*bits), &mut out).unwrap();
+            assert_eq!(out, *expected, "{bits:#x}");
+        }
+    }
+
+    #[test]
+    fn adr_0002_job_manifest_example() {
+        let manifest = r#"{"timeout": 30, "command": ["/usr/bin/env", "bash", "-lc"],
+            "env": {"GREETING": "hello"}, "args": ["echo", "hello"]}"#;
+        let canonical = r#"{"args":["echo","hello"],"command":["/usr/bin/env","bash","-lc"],"env":{"GREETING":"hello"},"timeout":30}"#;
+        assert_eq!(jcs(manifest), canonical);
+        assert_eq!(
+            content_id_json(&parse(manifest).unwrap()).unwrap(),
+            *blake3::hash(canonical.as_bytes()).as_bytes()
+        );
+    }
+
+    #[test]
+    fn keys_sort_by_utf16_and_strings_normalize_to_nfc() {
+        // U+1F600 (surrogates D83D..) sorts before U+FB01 in UTF-16 order.
+        assert_eq!(
+            jcs("{\"\u{fb01}\":1,\"\u{1f600}\":2}"),
+            "{\"\u{1f600}\":2,\"\u{fb01}\":1}"
+        );
+        // "e" + combining acute normalizes to precomposed U+00E9.
+        assert_eq!(jcs("[\"e\u{301}\"]"), "[\"\u{e9}\"]");
+        assert_eq!(
+            canonicalize_str("{\"\u{e9}\":1,\"e\u{301}\":2}"),
+            Err(JcsError::DuplicateKey("\u{e9}".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(
+            parse(r#"{"a":1,"a":2}"#),
+            Err(JcsError::DuplicateKey("a".to_string()))
+        );
+        assert_eq!(parse("[01]"), Err(JcsError::Syntax(1)));
+        assert_eq!(parse("[1,]"), Err(JcsError::Syntax(3)));
+        assert_eq!(parse(r#"["\ud800"]"#), Err(JcsError::LoneSurrogate(4)));
+        assert_eq!(parse("1e400"), Err(JcsError::NumberOutOfRange));
+        assert_eq!(parse("{} x"), Err(JcsError::Syntax(3)));
+        assert_eq!(parse("\"\u{1}\""), Err(JcsError::Syntax(1)));
+        assert_eq!(parse(r#""\u+041""#), Err(JcsError::Syntax(3)));
+        assert_eq!(parse(r#""\u004""#), Err(JcsError::Syntax(3)));
+    }
+}
@@ -12,9 +12,11 @@
 //! Caveats: determinism assumes a fixed type definition and serialization
 //! config across crate versions; floats are serialized bitwise; endianness is
 //! normalized by the format; changing field order or enum variants will change
-//! bytes.
+//! bytes. Released `CommitCore` layouts are frozen in [`versioning`] so ids of
+//! older commits stay verifiable.
 
 extern crate alloc;
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 
@@ -22,6 +24,62 @@ use bincode::{config, encode_to_vec, Decode, Encode};
 use serde_with::serde_as;
 use smallvec::SmallVec;
 
+pub mod chain;
+pub mod cid;
+pub mod codec;
+pub mod dag_cbor;
+pub mod ed25519;
+pub mod envelope;
+pub mod error;
+pub mod explorer;
+pub mod ids;
+pub mod jcs;
+pub mod merkle;
+pub mod multihash;
+pub mod quorum;
+pub mod store;
+pub mod trailers;
+pub mod tree;
+pub mod ulid;
+pub mod versioning;
+
+pub use chain::{verify_chain, ChainAnchor, ChainIssue, ChainPolicy, ChainReport};
+pub use cid::{Cid, CidError};
+pub use codec::{
+    commit_object_id, decode_commit, encode_commit, get_commit, object_id_matches, put_commit,
+    verify_object_id,
+};
+pub use dag_cbor::{event_cid, CborError};
+pub use ed25519::{
+    ed25519_public_key, ed25519_sign, ed25519_verify, SecretKey, SignatureReport, VerifyError,
+};
+pub use envelope::{rfc3339_utc_seconds, EnvelopeError, EnvelopeSig, EventEnvelope, SigAlg};
+pub use error::{BackendError, BackendErrorKind, StoreError};
+pub use explorer::{
+    compute_explorer_root, compute_explorer_root_hex, explorer_root_preimage, ExplorerRootError,
+};
+pub use ids::{Blake3Digest, Ed25519Key, IdError, Sha256Digest};
+pub use jcs::{content_id_json, JcsError, JsonValue};
+pub use merkle::{ConsistencyProof, InclusionProof, MerkleAccumulator, MerkleLog, ProofError};
+pub use multihash::{verify_object_address, HashAlg, Multihash};
+pub use quorum::ThresholdVerdict;
+pub use store::{HeaplessStore, MemoryStore};
+pub use trailers::{
+    parse_trailers, AlgHex, ApprovalTrailers, Artifact, ArtifactKind, CheckpointTrailers,
+    CommitOid, EventTrailers, GrantTrailers, ProposalTrailers, ResultTrailers, RevocationTrailers,
+    TrailerError, TrailerSet, TrailerSig,
+};
+pub use tree::{
+    build_tree, diff_trees, get_tree, put_tree, walk_tree, EntryKind, Tree, TreeChange, TreeEntry,
+    TreeError,
+};
+#[cfg(feature = "std")]
+pub use ulid::UlidGenerator;
+pub use ulid::{Ulid, UlidError};
+pub use versioning::{
+    CommitCoreV1, VersionError, VersionedCommitCore, CURRENT_COMMIT_CORE_VERSION,
+};
+
 /// 256-bit BLAKE3 content hash digest.
 ///
 /// - Size: 32 bytes (verbatim byte array as produced by `blake3` — no
@@ -31,30 +89,18 @@ pub type Hash = [u8; 32];
 
 /// Public key bytes for signature verification.
 ///
-/// The concrete scheme (e.g., ed25519) is defined at the policy/enforcement
-/// layer. We use a fixed 32-byte array here to keep the core portable and
-/// deterministic across platforms and backends.
+/// The concrete scheme is defined at the policy/enforcement layer; the core
+/// ships Ed25519 verification (see [`ed25519`]). We use a fixed 32-byte array
+/// here to keep the core portable and deterministic across platforms and
+/// backends.
 pub type PubKey = [u8; 32];
 
-/// Errors produced by storage backends implementing [`ObjectStore`].
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum StoreError {
-    /// Underlying backend I/O or system error. Carries backend-provided detail
-    /// where available to aid diagnostics in `std` backends.
-    Io(String),
-    /// Data corruption detected (e.g., hash mismatch).
-    Corruption,
-    /// Operation unsupported by this backend.
-    Unsupported,
-    /// Internal invariant violation.
-    Invariant,
-}
-
 /// Abstraction for content-addressed object storage.
 ///
 /// Backends MUST ensure that `id` is the BLAKE3 hash of `data` when storing
-/// content. Implementations SHOULD be idempotent: storing the same `(id, data)`
-/// pair multiple times is not an error.
+/// content, or [`commit_object_id`] of the core's content id for canonical
+/// commit objects (see [`object_id_matches`]). Implementations SHOULD be idempotent: storing the
+/// same `(id, data)` pair multiple times is not an error.
 pub trait ObjectStore {
     /// Persist bytes under the given content `id`.
     ///
@@ -73,6 +119,121 @@ pub trait ObjectStore {
     /// Returns a [`StoreError`] if the backend fails to access the underlying
     /// storage or detects corruption.
     fn get_object(&self, id: &Hash) -> Result<Option<Vec<u8>>, StoreError>;
+
+    /// Optional operations this backend supports. Defaults to none.
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities::NONE
+    }
+
+    /// Whether an object is stored under `id`.
+    ///
+    /// The default fetches the object; backends SHOULD override this with a
+    /// cheaper existence check.
+    ///
+    /// # Errors
+    /// Returns a [`StoreError`] if the backend cannot be queried.
+    fn has_object(&self, id: &Hash) -> Result<bool, StoreError> {
+        self.get_object(id).map(|o| o.is_some())
+    }
+
+    /// Persist several objects. Each `(id, data)` pair obeys the same
+    /// invariant as [`ObjectStore::put_object`].
+    ///
+    /// The default stores them one by one and stops at the first error;
+    /// backends MAY make the batch atomic.
+    ///
+    /// # Errors
+    /// Returns the first [`StoreError`] encountered.
+    fn put_objects(&mut self, objects: &[(Hash, &[u8])]) -> Result<(), StoreError> {
+        objects
+            .iter()
+            .try_for_each(|(id, data)| self.put_object(id, data))
+    }
+
+    /// Retrieve several objects; the result is positionally aligned with `ids`.
+    ///
+    /// # Errors
+    /// Returns the first [`StoreError`] encountered.
+    fn get_objects(&self, ids: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        ids.iter().map(|id| self.get_object(id)).collect()
+    }
+
+    /// Iterate over the ids of all stored objects, in unspecified order.
+    ///
+    /// # Errors
+    /// Returns [`StoreError::Unsupported`] unless
+    /// [`StoreCapabilities::enumerate`] is set.
+    fn object_ids(&self) -> Result<ObjectIds<'_>, StoreError> {
+        Err(StoreError::Unsupported)
+    }
+
+    /// Remove the object stored under `id`. Returns `Ok(false)` if it was
+    /// absent.
+    ///
+    /// Deleting breaks content addressing for anything that still references
+    /// the object; callers (compaction, GC) are responsible for reachability.
+    ///
+    /// # Errors
+    /// Returns [`StoreError::Unsupported`] unless [`StoreCapabilities::delete`]
+    /// is set.
+    fn delete_object(&mut self, _id: &Hash) -> Result<bool, StoreError> {
+        Err(StoreError::Unsupported)
+    }
+
+    /// Persist bytes under a [`Multihash`] address and return the BLAKE3 id
+    /// they are stored under.
+    ///
+    /// BLAKE3 addresses go straight to [`ObjectStore::put_object`]. Backends
+    /// advertising [`StoreCapabilities::multihash`] also accept other
+    /// algorithms, storing the object under its BLAKE3 id plus an alias.
+    ///
+    /// # Errors
+    /// Returns [`StoreError::Unsupported`] for non-BLAKE3 addresses unless
+    /// [`StoreCapabilities::multihash`] is set, and
+    /// [`StoreError::Corruption`] if `data` does not hash to `id`.
+    fn put_object_by(&mut self, id: &Multihash, data: &[u8]) -> Result<Hash, StoreError> {
+        match id.alg() {
+            HashAlg::Blake3 => self.put_object(id.digest(), data).map(|()| *id.digest()),
+            HashAlg::Sha256 => Err(StoreError::Unsupported),
+        }
+    }
+
+    /// Retrieve bytes by a [`Multihash`] address; see
+    /// [`ObjectStore::put_object_by`].
+    ///
+    /// # Errors
+    /// Returns [`StoreError::Unsupported`] for non-BLAKE3 addresses unless
+    /// [`StoreCapabilities::multihash`] is set, or any backend error.
+    fn get_object_by(&self, id: &Multihash) -> Result<Option<Vec<u8>>, StoreError> {
+        match id.alg() {
+            HashAlg::Blake3 => self.get_object(id.digest()),
+            HashAlg::Sha256 => Err(StoreError::Unsupported),
+        }
+    }
+}
+
+/// Iterator over stored object ids returned by [`ObjectStore::object_ids`].
+pub type ObjectIds<'a> = Box<dyn Iterator<Item = Result<Hash, StoreError>> + 'a>;
+
+/// Optional [`ObjectStore`] operations a backend advertises.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreCapabilities {
+    /// [`ObjectStore::object_ids`] is implemented.
+    pub enumerate: bool,
+    /// [`ObjectStore::delete_object`] is implemented and enabled.
+    pub delete: bool,
+    /// [`ObjectStore::put_object_by`] and [`ObjectStore::get_object_by`]
+    /// accept every [`HashAlg`], not only BLAKE3.
+    pub multihash: bool,
+}
+
+impl StoreCapabilities {
+    /// Only the required `put_object`/`get_object` operations.
+    pub const NONE: Self = Self {
+        enumerate: false,
+        delete: false,
+        multihash: false,
+    };
 }
 
 /// Immutable core content of a commit (unsigned).
@@ -106,6 +267,9 @@ pub struct Signature {
 ///
 /// IMPORTANT: The canonical commit identifier is derived solely from the
 /// serialized `CommitCore`. Signatures do not affect the identifier.
+///
+/// Implements bincode `Encode`/`Decode` by hand (see [`codec`]) because
+/// `SmallVec` has no bincode support.
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Commit {
     pub core: CommitCore,
@@ -113,6 +277,20 @@ pub struct Commit {
     pub sigs: SmallVec<[Signature; 2]>,
 }
 
+/// Most memory a single decode of stored bytes may claim (16 MiB).
+///
+/// Stored objects are untrusted. bincode sizes containers from their length
+/// prefix, so without a limit a forged prefix makes it allocate, or abort on,
+/// whatever the prefix names.
+pub const DECODE_LIMIT: usize = 16 << 20;
+
+/// `config::standard()` bounded by [`DECODE_LIMIT`]. Every decode of stored
+/// bytes uses it; encoding is unaffected by the limit.
+pub(crate) const fn decode_config(
+) -> config::Configuration<config::LittleEndian, config::Varint, config::Limit<DECODE_LIMIT>> {
+    config::standard().with_limit::<DECODE_LIMIT>()
+}
+
 /// Compute the canonical commit identifier for a `Commit`.
 ///
 /// This is defined as the BLAKE3 hash of the canonical bincode encoding of the
@@ -138,10 +316,32 @@ pub fn compute_content_id(core: &CommitCore) -> Result<Hash, bincode::error::Enc
     Ok(blake3::hash(&bytes).into())
 }
 
-// Test support: enable std for unit tests.
-#[cfg(test)]
+// Test support and the `std` feature link std.
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use alloc::collections::BTreeMap;
+
+    /// Minimal map-backed store enforcing the id/content invariant.
+    #[derive(Default)]
+    pub struct MapStore(pub BTreeMap<Hash, Vec<u8>>);
+
+    impl ObjectStore for MapStore {
+        fn put_object(&mut self, id: &Hash, data: &[u8]) -> Result<(), StoreError> {
+            codec::verify_object_id(id, data)?;
+            self.0.insert(*id, data.to_vec());
+            Ok(())
+        }
+
+        fn get_object(&self, id: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
+            Ok(self.0.get(id).cloned())
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,8 +357,30 @@ mod tests {
         }
     }
 
-    // Note: Commit is encoded/decoded via serde formats externally; the
-    // canonical id is derived from the core only, tested below.
+    #[test]
+    fn object_store_defaults() {
+        let mut store = testing::MapStore::default();
+        let (a, b) = (&b"a"[..], &b"b"[..]);
+        let ids = [blake3::hash(a).into(), blake3::hash(b).into()];
+        store.put_objects(&[(ids[0], a), (ids[1], b)]).unwrap();
+        assert!(store.has_object(&ids[1]).unwrap());
+        assert!(!store.has_object(&[0; 32]).unwrap());
+        assert_eq!(
+            store.get_objects(&[ids[0], [0; 32]]).unwrap(),
+            [Some(a.to_vec()), None]
+        );
+        // Batch stops at the first invariant violation.
+        assert_eq!(
+            store.put_objects(&[([9; 32], a)]),
+            Err(StoreError::corruption(&[9; 32], a))
+        );
+        assert_eq!(store.capabilities(), StoreCapabilities::NONE);
+        assert!(matches!(store.object_ids(), Err(StoreError::Unsupported)));
+        assert_eq!(store.delete_object(&ids[0]), Err(StoreError::Unsupported));
+    }
+
+    // Note: Commit encoding lives in `codec`; the canonical id is derived from
+    // the core only, tested below.
 
     #[test]
     fn test_compute_commit_id_invariant_under_signatures() {
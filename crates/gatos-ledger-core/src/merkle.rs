@@ -0,0 +1,497 @@
+//! Merkle accumulator over event ids with inclusion and consistency proofs.
+//!
+//! The tree is the RFC 6962 (Certificate Transparency) Merkle tree with
+//! BLAKE3 in place of SHA-256 and the same domain separation:
+//!
+//! ```text
+//! leaf = blake3(0x00 || event_id)
+//! node = blake3(0x01 || left || right)
+//! root of the empty tree = blake3("")
+//! ```
+//!
+//! [`MerkleAccumulator`] appends in amortized O(1) and keeps only the
+//! Merkle Mountain Range peaks (O(log n) memory); bagging the peaks from the
+//! right yields exactly the RFC 6962 root. [`MerkleLog`] retains all leaves
+//! so it can also produce proofs. Proof verification needs only the proof,
+//! the event id and the roots, so auditors and light clients can check an
+//! event against a checkpoint without access to the store.
+
+use alloc::vec::Vec;
+
+use crate::{get_commit, Hash, ObjectStore, StoreError};
+
+/// Errors produced while building proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofError {
+    /// Leaf index is not below the tree size.
+    IndexOutOfRange,
+    /// Requested tree size exceeds the log, or sizes are out of order.
+    InvalidSize,
+}
+
+/// Hash of a leaf holding `event_id`.
+#[must_use]
+pub fn leaf_hash(event_id: &Hash) -> Hash {
+    let mut h = blake3::Hasher::new();
+    h.update(&[0x00]);
+    h.update(event_id);
+    h.finalize().into()
+}
+
+/// Hash of an interior node.
+#[must_use]
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut h = blake3::Hasher::new();
+    h.update(&[0x01]);
+    h.update(left);
+    h.update(right);
+    h.finalize().into()
+}
+
+fn empty_root() -> Hash {
+    blake3::hash(&[]).into()
+}
+
+/// Append-only accumulator storing only the mountain peaks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MerkleAccumulator {
+    /// Perfect-subtree roots, largest (leftmost) first. Their sizes are the
+    /// set bits of `len`.
+    peaks: Vec<Hash>,
+    len: u64,
+}
+
+impl MerkleAccumulator {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of events appended.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Current mountain peaks, leftmost first.
+    #[must_use]
+    pub fn peaks(&self) -> &[Hash] {
+        &self.peaks
+    }
+
+    /// Append one event id.
+    pub fn push(&mut self, event_id: &Hash) {
+        let mut h = leaf_hash(event_id);
+        let mut n = self.len;
+        while n & 1 == 1 {
+            let left = self
+                .peaks
+                .pop()
+                .expect("peak count matches set bits of len");
+            h = node_hash(&left, &h);
+            n >>= 1;
+        }
+        self.peaks.push(h);
+        self.len += 1;
+    }
+
+    /// Root over all appended events.
+    #[must_use]
+    pub fn root(&self) -> Hash {
+        let mut peaks = self.peaks.iter().rev();
+        let Some(last) = peaks.next() else {
+            return empty_root();
+        };
+        peaks.fold(*last, |acc, peak| node_hash(peak, &acc))
+    }
+}
+
+/// A Merkle log retaining every leaf, able to produce proofs for any prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MerkleLog {
+    leaves: Vec<Hash>,
+}
+
+impl MerkleLog {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a log over `event_ids` in order.
+    pub fn from_event_ids<'a>(event_ids: impl IntoIterator<Item = &'a Hash>) -> Self {
+        Self {
+            leaves: event_ids.into_iter().map(leaf_hash).collect(),
+        }
+    }
+
+    /// Build a log over the ledger window `start..=end`, oldest first, by
+    /// walking parent links back from `end`.
+    ///
+    /// # Errors
+    /// Returns [`StoreError::NotFound`] naming the missing commit if the walk
+    /// leaves the store, or naming `start` if it reaches a root without
+    /// meeting `start`; any error from [`get_commit`] is propagated.
+    pub fn from_chain_window<S: ObjectStore + ?Sized>(
+        store: &S,
+        start: &Hash,
+        end: &Hash,
+    ) -> Result<Self, StoreError> {
+        let mut ids = Vec::new();
+        let mut current = *end;
+        loop {
+            let commit = get_commit(store, &current)?.ok_or(StoreError::NotFound(current))?;
+            ids.push(current);
+            if &current == start {
+                break;
+            }
+            current = commit.core.parent.ok_or(StoreError::NotFound(*start))?;
+        }
+        ids.reverse();
+        Ok(Self::from_event_ids(&ids))
+    }
+
+    pub fn push(&mut self, event_id: &Hash) {
+        self.leaves.push(leaf_hash(event_id));
+    }
+
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Root over all events.
+    #[must_use]
+    pub fn root(&self) -> Hash {
+        subtree_root(&self.leaves)
+    }
+
+    /// Root over the first `size` events.
+    ///
+    /// # Errors
+    /// Returns [`ProofError::InvalidSize`] if `size` exceeds the log length.
+    pub fn root_at(&self, size: u64) -> Result<Hash, ProofError> {
+        Ok(subtree_root(self.prefix(size)?))
+    }
+
+    /// Prove that event `index` is in the tree of the first `tree_size` events.
+    ///
+    /// # Errors
+    /// Returns [`ProofError`] if `tree_size` exceeds the log or `index` is
+    /// not below it.
+    pub fn inclusion_proof(
+        &self,
+        index: u64,
+        tree_size: u64,
+    ) -> Result<InclusionProof, ProofError> {
+        let leaves = self.prefix(tree_size)?;
+        if index >= tree_size {
+            return Err(ProofError::IndexOutOfRange);
+        }
+        let mut path = Vec::new();
+        inclusion_path(index as usize, leaves, &mut path);
+        Ok(InclusionProof {
+            index,
+            tree_size,
+            path,
+        })
+    }
+
+    /// Prove that the tree of the first `old_size` events is a prefix of the
+    /// tree of the first `new_size` events.
+    ///
+    /// # Errors
+    /// Returns [`ProofError::InvalidSize`] unless `old_size <= new_size <= len`.
+    pub fn consistency_proof(
+        &self,
+        old_size: u64,
+        new_size: u64,
+    ) -> Result<ConsistencyProof, ProofError> {
+        let leaves = self.prefix(new_size)?;
+        if old_size > new_size {
+            return Err(ProofError::InvalidSize);
+        }
+        let mut path = Vec::new();
+        if old_size > 0 && old_size < new_size {
+            consistency_path(old_size as usize, leaves, true, &mut path);
+        }
+        Ok(ConsistencyProof {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+
+    fn prefix(&self, size: u64) -> Result<&[Hash], ProofError> {
+        usize::try_from(size)
+            .ok()
+            .and_then(|n| self.leaves.get(..n))
+            .ok_or(ProofError::InvalidSize)
+    }
+}
+
+/// Largest power of two strictly below `n` (`n >= 2`).
+fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+fn subtree_root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => empty_root(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&subtree_root(&leaves[..k]), &subtree_root(&leaves[k..]))
+        }
+    }
+}
+
+/// RFC 6962 §2.1.1 `PATH(m, D[n])`, siblings ordered leaf to root.
+fn inclusion_path(m: usize, leaves: &[Hash], out: &mut Vec<Hash>) {
+    let n = leaves.len();
+    if n <= 1 {
+        return;
+    }
+    let k = split_point(n);
+    if m < k {
+        inclusion_path(m, &leaves[..k], out);
+        out.push(subtree_root(&leaves[k..]));
+    } else {
+        inclusion_path(m - k, &leaves[k..], out);
+        out.push(subtree_root(&leaves[..k]));
+    }
+}
+
+/// RFC 6962 §2.1.2 `SUBPROOF(m, D[n], b)`.
+fn consistency_path(m: usize, leaves: &[Hash], complete: bool, out: &mut Vec<Hash>) {
+    let n = leaves.len();
+    if m == n {
+        if !complete {
+            out.push(subtree_root(leaves));
+        }
+        return;
+    }
+    let k = split_point(n);
+    if m <= k {
+        consistency_path(m, &leaves[..k], complete, out);
+        out.push(subtree_root(&leaves[k..]));
+    } else {
+        consistency_path(m - k, &leaves[k..], false, out);
+        out.push(subtree_root(&leaves[..k]));
+    }
+}
+
+/// Proof that one event is included in a tree of `tree_size` events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub index: u64,
+    pub tree_size: u64,
+    /// Sibling hashes from the leaf up to the root.
+    pub path: Vec<Hash>,
+}
+
+impl InclusionProof {
+    /// Check that `event_id` sits at `index` in the tree with root `root`
+    /// (RFC 9162 §2.1.3.2).
+    #[must_use]
+    pub fn verify(&self, event_id: &Hash, root: &Hash) -> bool {
+        if self.index >= self.tree_size {
+            return false;
+        }
+        let (mut fnode, mut snode) = (self.index, self.tree_size - 1);
+        let mut r = leaf_hash(event_id);
+        for p in &self.path {
+            if snode == 0 {
+                return false;
+            }
+            if fnode & 1 == 1 || fnode == snode {
+                r = node_hash(p, &r);
+                while fnode & 1 == 0 && fnode != 0 {
+                    fnode >>= 1;
+                    snode >>= 1;
+                }
+            } else {
+                r = node_hash(&r, p);
+            }
+            fnode >>= 1;
+            snode >>= 1;
+        }
+        snode == 0 && &r == root
+    }
+}
+
+/// Proof that a tree of `old_size` events is a prefix of one of `new_size`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub old_size: u64,
+    pub new_size: u64,
+    pub path: Vec<Hash>,
+}
+
+impl ConsistencyProof {
+    /// Check the proof against both roots (RFC 9162 §2.1.4.2).
+    #[must_use]
+    pub fn verify(&self, old_root: &Hash, new_root: &Hash) -> bool {
+        let (first, second) = (self.old_size, self.new_size);
+        if first > second {
+            return false;
+        }
+        if first == second {
+            return self.path.is_empty() && old_root == new_root;
+        }
+        if first == 0 {
+            // Every tree extends the empty tree.
+            return self.path.is_empty() && old_root == &empty_root();
+        }
+        let mut path = self.path.iter();
+        let seed = if first.is_power_of_two() {
+            old_root
+        } else {
+            match path.next() {
+                Some(h) => h,
+                None => return false,
+            }
+        };
+        let (mut fnode, mut snode) = (first - 1, second - 1);
+        while fnode & 1 == 1 {
+            fnode >>= 1;
+            snode >>= 1;
+        }
+        let (mut fr, mut sr) = (*seed, *seed);
+        for c in path {
+            if snode == 0 {
+                return false;
+            }
+            if fnode & 1 == 1 || fnode == snode {
+                fr = node_hash(c, &fr);
+                sr = node_hash(c, &sr);
+                while fnode & 1 == 0 && fnode != 0 {
+                    fnode >>= 1;
+                    snode >>= 1;
+                }
+            } else {
+                sr = node_hash(&sr, c);
+            }
+            fnode >>= 1;
+            snode >>= 1;
+        }
+        snode == 0 && &fr == old_root && &sr == new_root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(n: u8) -> Vec<Hash> {
+        (0..n).map(|i| blake3::hash(&[i]).into()).collect()
+    }
+
+    #[test]
+    fn accumulator_matches_log_root() {
+        let ids = ids(33);
+        let mut acc = MerkleAccumulator::new();
+        assert_eq!(acc.root(), MerkleLog::new().root());
+        for (i, id) in ids.iter().enumerate() {
+            acc.push(id);
+            let log = MerkleLog::from_event_ids(&ids[..=i]);
+            assert_eq!(acc.root(), log.root(), "size {}", i + 1);
+            assert_eq!(acc.peaks().len() as u32, acc.len().count_ones());
+        }
+    }
+
+    #[test]
+    fn small_tree_shape() {
+        let ids = ids(3);
+        let l: Vec<Hash> = ids.iter().map(leaf_hash).collect();
+        let expected = node_hash(&node_hash(&l[0], &l[1]), &l[2]);
+        assert_eq!(MerkleLog::from_event_ids(&ids).root(), expected);
+    }
+
+    #[test]
+    fn inclusion_proofs_verify_for_every_leaf() {
+        let ids = ids(13);
+        let log = MerkleLog::from_event_ids(&ids);
+        for size in 1..=13u64 {
+            let root = log.root_at(size).unwrap();
+            for index in 0..size {
+                let proof = log.inclusion_proof(index, size).unwrap();
+                assert!(proof.verify(&ids[index as usize], &root), "{index}/{size}");
+                // Wrong event or wrong root fails.
+                let other = ids[((index + 1) % 13) as usize];
+                assert!(!proof.verify(&other, &root));
+                assert!(!proof.verify(&ids[index as usize], &[0; 32]));
+            }
+        }
+        assert_eq!(log.inclusion_proof(5, 5), Err(ProofError::IndexOutOfRange));
+        assert_eq!(log.inclusion_proof(0, 14), Err(ProofError::InvalidSize));
+    }
+
+    #[test]
+    fn consistency_proofs_verify_for_every_prefix() {
+        let ids = ids(13);
+        let log = MerkleLog::from_event_ids(&ids);
+        for new in 0..=13u64 {
+            let new_root = log.root_at(new).unwrap();
+            for old in 0..=new {
+                let old_root = log.root_at(old).unwrap();
+                let proof = log.consistency_proof(old, new).unwrap();
+                assert!(proof.verify(&old_root, &new_root), "{old}->{new}");
+                if old > 0 && old < new {
+                    assert!(!proof.verify(&new_root, &new_root), "{old}->{new}");
+                }
+            }
+        }
+        assert_eq!(log.consistency_proof(4, 3), Err(ProofError::InvalidSize));
+    }
+
+    #[test]
+    fn tampered_proof_fails() {
+        let ids = ids(7);
+        let log = MerkleLog::from_event_ids(&ids);
+        let mut proof = log.inclusion_proof(3, 7).unwrap();
+        proof.path[1][0] ^= 1;
+        assert!(!proof.verify(&ids[3], &log.root()));
+        let mut proof = log.consistency_proof(3, 7).unwrap();
+        proof.path.pop();
+        assert!(!proof.verify(&log.root_at(3).unwrap(), &log.root()));
+    }
+
+    #[test]
+    fn window_from_chain() {
+        use crate::{put_commit, Commit, CommitCore, MemoryStore};
+        use std::string::ToString;
+
+        let mut store = MemoryStore::new();
+        let mut chain: Vec<Hash> = Vec::new();
+        for ts in 0..5 {
+            let commit = Commit {
+                core: CommitCore {
+                    parent: chain.last().copied(),
+                    tree: [0; 32],
+                    message: "e".to_string(),
+                    timestamp: ts,
+                },
+                sigs: smallvec::SmallVec::new(),
+            };
+            chain.push(put_commit(&mut store, &commit).unwrap());
+        }
+        let log = MerkleLog::from_chain_window(&store, &chain[1], &chain[3]).unwrap();
+        assert_eq!(log.root(), MerkleLog::from_event_ids(&chain[1..=3]).root());
+        // `start` not an ancestor of `end`.
+        assert_eq!(
+            MerkleLog::from_chain_window(&store, &chain[4], &chain[3]),
+            Err(StoreError::NotFound(chain[4]))
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Algorithm-tagged digests (multihash).
+//!
+//! The spec mixes `blake3:` ids with `sha256:` roots (`Fold-Root`,
+//! `Policy-Code-Root`, job `policy_root`). [`Multihash`] carries either, so
+//! both are parsed, compared and verified by the same code:
+//!
+//! ```text
+//! text   = <alg>:<64 lowercase hex>              (as Blake3Digest/Sha256Digest)
+//! binary = varint(code) || varint(32) || digest  (multiformats multihash)
+//! ```
+//!
+//! [`Hash`] stays the BLAKE3 object id used by [`ObjectStore`](crate::ObjectStore);
+//! stores that accept other algorithms alias them to that id (see
+//! [`ObjectStore::put_object_by`](crate::ObjectStore::put_object_by)).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use bincode::{Decode, Encode};
+use sha2::Digest as _;
+
+use crate::cid::{put_varint, take_varint};
+use crate::ids::{hex_decode_32, hex_encode, impl_serde_via_str};
+use crate::{cid, object_id_matches, Blake3Digest, Hash, IdError, Sha256Digest, StoreError};
+
+/// A supported digest algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+pub enum HashAlg {
+    Blake3,
+    Sha256,
+}
+
+impl HashAlg {
+    /// Multicodec code.
+    #[must_use]
+    pub const fn code(self) -> u64 {
+        match self {
+            Self::Blake3 => cid::BLAKE3,
+            Self::Sha256 => cid::SHA2_256,
+        }
+    }
+
+    /// Text prefix, without the trailing `:`.
+    #[must_use]
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::Blake3 => Blake3Digest::PREFIX,
+            Self::Sha256 => Sha256Digest::PREFIX,
+        }
+    }
+
+    #[must_use]
+    pub fn from_code(code: u64) -> Option<Self> {
+        [Self::Blake3, Self::Sha256]
+            .into_iter()
+            .find(|a| a.code() == code)
+    }
+
+    #[must_use]
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        [Self::Blake3, Self::Sha256]
+            .into_iter()
+            .find(|a| a.prefix() == prefix)
+    }
+
+    /// Digest `data` with this algorithm.
+    #[must_use]
+    pub fn digest(self, data: &[u8]) -> Hash {
+        match self {
+            Self::Blake3 => blake3::hash(data).into(),
+            Self::Sha256 => sha2::Sha256::digest(data).into(),
+        }
+    }
+}
+
+/// A 256-bit digest tagged with its algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+pub struct Multihash {
+    alg: HashAlg,
+    digest: Hash,
+}
+
+impl Multihash {
+    #[must_use]
+    pub const fn new(alg: HashAlg, digest: Hash) -> Self {
+        Self { alg, digest }
+    }
+
+    /// Digest of `data` under `alg`.
+    #[must_use]
+    pub fn of(alg: HashAlg, data: &[u8]) -> Self {
+        Self::new(alg, alg.digest(data))
+    }
+
+    #[must_use]
+    pub const fn alg(&self) -> HashAlg {
+        self.alg
+    }
+
+    #[must_use]
+    pub const fn digest(&self) -> &Hash {
+        &self.digest
+    }
+
+    /// `true` if `data` hashes to this digest under its algorithm.
+    #[must_use]
+    pub fn verify(&self, data: &[u8]) -> bool {
+        self.alg.digest(data) == self.digest
+    }
+
+    /// `true` if `data` may be stored under this address: the plain digest
+    /// check, plus the commit content-id rule of
+    /// [`object_id_matches`] for BLAKE3.
+    #[must_use]
+    pub fn addresses(&self, data: &[u8]) -> bool {
+        match self.alg {
+            HashAlg::Blake3 => object_id_matches(&self.digest, data),
+            HashAlg::Sha256 => self.verify(data),
+        }
+    }
+
+    /// Binary multihash: `varint(code) || varint(32) || digest`.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(34);
+        put_varint(&mut out, self.alg.code());
+        put_varint(&mut out, self.digest.len() as u64);
+        out.extend_from_slice(&self.digest);
+        out
+    }
+
+    /// Parse a binary multihash. The input must contain exactly one.
+    ///
+    /// # Errors
+    /// Returns [`IdError::WrongAlgorithm`] for unsupported codes and
+    /// [`IdError::Length`] for malformed lengths.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdError> {
+        let mut rest = bytes;
+        let code = take_varint(&mut rest).map_err(|_| IdError::Length)?;
+        let alg = HashAlg::from_code(code).ok_or(IdError::WrongAlgorithm)?;
+        let len = take_varint(&mut rest).map_err(|_| IdError::Length)?;
+        if len != 32 {
+            return Err(IdError::Length);
+        }
+        let digest = rest.try_into().map_err(|_| IdError::Length)?;
+        Ok(Self::new(alg, digest))
+    }
+
+    /// Bare lowercase hex, without the prefix.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        hex_encode(&self.digest)
+    }
+}
+
+impl From<Blake3Digest> for Multihash {
+    fn from(d: Blake3Digest) -> Self {
+        Self::new(HashAlg::Blake3, d.0)
+    }
+}
+
+impl From<Sha256Digest> for Multihash {
+    fn from(d: Sha256Digest) -> Self {
+        Self::new(HashAlg::Sha256, d.0)
+    }
+}
+
+impl TryFrom<Multihash> for Blake3Digest {
+    type Error = IdError;
+
+    fn try_from(m: Multihash) -> Result<Self, Self::Error> {
+        match m.alg {
+            HashAlg::Blake3 => Ok(Self(m.digest)),
+            HashAlg::Sha256 => Err(IdError::WrongAlgorithm),
+        }
+    }
+}
+
+impl TryFrom<Multihash> for Sha256Digest {
+    type Error = IdError;
+
+    fn try_from(m: Multihash) -> Result<Self, Self::Error> {
+        match m.alg {
+            HashAlg::Sha256 => Ok(Self(m.digest)),
+            HashAlg::Blake3 => Err(IdError::WrongAlgorithm),
+        }
+    }
+}
+
+impl fmt::Display for Multihash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.alg.prefix(), self.to_hex())
+    }
+}
+
+impl FromStr for Multihash {
+    type Err = IdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, body) = s.split_once(':').ok_or(IdError::MissingPrefix)?;
+        let alg = HashAlg::from_prefix(prefix).ok_or(IdError::WrongAlgorithm)?;
+        hex_decode_32(body).map(|digest| Self::new(alg, digest))
+    }
+}
+
+impl_serde_via_str!(Multihash, "a `blake3:<hex>` or `sha256:<hex>` digest");
+
+/// [`Multihash::addresses`] as a `Result`, for use at the top of
+/// [`ObjectStore::put_object_by`](crate::ObjectStore::put_object_by)
+/// implementations.
+///
+/// # Errors
+/// Returns [`StoreError::Corruption`] with `actual` set to the digest of
+/// `data` under `id`'s algorithm if `data` may not be stored under `id`.
+pub fn verify_object_address(id: &Multihash, data: &[u8]) -> Result<(), StoreError> {
+    if id.addresses(data) {
+        Ok(())
+    } else {
+        Err(StoreError::Corruption {
+            expected: id.digest,
+            actual: id.alg.digest(data),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn sha256_and_blake3_share_verification() {
+        // FIPS 180-2 test vector.
+        let abc = Multihash::of(HashAlg::Sha256, b"abc");
+        assert_eq!(
+            abc.to_string(),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(abc.verify(b"abc"));
+        assert!(!abc.verify(b"abd"));
+        let b3 = Multihash::of(HashAlg::Blake3, b"abc");
+        assert_eq!(
+            Blake3Digest::try_from(b3),
+            Ok(Blake3Digest::new(blake3::hash(b"abc").into()))
+        );
+        assert_eq!(Sha256Digest::try_from(b3), Err(IdError::WrongAlgorithm));
+
+        // A parsed `Fold-Root` verifies through the same type.
+        let root: Sha256Digest = abc.to_string().parse().unwrap();
+        assert!(Multihash::from(root).verify(b"abc"));
+    }
+
+    #[test]
+    fn text_and_binary_roundtrip() {
+        for alg in [HashAlg::Blake3, HashAlg::Sha256] {
+            let mh = Multihash::of(alg, b"x");
+            assert_eq!(mh.to_string().parse::<Multihash>(), Ok(mh));
+            let bytes = mh.to_bytes();
+            assert_eq!(bytes[..2], [alg.code() as u8, 0x20]);
+            assert_eq!(Multihash::from_bytes(&bytes), Ok(mh));
+        }
+        assert_eq!("md5:00".parse::<Multihash>(), Err(IdError::WrongAlgorithm));
+        assert_eq!(
+            Multihash::from_bytes(&[0x13, 0x20]),
+            Err(IdError::WrongAlgorithm)
+        );
+        assert_eq!(
+            Multihash::from_bytes(&[0x12, 0x20, 0]),
+            Err(IdError::Length)
+        );
+    }
+}
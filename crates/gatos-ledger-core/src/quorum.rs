@@ -0,0 +1,133 @@
+//! Threshold (N-of-M) signature checks over [`Commit::sigs`].
+//!
+//! Used for trust-data updates and state checkpoints that must be signed by a
+//! quorum of known maintainers (e.g., "2 of 3"). Each allowed signer counts at
+//! most once; signatures from keys outside the allowed set never count.
+
+use alloc::vec::Vec;
+
+use crate::{compute_content_id, Commit, PubKey, VerifyError};
+
+/// Result of checking a commit's signatures against an N-of-M quorum.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThresholdVerdict {
+    /// Number of distinct valid signers required.
+    pub threshold: usize,
+    /// Distinct allowed signers with a valid signature, in signature order.
+    pub counted: Vec<PubKey>,
+    /// Allowed signers that signed more than once; extra copies are ignored.
+    pub duplicates: Vec<PubKey>,
+    /// Signers outside the allowed set; rejected regardless of validity.
+    pub unknown: Vec<PubKey>,
+    /// Allowed signers whose signature failed verification.
+    pub invalid: Vec<(PubKey, VerifyError)>,
+}
+
+impl ThresholdVerdict {
+    /// Returns `true` when at least `threshold` distinct allowed signers
+    /// produced valid signatures. A zero threshold is never met.
+    #[must_use]
+    pub fn is_met(&self) -> bool {
+        self.threshold > 0 && self.counted.len() >= self.threshold
+    }
+}
+
+impl Commit {
+    /// Check whether at least `threshold` distinct signers from `allowed`
+    /// signed this commit's content id.
+    ///
+    /// # Errors
+    /// Returns an error if the core cannot be serialized.
+    pub fn verify_threshold(
+        &self,
+        allowed: &[PubKey],
+        threshold: usize,
+    ) -> Result<ThresholdVerdict, bincode::error::EncodeError> {
+        let id = compute_content_id(&self.core)?;
+        let mut verdict = ThresholdVerdict {
+            threshold,
+            ..ThresholdVerdict::default()
+        };
+        for sig in &self.sigs {
+            if !allowed.contains(&sig.signer) {
+                if !verdict.unknown.contains(&sig.signer) {
+                    verdict.unknown.push(sig.signer);
+                }
+                continue;
+            }
+            if verdict.counted.contains(&sig.signer) {
+                verdict.duplicates.push(sig.signer);
+                continue;
+            }
+            match sig.verify(&id) {
+                Ok(()) => verdict.counted.push(sig.signer),
+                Err(e) => verdict.invalid.push((sig.signer, e)),
+            }
+        }
+        Ok(verdict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ed25519_public_key, CommitCore, Signature};
+    use smallvec::SmallVec;
+    use std::string::ToString;
+
+    fn commit() -> Commit {
+        Commit {
+            core: CommitCore {
+                parent: Some([0x11; 32]),
+                tree: [0x22; 32],
+                message: "trust update".to_string(),
+                timestamp: 1_725_000_000,
+            },
+            sigs: SmallVec::new(),
+        }
+    }
+
+    fn maintainers() -> [PubKey; 3] {
+        [
+            ed25519_public_key(&[1; 32]),
+            ed25519_public_key(&[2; 32]),
+            ed25519_public_key(&[3; 32]),
+        ]
+    }
+
+    #[test]
+    fn two_of_three_met() {
+        let mut c = commit();
+        c.sign(&[1; 32]).unwrap();
+        c.sign(&[3; 32]).unwrap();
+        let verdict = c.verify_threshold(&maintainers(), 2).unwrap();
+        assert!(verdict.is_met());
+        assert_eq!(verdict.counted, [maintainers()[0], maintainers()[2]]);
+    }
+
+    #[test]
+    fn duplicates_and_unknown_signers_do_not_count() {
+        let mut c = commit();
+        c.sign(&[1; 32]).unwrap();
+        c.sign(&[1; 32]).unwrap();
+        c.sign(&[9; 32]).unwrap();
+        // Allowed signer, but signature over another id.
+        c.sigs.push(Signature::sign(&[2; 32], &[0u8; 32]));
+
+        let verdict = c.verify_threshold(&maintainers(), 2).unwrap();
+        assert!(!verdict.is_met());
+        assert_eq!(verdict.counted, [maintainers()[0]]);
+        assert_eq!(verdict.duplicates, [maintainers()[0]]);
+        assert_eq!(verdict.unknown, [ed25519_public_key(&[9; 32])]);
+        assert_eq!(
+            verdict.invalid,
+            [(maintainers()[1], VerifyError::BadSignature)]
+        );
+    }
+
+    #[test]
+    fn zero_threshold_is_never_met() {
+        let verdict = commit().verify_threshold(&maintainers(), 0).unwrap();
+        assert!(!verdict.is_met());
+    }
+}
@@ -0,0 +1,318 @@
+//! In-process [`ObjectStore`] backends.
+//!
+//! - [`MemoryStore`]: unbounded, `BTreeMap`-backed; for tests and tools.
+//! - [`HeaplessStore`]: fixed capacity with no heap growth; for embedded
+//!   targets without a filesystem. Objects live in one inline byte arena of
+//!   `BYTES` bytes, indexed by up to `N` ids kept sorted for binary search.
+//!
+//! Both enforce the id/content invariant via [`verify_object_id`] and
+//! support enumeration and deletion. [`MemoryStore`] additionally accepts
+//! SHA-256 addresses, kept as aliases of the BLAKE3 id.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{
+    verify_object_address, verify_object_id, Hash, HashAlg, Multihash, ObjectIds, ObjectStore,
+    StoreCapabilities, StoreError,
+};
+
+const CAPABILITIES: StoreCapabilities = StoreCapabilities {
+    enumerate: true,
+    delete: true,
+    multihash: false,
+};
+
+/// Unbounded in-memory store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    objects: BTreeMap<Hash, Vec<u8>>,
+    /// Non-BLAKE3 addresses and the BLAKE3 id they resolve to.
+    aliases: BTreeMap<Multihash, Hash>,
+}
+
+impl MemoryStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    fn put_object(&mut self, id: &Hash, data: &[u8]) -> Result<(), StoreError> {
+        verify_object_id(id, data)?;
+        self.objects.insert(*id, data.to_vec());
+        Ok(())
+    }
+
+    fn get_object(&self, id: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self.objects.get(id).cloned())
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            multihash: true,
+            ..CAPABILITIES
+        }
+    }
+
+    fn has_object(&self, id: &Hash) -> Result<bool, StoreError> {
+        Ok(self.objects.contains_key(id))
+    }
+
+    fn object_ids(&self) -> Result<ObjectIds<'_>, StoreError> {
+        Ok(Box::new(self.objects.keys().copied().map(Ok)))
+    }
+
+    fn delete_object(&mut self, id: &Hash) -> Result<bool, StoreError> {
+        self.aliases.retain(|_, target| target != id);
+        Ok(self.objects.remove(id).is_some())
+    }
+
+    fn put_object_by(&mut self, id: &Multihash, data: &[u8]) -> Result<Hash, StoreError> {
+        if id.alg() == HashAlg::Blake3 {
+            return self.put_object(id.digest(), data).map(|()| *id.digest());
+        }
+        verify_object_address(id, data)?;
+        let blake3_id = blake3::hash(data).into();
+        self.put_object(&blake3_id, data)?;
+        self.aliases.insert(*id, blake3_id);
+        Ok(blake3_id)
+    }
+
+    fn get_object_by(&self, id: &Multihash) -> Result<Option<Vec<u8>>, StoreError> {
+        match id.alg() {
+            HashAlg::Blake3 => self.get_object(id.digest()),
+            HashAlg::Sha256 => Ok(self
+                .aliases
+                .get(id)
+                .and_then(|target| self.objects.get(target))
+                .cloned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Slot {
+    id: Hash,
+    offset: usize,
+    len: usize,
+}
+
+/// Fixed-capacity store holding at most `N` objects and `BYTES` bytes of
+/// content in total, without heap allocation for storage.
+///
+/// Puts beyond either limit fail with [`StoreError::CapacityExceeded`] and
+/// leave the store unchanged. Deleting compacts the arena.
+#[derive(Debug, Clone)]
+pub struct HeaplessStore<const N: usize, const BYTES: usize> {
+    index: heapless::Vec<Slot, N>,
+    arena: heapless::Vec<u8, BYTES>,
+}
+
+impl<const N: usize, const BYTES: usize> Default for HeaplessStore<N, BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const BYTES: usize> HeaplessStore<N, BYTES> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            index: heapless::Vec::new(),
+            arena: heapless::Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Content bytes currently in use.
+    #[must_use]
+    pub fn bytes_used(&self) -> usize {
+        self.arena.len()
+    }
+
+    fn find(&self, id: &Hash) -> Result<usize, usize> {
+        self.index.binary_search_by(|s| s.id.cmp(id))
+    }
+
+    fn content(&self, slot: &Slot) -> &[u8] {
+        &self.arena[slot.offset..slot.offset + slot.len]
+    }
+
+    fn remove_at(&mut self, pos: usize) {
+        let slot = self.index.remove(pos);
+        let end = slot.offset + slot.len;
+        let tail = self.arena.len() - end;
+        self.arena.copy_within(end.., slot.offset);
+        self.arena.truncate(slot.offset + tail);
+        for s in self.index.iter_mut().filter(|s| s.offset > slot.offset) {
+            s.offset -= slot.len;
+        }
+    }
+}
+
+impl<const N: usize, const BYTES: usize> ObjectStore for HeaplessStore<N, BYTES> {
+    fn put_object(&mut self, id: &Hash, data: &[u8]) -> Result<(), StoreError> {
+        verify_object_id(id, data)?;
+        let existing = match self.find(id) {
+            Ok(pos) if self.content(&self.index[pos]) == data => return Ok(()),
+            Ok(pos) => Some(pos),
+            Err(_) => None,
+        };
+        // Check capacity up front so a failed put leaves the store unchanged.
+        let freed = existing.map_or(0, |pos| self.index[pos].len);
+        let slots_needed = usize::from(existing.is_none());
+        if self.arena.len() - freed + data.len() > BYTES || self.index.len() + slots_needed > N {
+            return Err(StoreError::CapacityExceeded);
+        }
+        if let Some(pos) = existing {
+            self.remove_at(pos);
+        }
+        let slot = Slot {
+            id: *id,
+            offset: self.arena.len(),
+            len: data.len(),
+        };
+        self.arena
+            .extend_from_slice(data)
+            .map_err(|()| StoreError::Invariant("arena space checked"))?;
+        let pos = self.find(id).unwrap_or_else(|p| p);
+        self.index
+            .insert(pos, slot)
+            .map_err(|_| StoreError::Invariant("index slot checked"))
+    }
+
+    fn get_object(&self, id: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self
+            .find(id)
+            .ok()
+            .map(|pos| self.content(&self.index[pos]).to_vec()))
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        CAPABILITIES
+    }
+
+    fn has_object(&self, id: &Hash) -> Result<bool, StoreError> {
+        Ok(self.find(id).is_ok())
+    }
+
+    fn object_ids(&self) -> Result<ObjectIds<'_>, StoreError> {
+        Ok(Box::new(self.index.iter().map(|s| Ok(s.id))))
+    }
+
+    fn delete_object(&mut self, id: &Hash) -> Result<bool, StoreError> {
+        match self.find(id) {
+            Ok(pos) => {
+                self.remove_at(pos);
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(data: &[u8]) -> Hash {
+        blake3::hash(data).into()
+    }
+
+    fn exercise(store: &mut impl ObjectStore) {
+        let (a, b) = (&b"alpha"[..], &b"beta"[..]);
+        assert_eq!(
+            store.put_object(&id(b), a),
+            Err(StoreError::corruption(&id(b), a))
+        );
+        store.put_object(&id(a), a).unwrap();
+        store.put_object(&id(b), b).unwrap();
+        store.put_object(&id(a), a).unwrap(); // idempotent
+        assert_eq!(store.get_object(&id(b)).unwrap().as_deref(), Some(b));
+        assert!(store.has_object(&id(a)).unwrap());
+        assert_eq!(store.object_ids().unwrap().count(), 2);
+
+        assert!(store.delete_object(&id(a)).unwrap());
+        assert!(!store.delete_object(&id(a)).unwrap());
+        assert_eq!(store.get_object(&id(a)).unwrap(), None);
+        assert_eq!(store.get_object(&id(b)).unwrap().as_deref(), Some(b));
+    }
+
+    #[test]
+    fn memory_store() {
+        let mut store = MemoryStore::new();
+        exercise(&mut store);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn memory_store_sha256_addresses() {
+        let mut store = MemoryStore::new();
+        let data = &b"policy code"[..];
+        let sha = Multihash::of(HashAlg::Sha256, data);
+        assert_eq!(store.put_object_by(&sha, data).unwrap(), id(data));
+        assert_eq!(store.get_object_by(&sha).unwrap().as_deref(), Some(data));
+        let b3 = Multihash::of(HashAlg::Blake3, data);
+        assert_eq!(store.get_object_by(&b3).unwrap().as_deref(), Some(data));
+        assert!(matches!(
+            store.put_object_by(&sha, b"other"),
+            Err(StoreError::Corruption { expected, .. }) if expected == *sha.digest()
+        ));
+        assert!(store.delete_object(&id(data)).unwrap());
+        assert_eq!(store.get_object_by(&sha).unwrap(), None);
+
+        // Stores without the capability only take BLAKE3 addresses.
+        let mut heapless = HeaplessStore::<2, 64>::new();
+        assert_eq!(heapless.put_object_by(&b3, data), Ok(id(data)));
+        assert_eq!(heapless.get_object_by(&sha), Err(StoreError::Unsupported));
+    }
+
+    #[test]
+    fn heapless_store() {
+        let mut store = HeaplessStore::<4, 64>::new();
+        exercise(&mut store);
+        assert_eq!(store.bytes_used(), 4);
+    }
+
+    #[test]
+    fn heapless_capacity_limits() {
+        let mut store = HeaplessStore::<2, 8>::new();
+        store.put_object(&id(b"1234"), b"1234").unwrap();
+        assert_eq!(
+            store.put_object(&id(b"56789"), b"56789"),
+            Err(StoreError::CapacityExceeded)
+        );
+        store.put_object(&id(b"5678"), b"5678").unwrap();
+        assert_eq!(
+            store.put_object(&id(b""), b""),
+            Err(StoreError::CapacityExceeded)
+        );
+        // Deleting frees both a slot and arena space; survivors stay intact.
+        assert!(store.delete_object(&id(b"1234")).unwrap());
+        store.put_object(&id(b"abcd"), b"abcd").unwrap();
+        assert_eq!(store.get_object(&id(b"5678")).unwrap().unwrap(), b"5678");
+        assert_eq!(store.get_object(&id(b"abcd")).unwrap().unwrap(), b"abcd");
+    }
+}
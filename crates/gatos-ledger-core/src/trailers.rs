@@ -0,0 +1,702 @@
+//! Git trailers carrying GATOS commit metadata (SPEC §4.1, §5.3, §19.3, §20.2).
+//!
+//! Each artifact kind (ledger event, state checkpoint, job result, proposal,
+//! approval, grant, revocation) has a typed trailer set. Parsing extracts the trailer
+//! block from a commit message, requires the normative fields, validates the
+//! prefixed encodings (see [`crate::ids`]) and ignores unknown trailers.
+//! Emitting writes the known trailers in canonical order, which is the order
+//! the SPEC lists them in.
+//!
+//! Trailer keys match case-insensitively, as `git interpret-trailers` does;
+//! a known key that appears twice is rejected rather than guessed at, unless
+//! the set declares it repeated (one trailer per value, e.g. `Commit-Sig`).
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::ids::{hex_decode, hex_encode, Blake3Digest, Ed25519Key, Sha256Digest};
+use crate::{Cid, SigAlg, Signature};
+
+/// Errors produced while reading a trailer set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrailerError {
+    /// A required trailer is absent.
+    Missing(&'static str),
+    /// A known trailer appears more than once.
+    Duplicate(&'static str),
+    /// A known trailer has a malformed value.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for TrailerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(k) => write!(f, "missing required trailer `{k}`"),
+            Self::Duplicate(k) => write!(f, "duplicate trailer `{k}`"),
+            Self::Invalid(k) => write!(f, "malformed value for trailer `{k}`"),
+        }
+    }
+}
+
+/// A git object id as written in `Ledger-Head`/`Policy-Root`: lowercase hex,
+/// 40 digits (SHA-1 repositories) or 64 digits (SHA-256 repositories).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CommitOid(String);
+
+impl CommitOid {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for CommitOid {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f'));
+        if hex && matches!(s.len(), 40 | 64) {
+            Ok(Self(s.to_string()))
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for CommitOid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A value that can appear on the right-hand side of a trailer.
+pub trait TrailerValue: Sized {
+    /// Parse a trimmed trailer value; `None` if malformed.
+    fn parse_value(s: &str) -> Option<Self>;
+    /// Canonical textual form.
+    fn emit_value(&self) -> String;
+}
+
+macro_rules! trailer_value_via_str {
+    ($($ty:ty),*) => {$(
+        impl TrailerValue for $ty {
+            fn parse_value(s: &str) -> Option<Self> {
+                s.parse().ok()
+            }
+            fn emit_value(&self) -> String {
+                self.to_string()
+            }
+        }
+    )*};
+}
+
+trailer_value_via_str!(
+    Blake3Digest,
+    Sha256Digest,
+    Ed25519Key,
+    CommitOid,
+    Cid,
+    SigAlg
+);
+
+/// Free-form values must be non-empty single lines.
+impl TrailerValue for String {
+    fn parse_value(s: &str) -> Option<Self> {
+        (!s.is_empty() && !s.contains(['\n', '\r'])).then(|| s.to_string())
+    }
+    fn emit_value(&self) -> String {
+        self.clone()
+    }
+}
+
+/// Bytes tagged with their signature algorithm, `<alg>:<hex>` with lowercase
+/// hex: the form of the event `Signer` and `Sig` trailers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgHex {
+    pub alg: SigAlg,
+    pub bytes: Vec<u8>,
+}
+
+impl TrailerValue for AlgHex {
+    fn parse_value(s: &str) -> Option<Self> {
+        let (alg, hex) = s.split_once(':')?;
+        let bytes = hex_decode(hex).ok()?;
+        (!bytes.is_empty()).then_some(Self {
+            alg: alg.parse().ok()?,
+            bytes,
+        })
+    }
+    fn emit_value(&self) -> String {
+        format!("{}:{}", self.alg, hex_encode(&self.bytes))
+    }
+}
+
+/// A signature with its signer key, in a `Commit-Sig` trailer as
+/// `<alg>:<signer hex> <sig hex>`, with lowercase hex and an algorithm that
+/// matches the key material (see [`SigAlg::infer`]). Event envelope
+/// signatures use the same struct but are spread over the SPEC's `Sig-Alg`,
+/// `Signer` and `Sig` trailers (see [`EventTrailers::envelope_sig`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailerSig {
+    pub alg: SigAlg,
+    pub signer: Vec<u8>,
+    pub sig: Vec<u8>,
+}
+
+impl TrailerSig {
+    /// The Ed25519 commit signature this value carries, if it is one.
+    #[must_use]
+    pub fn to_commit_signature(&self) -> Option<Signature> {
+        if self.alg != SigAlg::Ed25519 {
+            return None;
+        }
+        Some(Signature {
+            signer: self.signer.as_slice().try_into().ok()?,
+            sig: self.sig.as_slice().try_into().ok()?,
+        })
+    }
+}
+
+impl From<&Signature> for TrailerSig {
+    fn from(sig: &Signature) -> Self {
+        Self {
+            alg: SigAlg::Ed25519,
+            signer: sig.signer.to_vec(),
+            sig: sig.sig.to_vec(),
+        }
+    }
+}
+
+impl TrailerValue for TrailerSig {
+    fn parse_value(s: &str) -> Option<Self> {
+        let (key, sig) = s.split_once(' ')?;
+        let (alg, signer) = key.split_once(':')?;
+        let alg: SigAlg = alg.parse().ok()?;
+        let signer = hex_decode(signer).ok()?;
+        let sig = hex_decode(sig).ok()?;
+        (SigAlg::infer(&signer) == Some(alg) && !sig.is_empty()).then_some(Self {
+            alg,
+            signer,
+            sig,
+        })
+    }
+    fn emit_value(&self) -> String {
+        format!(
+            "{}:{} {}",
+            self.alg,
+            hex_encode(&self.signer),
+            hex_encode(&self.sig)
+        )
+    }
+}
+
+/// Extract the trailer block of a commit message as `(key, value)` pairs.
+///
+/// The block is the last paragraph of the message, provided it is not the
+/// subject paragraph and every line in it is either `Key: value` or an
+/// indented continuation of the previous value. Otherwise the message has no
+/// trailers and an empty list is returned.
+#[must_use]
+pub fn parse_trailers(message: &str) -> Vec<(String, String)> {
+    let lines: Vec<&str> = message.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let end = lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    let Some(start) = lines[..end].iter().rposition(|l| l.trim().is_empty()) else {
+        return Vec::new();
+    };
+    let mut out: Vec<(String, String)> = Vec::new();
+    for line in &lines[start + 1..end] {
+        if line.starts_with([' ', '\t']) {
+            match out.last_mut() {
+                Some((_, v)) => {
+                    v.push(' ');
+                    v.push_str(line.trim());
+                }
+                None => return Vec::new(),
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            return Vec::new();
+        };
+        let key_ok = !key.is_empty() && key.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-');
+        if !key_ok {
+            return Vec::new();
+        }
+        out.push((key.to_string(), value.trim().to_string()));
+    }
+    out
+}
+
+/// Artifact kinds that carry a normative trailer set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ArtifactKind {
+    /// Ledger event commit on `refs/gatos/journal/<ns>/<actor>` (SPEC §4.1).
+    Event,
+    /// State checkpoint under `refs/gatos/state/<ns>` (SPEC §5.3).
+    Checkpoint,
+    /// Job result commit (SPEC §19.3, ADR-0002).
+    Result,
+    /// Governance proposal (SPEC §20.2, ADR-0003).
+    Proposal,
+    /// Governance approval.
+    Approval,
+    /// Governance grant.
+    Grant,
+    /// Grant revocation (SPEC §20.5).
+    Revocation,
+}
+
+/// A typed set of trailers for one [`ArtifactKind`].
+pub trait TrailerSet: Sized {
+    /// Artifact kind this set describes.
+    const KIND: ArtifactKind;
+
+    /// Build the set from raw `(key, value)` pairs, ignoring unknown keys.
+    ///
+    /// # Errors
+    /// Returns a [`TrailerError`] if a required trailer is missing, a known
+    /// trailer is repeated, or a value is malformed.
+    fn from_trailers(trailers: &[(String, String)]) -> Result<Self, TrailerError>;
+
+    /// Known trailers in canonical order; absent optional trailers are omitted.
+    fn to_trailers(&self) -> Vec<(&'static str, String)>;
+
+    /// Parse the trailer block of a commit message.
+    ///
+    /// # Errors
+    /// See [`TrailerSet::from_trailers`].
+    fn parse(message: &str) -> Result<Self, TrailerError> {
+        Self::from_trailers(&parse_trailers(message))
+    }
+
+    /// Render the trailer block (one `Key: value` line each, `\n`-terminated).
+    fn emit(&self) -> String {
+        self.to_trailers()
+            .into_iter()
+            .map(|(k, v)| format!("{k}: {v}\n"))
+            .collect()
+    }
+
+    /// Compose a full commit message: `body`, a blank line, then the trailers.
+    fn to_message(&self, body: &str) -> String {
+        format!("{}\n\n{}", body.trim_end(), self.emit())
+    }
+}
+
+macro_rules! trailer_set {
+    (@get required $key:literal, $v:expr) => { $v.ok_or(TrailerError::Missing($key))? };
+    (@get optional $key:literal, $v:expr) => { $v };
+    (@get repeated $key:literal, $v:expr) => { $v };
+    (@ty required $ty:ty) => { $ty };
+    (@ty optional $ty:ty) => { Option<$ty> };
+    (@ty repeated $ty:ty) => { Vec<$ty> };
+    (@slot repeated $ty:ty) => { Vec<$ty> };
+    (@slot $req:ident $ty:ty) => { Option<$ty> };
+    (@put repeated $key:literal, $slot:ident, $v:expr) => { $slot.push($v) };
+    (@put $req:ident $key:literal, $slot:ident, $v:expr) => {
+        if $slot.replace($v).is_some() {
+            return Err(TrailerError::Duplicate($key));
+        }
+    };
+    (@emit required $out:ident, $key:literal, $v:expr) => { $out.push(($key, $v.emit_value())) };
+    (@emit optional $out:ident, $key:literal, $v:expr) => {
+        if let Some(v) = &$v {
+            $out.push(($key, v.emit_value()));
+        }
+    };
+    (@emit repeated $out:ident, $key:literal, $v:expr) => {
+        $out.extend($v.iter().map(|v| ($key, v.emit_value())))
+    };
+    (
+        $(#[$doc:meta])*
+        $name:ident => $kind:ident {
+            $( $(#[$fdoc:meta])* $field:ident: $req:ident $ty:ty = $key:literal, )*
+        }
+    ) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name {
+            $( $(#[$fdoc])* pub $field: trailer_set!(@ty $req $ty), )*
+        }
+
+        impl TrailerSet for $name {
+            const KIND: ArtifactKind = ArtifactKind::$kind;
+
+            fn from_trailers(trailers: &[(String, String)]) -> Result<Self, TrailerError> {
+                $( let mut $field: trailer_set!(@slot $req $ty) = Default::default(); )*
+                for (k, v) in trailers {
+                    $(
+                        if k.eq_ignore_ascii_case($key) {
+                            let value = <$ty>::parse_value(v).ok_or(TrailerError::Invalid($key))?;
+                            trailer_set!(@put $req $key, $field, value);
+                            continue;
+                        }
+                    )*
+                }
+                Ok(Self { $( $field: trailer_set!(@get $req $key, $field), )* })
+            }
+
+            #[allow(clippy::vec_init_then_push)]
+            fn to_trailers(&self) -> Vec<(&'static str, String)> {
+                let mut out = Vec::new();
+                $( trailer_set!(@emit $req out, $key, self.$field); )*
+                out
+            }
+        }
+    };
+}
+
+trailer_set! {
+    /// Ledger event commit trailers (SPEC §4.1). `Sig-Alg`, `Signer` and
+    /// `Sig` carry the event envelope's signature over its canonical bytes
+    /// (read them through [`EventTrailers::envelope_sig`]); each `Commit-Sig`
+    /// signs the `Content-Id` of the ledger commit.
+    EventTrailers => Event {
+        content_id: required Blake3Digest = "Content-Id",
+        event_cid: optional Cid = "Event-CID",
+        sig_alg: optional SigAlg = "Sig-Alg",
+        signer: optional AlgHex = "Signer",
+        sig: optional AlgHex = "Sig",
+        commit_sigs: repeated TrailerSig = "Commit-Sig",
+    }
+}
+
+impl EventTrailers {
+    /// The envelope signature, if the commit carries one.
+    ///
+    /// # Errors
+    /// `Sig-Alg`, `Signer` and `Sig` go together: returns
+    /// [`TrailerError::Missing`] if only some are present, and
+    /// [`TrailerError::Invalid`] if they name different algorithms or the
+    /// signer key does not fit the algorithm.
+    pub fn envelope_sig(&self) -> Result<Option<TrailerSig>, TrailerError> {
+        let (alg, signer, sig) = match (self.sig_alg, &self.signer, &self.sig) {
+            (None, None, None) => return Ok(None),
+            (Some(alg), Some(signer), Some(sig)) => (alg, signer, sig),
+            (None, ..) => return Err(TrailerError::Missing("Sig-Alg")),
+            (_, None, _) => return Err(TrailerError::Missing("Signer")),
+            (.., None) => return Err(TrailerError::Missing("Sig")),
+        };
+        if signer.alg != alg || SigAlg::infer(&signer.bytes) != Some(alg) {
+            return Err(TrailerError::Invalid("Signer"));
+        }
+        if sig.alg != alg {
+            return Err(TrailerError::Invalid("Sig"));
+        }
+        Ok(Some(TrailerSig {
+            alg,
+            signer: signer.bytes.clone(),
+            sig: sig.bytes.clone(),
+        }))
+    }
+
+    /// Write (or with `None`, clear) the envelope signature trailers.
+    pub fn set_envelope_sig(&mut self, sig: Option<&TrailerSig>) {
+        self.sig_alg = sig.map(|s| s.alg);
+        self.signer = sig.map(|s| AlgHex {
+            alg: s.alg,
+            bytes: s.signer.clone(),
+        });
+        self.sig = sig.map(|s| AlgHex {
+            alg: s.alg,
+            bytes: s.sig.clone(),
+        });
+    }
+}
+
+trailer_set! {
+    /// State checkpoint trailers (SPEC §5.3). `Fold-Math`/`Fold-RNG` fall
+    /// back to profile defaults when absent.
+    CheckpointTrailers => Checkpoint {
+        state_root: required Blake3Digest = "State-Root",
+        ledger_head: required CommitOid = "Ledger-Head",
+        policy_root: required CommitOid = "Policy-Root",
+        policy_code_root: optional Sha256Digest = "Policy-Code-Root",
+        fold_engine: required String = "Fold-Engine",
+        fold_root: required Sha256Digest = "Fold-Root",
+        fold_version: optional String = "Fold-Version",
+        fold_math: optional String = "Fold-Math",
+        fold_rng: optional String = "Fold-RNG",
+    }
+}
+
+trailer_set! {
+    /// Job result trailers (SPEC §19.3, ADR-0002).
+    ResultTrailers => Result {
+        job_id: required Blake3Digest = "Job-Id",
+        worker_id: required Ed25519Key = "Worker-Id",
+        proof_of_execution: required Blake3Digest = "Proof-Of-Execution",
+        attest_program: optional Blake3Digest = "Attest-Program",
+        /// `ed25519:<sig>`; kept verbatim, verified against the attestation
+        /// envelope by the job plane.
+        attest_sig: optional String = "Attest-Sig",
+    }
+}
+
+trailer_set! {
+    /// Governance proposal trailers (SPEC §20.2).
+    ProposalTrailers => Proposal {
+        action: required String = "Action",
+        target: required String = "Target",
+        proposal_id: required Blake3Digest = "Proposal-Id",
+        required_quorum: required String = "Required-Quorum",
+        expire_at: required String = "Expire-At",
+        policy_rule: required String = "Policy-Rule",
+        created_by: required String = "Created-By",
+    }
+}
+
+trailer_set! {
+    /// Governance approval trailers (SPEC §20.2).
+    ApprovalTrailers => Approval {
+        proposal_id: required Blake3Digest = "Proposal-Id",
+        approval_id: required Blake3Digest = "Approval-Id",
+        signer: required Ed25519Key = "Signer",
+        expires_at: optional String = "Expires-At",
+    }
+}
+
+trailer_set! {
+    /// Governance grant trailers (SPEC §20.2, ADR-0003).
+    GrantTrailers => Grant {
+        proposal_id: required Blake3Digest = "Proposal-Id",
+        grant_id: required Blake3Digest = "Grant-Id",
+        proof_of_consensus: required Blake3Digest = "Proof-Of-Consensus",
+        supersedes: optional Blake3Digest = "Supersedes",
+    }
+}
+
+trailer_set! {
+    /// Grant revocation trailers (SPEC §20.5).
+    RevocationTrailers => Revocation {
+        grant_id: required Blake3Digest = "Grant-Id",
+        revocation_id: required Blake3Digest = "Revocation-Id",
+        reason: required String = "Reason",
+        revoked_by: required String = "Revoked-By",
+    }
+}
+
+/// Trailer set of any artifact kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Artifact {
+    Event(EventTrailers),
+    Checkpoint(CheckpointTrailers),
+    Result(ResultTrailers),
+    Proposal(ProposalTrailers),
+    Approval(ApprovalTrailers),
+    Grant(GrantTrailers),
+    Revocation(RevocationTrailers),
+}
+
+impl Artifact {
+    /// Parse the trailers of `message` as an artifact of the given kind.
+    ///
+    /// # Errors
+    /// See [`TrailerSet::from_trailers`].
+    pub fn parse(kind: ArtifactKind, message: &str) -> Result<Self, TrailerError> {
+        Ok(match kind {
+            ArtifactKind::Event => Self::Event(TrailerSet::parse(message)?),
+            ArtifactKind::Checkpoint => Self::Checkpoint(TrailerSet::parse(message)?),
+            ArtifactKind::Result => Self::Result(TrailerSet::parse(message)?),
+            ArtifactKind::Proposal => Self::Proposal(TrailerSet::parse(message)?),
+            ArtifactKind::Approval => Self::Approval(TrailerSet::parse(message)?),
+            ArtifactKind::Grant => Self::Grant(TrailerSet::parse(message)?),
+            ArtifactKind::Revocation => Self::Revocation(TrailerSet::parse(message)?),
+        })
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> ArtifactKind {
+        match self {
+            Self::Event(_) => ArtifactKind::Event,
+            Self::Checkpoint(_) => ArtifactKind::Checkpoint,
+            Self::Result(_) => ArtifactKind::Result,
+            Self::Proposal(_) => ArtifactKind::Proposal,
+            Self::Approval(_) => ArtifactKind::Approval,
+            Self::Grant(_) => ArtifactKind::Grant,
+            Self::Revocation(_) => ArtifactKind::Revocation,
+        }
+    }
+
+    /// Render the trailer block in canonical order.
+    #[must_use]
+    pub fn emit(&self) -> String {
+        match self {
+            Self::Event(t) => t.emit(),
+            Self::Checkpoint(t) => t.emit(),
+            Self::Result(t) => t.emit(),
+            Self::Proposal(t) => t.emit(),
+            Self::Approval(t) => t.emit(),
+            Self::Grant(t) => t.emit(),
+            Self::Revocation(t) => t.emit(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b3(n: u8) -> String {
+        format!("blake3:{}", format!("{n:02x}").repeat(32))
+    }
+
+    #[test]
+    fn checkpoint_roundtrip_in_canonical_order() {
+        let oid = "a".repeat(40);
+        let fold_root = format!("sha256:{}", "f".repeat(64));
+        // Scrambled order, an unknown trailer, and a lowercase key.
+        let msg = format!(
+            "checkpoint: state/ns\n\nBody text.\n\nFold-Root: {fold_root}\nX-Custom: whatever\n\
+             ledger-head: {oid}\nState-Root: {}\nPolicy-Root: {oid}\n\
+             Fold-Engine: echo@0.1.0+elc@0.1.0+num=q32.32+rng=pcg32@1\n",
+            b3(1)
+        );
+        let cp = CheckpointTrailers::parse(&msg).unwrap();
+        assert_eq!(cp.fold_math, None);
+        let emitted = cp.emit();
+        let keys: Vec<&str> = emitted
+            .lines()
+            .map(|l| l.split(':').next().unwrap())
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "State-Root",
+                "Ledger-Head",
+                "Policy-Root",
+                "Fold-Engine",
+                "Fold-Root"
+            ]
+        );
+        assert_eq!(
+            CheckpointTrailers::parse(&cp.to_message("checkpoint")),
+            Ok(cp)
+        );
+    }
+
+    #[test]
+    fn required_duplicate_and_malformed() {
+        let g = format!(
+            "grant\n\nProposal-Id: {}\nGrant-Id: {}\nProof-Of-Consensus: {}\n",
+            b3(1),
+            b3(2),
+            b3(3)
+        );
+        assert!(GrantTrailers::parse(&g).is_ok());
+
+        let missing = format!("grant\n\nProposal-Id: {}\nGrant-Id: {}\n", b3(1), b3(2));
+        assert_eq!(
+            GrantTrailers::parse(&missing),
+            Err(TrailerError::Missing("Proof-Of-Consensus"))
+        );
+
+        let dup = format!("{g}Grant-Id: {}\n", b3(4));
+        assert_eq!(
+            GrantTrailers::parse(&dup),
+            Err(TrailerError::Duplicate("Grant-Id"))
+        );
+
+        let upper = g.replace("blake3:01", "blake3:0A");
+        assert_eq!(
+            GrantTrailers::parse(&upper),
+            Err(TrailerError::Invalid("Proposal-Id"))
+        );
+    }
+
+    #[test]
+    fn trailer_block_detection() {
+        // Subject-only message: no trailers.
+        assert!(parse_trailers("Key: value").is_empty());
+        // Last paragraph with a prose line is not a trailer block.
+        assert!(parse_trailers("subj\n\nKey: v\nnot a trailer\n").is_empty());
+        // Continuation lines are folded.
+        assert_eq!(
+            parse_trailers("subj\n\nReason: too\n  broad\n"),
+            [("Reason".to_string(), "too broad".to_string())]
+        );
+    }
+
+    #[test]
+    fn artifact_dispatch() {
+        let key = format!("ed25519:{}", "d".repeat(64));
+        let msg = format!(
+            "approve\n\nProposal-Id: {}\nApproval-Id: {}\nSigner: {key}\n",
+            b3(1),
+            b3(2)
+        );
+        let a = Artifact::parse(ArtifactKind::Approval, &msg).unwrap();
+        assert_eq!(a.kind(), ArtifactKind::Approval);
+        assert!(a.emit().ends_with(&format!("Signer: {key}\n")));
+        assert_eq!(
+            Artifact::parse(ArtifactKind::Revocation, &msg),
+            Err(TrailerError::Missing("Grant-Id"))
+        );
+    }
+
+    #[test]
+    fn event_trailers_repeat_commit_sigs() {
+        let key = "d".repeat(64);
+        let sig = |b: &str| format!("ed25519:{key} {}", b.repeat(128));
+        let msg = format!(
+            "event.append 01HF4Z5Q3C7D6XJ9K8W2M1N0PR\n\nContent-Id: {}\n\
+             Commit-Sig: {}\ncommit-sig: {}\n",
+            b3(1),
+            sig("a"),
+            sig("b")
+        );
+        let ev = EventTrailers::parse(&msg).unwrap();
+        assert_eq!(ev.commit_sigs.len(), 2);
+        assert_eq!(ev.envelope_sig(), Ok(None));
+        assert_eq!(
+            ev.commit_sigs[1].to_commit_signature().unwrap().sig,
+            [0xbb; 64]
+        );
+        assert_eq!(EventTrailers::parse(&ev.to_message("event")), Ok(ev));
+
+        // The algorithm prefix must match the key material.
+        let p256 = format!("{msg}Commit-Sig: ecdsa-p256:{key} {}\n", "a".repeat(128));
+        assert_eq!(
+            EventTrailers::parse(&p256),
+            Err(TrailerError::Invalid("Commit-Sig"))
+        );
+    }
+
+    #[test]
+    fn event_envelope_sig_uses_spec_trailers() {
+        let envelope = TrailerSig {
+            alg: SigAlg::Ed25519,
+            signer: alloc::vec![0xdd; 32],
+            sig: alloc::vec![0xaa; 64],
+        };
+        let mut ev = EventTrailers::parse(&format!("e\n\nContent-Id: {}\n", b3(1))).unwrap();
+        ev.set_envelope_sig(Some(&envelope));
+        let msg = ev.to_message("event");
+        assert!(msg.contains(&format!(
+            "Sig-Alg: ed25519\nSigner: ed25519:{}\nSig: ed25519:{}\n",
+            "dd".repeat(32),
+            "aa".repeat(64)
+        )));
+        let parsed = EventTrailers::parse(&msg).unwrap();
+        assert_eq!(parsed.envelope_sig(), Ok(Some(envelope)));
+
+        let without_signer = msg.replace(&format!("Signer: ed25519:{}\n", "dd".repeat(32)), "");
+        assert_eq!(
+            EventTrailers::parse(&without_signer)
+                .unwrap()
+                .envelope_sig(),
+            Err(TrailerError::Missing("Signer"))
+        );
+        let p256 = msg.replace("Sig-Alg: ed25519", "Sig-Alg: ecdsa-p256");
+        assert_eq!(
+            EventTrailers::parse(&p256).unwrap().envelope_sig(),
+            Err(TrailerError::Invalid("Signer"))
+        );
+    }
+}
@@ -0,0 +1,476 @@
+//! Content-addressed Merkle trees behind [`CommitCore::tree`].
+//!
+//! A [`Tree`] is a list of named entries, sorted bytewise by name with no
+//! duplicates, each pointing at a blob or a subtree by BLAKE3 id. The
+//! canonical encoding is bincode (`config::standard()`) and the tree id is
+//! `blake3(encoding)`, so trees are ordinary objects in any [`ObjectStore`].
+//! Unlike git trees there is no file mode and no SHA-1.
+//!
+//! [`CommitCore::tree`]: crate::CommitCore::tree
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use bincode::error::DecodeError;
+use bincode::{config, decode_from_slice, encode_to_vec, Decode, Encode};
+
+use crate::{decode_config, Hash, ObjectStore, StoreError};
+
+/// What a tree entry points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+pub enum EntryKind {
+    /// Raw object bytes.
+    Blob,
+    /// Another [`Tree`].
+    Tree,
+}
+
+/// A single named entry in a [`Tree`].
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct TreeEntry {
+    /// Path segment: non-empty, not `.` or `..`, no `/` or NUL.
+    pub name: String,
+    pub kind: EntryKind,
+    pub id: Hash,
+}
+
+/// Errors produced while building a [`Tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeError {
+    /// An entry name or path segment is not a valid name.
+    InvalidName(String),
+    /// Two entries share a name, or a path is both a blob and a directory.
+    DuplicateName(String),
+    /// The backing store failed.
+    Store(StoreError),
+}
+
+impl From<StoreError> for TreeError {
+    fn from(e: StoreError) -> Self {
+        Self::Store(e)
+    }
+}
+
+/// A canonical tree object: entries sorted by name, names unique.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct Tree {
+    entries: Vec<TreeEntry>,
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\0'])
+}
+
+impl Tree {
+    /// Build a tree from entries in any order.
+    ///
+    /// # Errors
+    /// Returns [`TreeError::InvalidName`] or [`TreeError::DuplicateName`].
+    pub fn from_entries(entries: impl IntoIterator<Item = TreeEntry>) -> Result<Self, TreeError> {
+        let mut entries: Vec<TreeEntry> = entries.into_iter().collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        for (i, e) in entries.iter().enumerate() {
+            if !valid_name(&e.name) {
+                return Err(TreeError::InvalidName(e.name.clone()));
+            }
+            if i > 0 && entries[i - 1].name == e.name {
+                return Err(TreeError::DuplicateName(e.name.clone()));
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// Entries in canonical (sorted) order.
+    #[must_use]
+    pub fn entries(&self) -> &[TreeEntry] {
+        &self.entries
+    }
+
+    /// Look up an entry by name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&TreeEntry> {
+        self.entries
+            .binary_search_by(|e| e.name.as_str().cmp(name))
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    /// Canonical bytes of this tree.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn encode(&self) -> Result<Vec<u8>, bincode::error::EncodeError> {
+        encode_to_vec(self, config::standard())
+    }
+
+    /// Decode a tree, rejecting non-canonical bytes (unsorted or duplicate
+    /// names, invalid names, trailing bytes, over-long varints) and length
+    /// prefixes claiming more than [`DECODE_LIMIT`](crate::DECODE_LIMIT).
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` is not a canonical tree encoding.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (tree, read): (Self, usize) = decode_from_slice(bytes, decode_config())?;
+        if read != bytes.len() {
+            return Err(DecodeError::Other("trailing bytes after tree"));
+        }
+        let sorted = tree
+            .entries
+            .windows(2)
+            .all(|w| w[0].name.as_bytes() < w[1].name.as_bytes());
+        if !sorted || !tree.entries.iter().all(|e| valid_name(&e.name)) {
+            return Err(DecodeError::Other("tree entries not canonical"));
+        }
+        if tree.encode().ok().as_deref() != Some(bytes) {
+            return Err(DecodeError::Other("non-canonical tree encoding"));
+        }
+        Ok(tree)
+    }
+
+    /// The BLAKE3 id of this tree.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn id(&self) -> Result<Hash, bincode::error::EncodeError> {
+        Ok(blake3::hash(&self.encode()?).into())
+    }
+}
+
+/// Persist `tree` in `store` and return its id.
+///
+/// # Errors
+/// Returns [`StoreError::Invariant`] if the tree cannot be serialized, or any
+/// error reported by the backend.
+pub fn put_tree<S: ObjectStore + ?Sized>(store: &mut S, tree: &Tree) -> Result<Hash, StoreError> {
+    let bytes = tree
+        .encode()
+        .map_err(|_| StoreError::Invariant("tree serializes"))?;
+    let id = blake3::hash(&bytes).into();
+    store.put_object(&id, &bytes)?;
+    Ok(id)
+}
+
+/// Load the tree stored under `id`.
+///
+/// # Errors
+/// Returns [`StoreError::Corruption`] if the object is not a canonical tree or
+/// does not hash to `id`, or any error reported by the backend.
+pub fn get_tree<S: ObjectStore + ?Sized>(store: &S, id: &Hash) -> Result<Option<Tree>, StoreError> {
+    let Some(bytes) = store.get_object(id)? else {
+        return Ok(None);
+    };
+    if blake3::hash(&bytes).as_bytes() != id {
+        return Err(StoreError::corruption(id, &bytes));
+    }
+    Tree::decode(&bytes)
+        .map(Some)
+        .map_err(|_| StoreError::corruption(id, &bytes))
+}
+
+fn load_tree<S: ObjectStore + ?Sized>(store: &S, id: &Hash) -> Result<Tree, StoreError> {
+    get_tree(store, id)?.ok_or(StoreError::NotFound(*id))
+}
+
+enum Node {
+    Blob(Hash),
+    Dir(BTreeMap<String, Node>),
+}
+
+/// Build and store a nested tree from `/`-separated paths to blob ids and
+/// return the root tree id. Intermediate directories are created as needed;
+/// blobs are assumed to be stored already.
+///
+/// # Errors
+/// Returns [`TreeError::InvalidName`] for empty, `.`/`..` or otherwise invalid
+/// path segments, [`TreeError::DuplicateName`] when a path repeats or is used
+/// as both blob and directory, or [`TreeError::Store`] on backend failure.
+pub fn build_tree<'a, S: ObjectStore + ?Sized>(
+    store: &mut S,
+    files: impl IntoIterator<Item = (&'a str, Hash)>,
+) -> Result<Hash, TreeError> {
+    let mut root = BTreeMap::new();
+    for (path, id) in files {
+        let mut dir = &mut root;
+        let mut segments = path.split('/').peekable();
+        while let Some(seg) = segments.next() {
+            if !valid_name(seg) {
+                return Err(TreeError::InvalidName(path.to_string()));
+            }
+            if segments.peek().is_none() {
+                if dir.insert(seg.to_string(), Node::Blob(id)).is_some() {
+                    return Err(TreeError::DuplicateName(path.to_string()));
+                }
+                break;
+            }
+            let node = dir
+                .entry(seg.to_string())
+                .or_insert_with(|| Node::Dir(BTreeMap::new()));
+            match node {
+                Node::Dir(children) => dir = children,
+                Node::Blob(_) => return Err(TreeError::DuplicateName(path.to_string())),
+            }
+        }
+    }
+    write_dir(store, root)
+}
+
+fn write_dir<S: ObjectStore + ?Sized>(
+    store: &mut S,
+    dir: BTreeMap<String, Node>,
+) -> Result<Hash, TreeError> {
+    let mut entries = Vec::with_capacity(dir.len());
+    for (name, node) in dir {
+        let (kind, id) = match node {
+            Node::Blob(id) => (EntryKind::Blob, id),
+            Node::Dir(children) => (EntryKind::Tree, write_dir(store, children)?),
+        };
+        entries.push(TreeEntry { name, kind, id });
+    }
+    // BTreeMap iteration is already sorted and unique.
+    Ok(put_tree(store, &Tree { entries })?)
+}
+
+/// Visit every entry reachable from the tree `root`, depth-first in canonical
+/// order. `visit` receives the full `/`-separated path and the entry; subtrees
+/// are visited before their children.
+///
+/// # Errors
+/// Returns [`StoreError::NotFound`] if a referenced tree is missing,
+/// [`StoreError::Corruption`] if one is malformed, or any error reported by
+/// the backend.
+pub fn walk_tree<S, F>(store: &S, root: &Hash, mut visit: F) -> Result<(), StoreError>
+where
+    S: ObjectStore + ?Sized,
+    F: FnMut(&str, &TreeEntry),
+{
+    walk_inner(store, root, "", &mut visit)
+}
+
+fn walk_inner<S, F>(store: &S, id: &Hash, prefix: &str, visit: &mut F) -> Result<(), StoreError>
+where
+    S: ObjectStore + ?Sized,
+    F: FnMut(&str, &TreeEntry),
+{
+    let tree = load_tree(store, id)?;
+    for entry in tree.entries() {
+        let path = join(prefix, &entry.name);
+        visit(&path, entry);
+        if entry.kind == EntryKind::Tree {
+            walk_inner(store, &entry.id, &path, visit)?;
+        }
+    }
+    Ok(())
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        let mut p = String::with_capacity(prefix.len() + 1 + name.len());
+        p.push_str(prefix);
+        p.push('/');
+        p.push_str(name);
+        p
+    }
+}
+
+/// A blob-level difference between two trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeChange {
+    Added { path: String, id: Hash },
+    Removed { path: String, id: Hash },
+    Modified { path: String, old: Hash, new: Hash },
+}
+
+/// Compute blob-level changes from tree `old` to tree `new`, in path order.
+/// `None` stands for the empty tree. Subtrees with equal ids are skipped
+/// without being loaded.
+///
+/// # Errors
+/// Returns [`StoreError::NotFound`] if a referenced tree is missing,
+/// [`StoreError::Corruption`] if one is malformed, or any error reported by
+/// the backend.
+pub fn diff_trees<S: ObjectStore + ?Sized>(
+    store: &S,
+    old: Option<&Hash>,
+    new: Option<&Hash>,
+) -> Result<Vec<TreeChange>, StoreError> {
+    let mut changes = Vec::new();
+    diff_inner(store, old, new, "", &mut changes)?;
+    Ok(changes)
+}
+
+fn diff_inner<S: ObjectStore + ?Sized>(
+    store: &S,
+    old: Option<&Hash>,
+    new: Option<&Hash>,
+    prefix: &str,
+    out: &mut Vec<TreeChange>,
+) -> Result<(), StoreError> {
+    if old == new {
+        return Ok(());
+    }
+    let old = old
+        .map(|id| load_tree(store, id))
+        .transpose()?
+        .unwrap_or_default();
+    let new = new
+        .map(|id| load_tree(store, id))
+        .transpose()?
+        .unwrap_or_default();
+    let (mut a, mut b) = (
+        old.entries().iter().peekable(),
+        new.entries().iter().peekable(),
+    );
+    loop {
+        let (left, right) = match (a.peek(), b.peek()) {
+            (None, None) => return Ok(()),
+            (Some(l), Some(r)) if l.name == r.name => (a.next(), b.next()),
+            (Some(l), Some(r)) if l.name < r.name => (a.next(), None),
+            (Some(_), None) => (a.next(), None),
+            _ => (None, b.next()),
+        };
+        let name = left.or(right).map(|e| e.name.as_str()).unwrap_or_default();
+        let path = join(prefix, name);
+        let blob = |e: Option<&TreeEntry>| e.filter(|e| e.kind == EntryKind::Blob).map(|e| e.id);
+        let tree = |e: Option<&TreeEntry>| e.filter(|e| e.kind == EntryKind::Tree).map(|e| e.id);
+        match (blob(left), blob(right)) {
+            (Some(o), Some(n)) if o != n => out.push(TreeChange::Modified {
+                path: path.clone(),
+                old: o,
+                new: n,
+            }),
+            (Some(o), None) => out.push(TreeChange::Removed {
+                path: path.clone(),
+                id: o,
+            }),
+            (None, Some(n)) => out.push(TreeChange::Added {
+                path: path.clone(),
+                id: n,
+            }),
+            _ => {}
+        }
+        let (old_sub, new_sub) = (tree(left), tree(right));
+        if old_sub.is_some() || new_sub.is_some() {
+            diff_inner(store, old_sub.as_ref(), new_sub.as_ref(), &path, out)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MapStore;
+
+    fn blob(store: &mut MapStore, data: &[u8]) -> Hash {
+        let id = blake3::hash(data).into();
+        store.put_object(&id, data).unwrap();
+        id
+    }
+
+    #[test]
+    fn entry_order_does_not_change_id() {
+        let e = |name: &str| TreeEntry {
+            name: name.to_string(),
+            kind: EntryKind::Blob,
+            id: [0x01; 32],
+        };
+        let t1 = Tree::from_entries([e("b"), e("a")]).unwrap();
+        let t2 = Tree::from_entries([e("a"), e("b")]).unwrap();
+        assert_eq!(t1.id().unwrap(), t2.id().unwrap());
+        assert_eq!(Tree::decode(&t1.encode().unwrap()).unwrap(), t1);
+        assert_eq!(
+            Tree::from_entries([e("a"), e("a")]),
+            Err(TreeError::DuplicateName("a".to_string()))
+        );
+        assert_eq!(
+            Tree::from_entries([e("..")]),
+            Err(TreeError::InvalidName("..".to_string()))
+        );
+    }
+
+    #[test]
+    fn decode_rejects_unsorted_entries() {
+        let e = |name: &str| TreeEntry {
+            name: name.to_string(),
+            kind: EntryKind::Blob,
+            id: [0x01; 32],
+        };
+        let unsorted = Tree {
+            entries: alloc::vec![e("b"), e("a")],
+        };
+        assert!(Tree::decode(&unsorted.encode().unwrap()).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_oversized_length_prefixes() {
+        for count in [u64::MAX / 2, 1 << 40] {
+            // As the entry count, and as the name length of a single entry.
+            let huge = [&[0xfd][..], &count.to_le_bytes()].concat();
+            assert!(Tree::decode(&huge).is_err());
+            assert!(Tree::decode(&[&[0x01][..], &huge].concat()).is_err());
+        }
+    }
+
+    #[test]
+    fn build_walk_and_diff() {
+        let mut store = MapStore::default();
+        let (x, y, z) = (
+            blob(&mut store, b"x"),
+            blob(&mut store, b"y"),
+            blob(&mut store, b"z"),
+        );
+
+        let old = build_tree(
+            &mut store,
+            [("state/a.json", x), ("state/b.json", y), ("README", z)],
+        )
+        .unwrap();
+        let new = build_tree(
+            &mut store,
+            [("state/a.json", x), ("state/b.json", z), ("jobs/j", y)],
+        )
+        .unwrap();
+
+        let mut paths = Vec::new();
+        walk_tree(&store, &old, |path, _| paths.push(path.to_string())).unwrap();
+        assert_eq!(paths, ["README", "state", "state/a.json", "state/b.json"]);
+
+        let changes = diff_trees(&store, Some(&old), Some(&new)).unwrap();
+        assert_eq!(
+            changes,
+            [
+                TreeChange::Removed {
+                    path: "README".to_string(),
+                    id: z
+                },
+                TreeChange::Added {
+                    path: "jobs/j".to_string(),
+                    id: y
+                },
+                TreeChange::Modified {
+                    path: "state/b.json".to_string(),
+                    old: y,
+                    new: z
+                },
+            ]
+        );
+        assert!(diff_trees(&store, Some(&new), Some(&new))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn build_rejects_blob_directory_conflict() {
+        let mut store = MapStore::default();
+        assert_eq!(
+            build_tree(&mut store, [("a", [0; 32]), ("a/b", [0; 32])]),
+            Err(TreeError::DuplicateName("a/b".to_string()))
+        );
+        assert_eq!(
+            build_tree(&mut store, [("a//b", [0; 32])]),
+            Err(TreeError::InvalidName("a//b".to_string()))
+        );
+    }
+}
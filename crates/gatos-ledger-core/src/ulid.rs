@@ -0,0 +1,367 @@
+//! ULIDs: 128-bit, lexicographically sortable identifiers.
+//!
+//! Used as idempotency keys in [`EventEnvelope`](crate::EventEnvelope), bus
+//! messages, job manifests and PoX refs. Layout is a 48-bit millisecond Unix
+//! timestamp followed by 80 bits of entropy; the text form is 26 characters of
+//! Crockford base32. Parsing is strict and accepts only the canonical
+//! uppercase form (`^[0-9A-HJKMNP-TV-Z]{26}$`), so string equality and ULID
+//! equality coincide.
+//!
+//! Monotonic generation ([`UlidGenerator`]) needs a clock and an entropy
+//! source and is available with the `std` feature.
+
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+use bincode::{Decode, Encode};
+
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const RANDOM_BITS: u32 = 80;
+const RANDOM_MASK: u128 = (1 << RANDOM_BITS) - 1;
+
+/// Largest representable timestamp (48 bits of milliseconds).
+pub const MAX_TIMESTAMP_MS: u64 = (1 << 48) - 1;
+
+/// Errors produced while parsing or generating a [`Ulid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UlidError {
+    /// Text form is not exactly 26 characters.
+    Length,
+    /// Character at the given index is not canonical Crockford base32.
+    InvalidChar(usize),
+    /// Value exceeds 128 bits (first character above `7`), or the timestamp
+    /// exceeds 48 bits.
+    Overflow,
+    /// Monotonic generation exhausted the 80-bit random space within one
+    /// millisecond.
+    RandomExhausted,
+    /// The entropy source could not produce random bytes.
+    EntropyUnavailable,
+}
+
+impl fmt::Display for UlidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length => f.write_str("ulid must be 26 characters"),
+            Self::InvalidChar(i) => write!(f, "invalid ulid character at {i}"),
+            Self::Overflow => f.write_str("ulid out of range"),
+            Self::RandomExhausted => f.write_str("ulid random component exhausted"),
+            Self::EntropyUnavailable => f.write_str("ulid entropy source unavailable"),
+        }
+    }
+}
+
+/// A ULID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+pub struct Ulid(u128);
+
+impl Ulid {
+    /// Build a ULID from a millisecond timestamp and 80 bits of randomness
+    /// (higher bits of `random` are ignored).
+    ///
+    /// # Errors
+    /// Returns [`UlidError::Overflow`] if `timestamp_ms` exceeds 48 bits.
+    pub fn from_parts(timestamp_ms: u64, random: u128) -> Result<Self, UlidError> {
+        if timestamp_ms > MAX_TIMESTAMP_MS {
+            return Err(UlidError::Overflow);
+        }
+        Ok(Self(
+            (u128::from(timestamp_ms) << RANDOM_BITS) | (random & RANDOM_MASK),
+        ))
+    }
+
+    #[must_use]
+    pub const fn from_u128(value: u128) -> Self {
+        Self(value)
+    }
+
+    #[must_use]
+    pub const fn to_u128(self) -> u128 {
+        self.0
+    }
+
+    /// Big-endian byte form, as used by binary ULID encodings.
+    #[must_use]
+    pub const fn to_bytes(self) -> [u8; 16] {
+        self.0.to_be_bytes()
+    }
+
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(u128::from_be_bytes(bytes))
+    }
+
+    /// Milliseconds since the Unix epoch encoded in the first 48 bits.
+    #[must_use]
+    pub const fn timestamp_ms(self) -> u64 {
+        (self.0 >> RANDOM_BITS) as u64
+    }
+
+    /// The 80-bit random component.
+    #[must_use]
+    pub const fn random(self) -> u128 {
+        self.0 & RANDOM_MASK
+    }
+
+    /// Canonical 26-character text form.
+    #[must_use]
+    pub fn to_text(self) -> String {
+        let mut out = String::with_capacity(26);
+        for i in (0..26).rev() {
+            out.push(CROCKFORD[((self.0 >> (5 * i)) & 0x1f) as usize] as char);
+        }
+        out
+    }
+}
+
+impl fmt::Display for Ulid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+impl FromStr for Ulid {
+    type Err = UlidError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 26 {
+            return Err(UlidError::Length);
+        }
+        let mut value = 0u128;
+        for (i, c) in s.bytes().enumerate() {
+            let digit = CROCKFORD
+                .iter()
+                .position(|&a| a == c)
+                .ok_or(UlidError::InvalidChar(i))?;
+            // 26 * 5 = 130 bits; the leading character carries only 3.
+            if i == 0 && digit > 7 {
+                return Err(UlidError::Overflow);
+            }
+            value = (value << 5) | digit as u128;
+        }
+        Ok(Self(value))
+    }
+}
+
+impl serde::Serialize for Ulid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Ulid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct V;
+        impl serde::de::Visitor<'_> for V {
+            type Value = Ulid;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a 26-character ULID")
+            }
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Ulid, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+        deserializer.deserialize_str(V)
+    }
+}
+
+#[cfg(feature = "std")]
+pub use generator::{Clock, Entropy, OsEntropy, SystemClock, UlidGenerator};
+
+#[cfg(feature = "std")]
+mod generator {
+    use super::{Ulid, UlidError, RANDOM_MASK};
+
+    /// Millisecond wall clock.
+    pub trait Clock {
+        fn now_ms(&mut self) -> u64;
+    }
+
+    /// Source of the 80-bit random component.
+    pub trait Entropy {
+        /// # Errors
+        /// Returns [`UlidError::EntropyUnavailable`] if no random bytes can
+        /// be produced.
+        fn fill(&mut self, buf: &mut [u8; 10]) -> Result<(), UlidError>;
+    }
+
+    /// [`Clock`] backed by `std::time::SystemTime`.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct SystemClock;
+
+    impl Clock for SystemClock {
+        fn now_ms(&mut self) -> u64 {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        }
+    }
+
+    /// [`Entropy`] from the operating system RNG.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct OsEntropy;
+
+    impl Entropy for OsEntropy {
+        fn fill(&mut self, buf: &mut [u8; 10]) -> Result<(), UlidError> {
+            getrandom::getrandom(buf).map_err(|_| UlidError::EntropyUnavailable)
+        }
+    }
+
+    impl<F: FnMut() -> u64> Clock for F {
+        fn now_ms(&mut self) -> u64 {
+            self()
+        }
+    }
+
+    /// Monotonic ULID generator.
+    ///
+    /// Within one millisecond, and whenever the clock goes backwards, the
+    /// previous ULID's random component is incremented instead of drawing
+    /// fresh entropy, so successive ULIDs from one generator strictly
+    /// increase.
+    #[derive(Debug)]
+    pub struct UlidGenerator<C = SystemClock, E = OsEntropy> {
+        clock: C,
+        entropy: E,
+        last: Option<Ulid>,
+    }
+
+    impl UlidGenerator {
+        #[must_use]
+        pub fn new() -> Self {
+            Self::with_sources(SystemClock, OsEntropy)
+        }
+    }
+
+    impl Default for UlidGenerator {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<C: Clock, E: Entropy> UlidGenerator<C, E> {
+        /// Generator with an injected clock and entropy source.
+        pub fn with_sources(clock: C, entropy: E) -> Self {
+            Self {
+                clock,
+                entropy,
+                last: None,
+            }
+        }
+
+        /// Produce the next ULID.
+        ///
+        /// # Errors
+        /// Returns [`UlidError::RandomExhausted`] if 2^80 ULIDs were requested
+        /// within one millisecond, [`UlidError::Overflow`] if the clock
+        /// reports a time beyond the 48-bit range, or
+        /// [`UlidError::EntropyUnavailable`] if the entropy source fails.
+        pub fn generate(&mut self) -> Result<Ulid, UlidError> {
+            let now = self.clock.now_ms();
+            let next = match self.last {
+                Some(last) if now <= last.timestamp_ms() => {
+                    if last.random() == RANDOM_MASK {
+                        return Err(UlidError::RandomExhausted);
+                    }
+                    Ulid(last.0 + 1)
+                }
+                _ => {
+                    let mut buf = [0u8; 10];
+                    self.entropy.fill(&mut buf)?;
+                    let mut wide = [0u8; 16];
+                    wide[6..].copy_from_slice(&buf);
+                    Ulid::from_parts(now, u128::from_be_bytes(wide))?
+                }
+            };
+            self.last = Some(next);
+            Ok(next)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn parse_roundtrip_and_timestamp() {
+        let text = "01ARZ3NDEKTSV4RRFFQ69G5FAV";
+        let ulid: Ulid = text.parse().unwrap();
+        assert_eq!(ulid.to_string(), text);
+        // 2016-07-30T23:54:10.259Z
+        assert_eq!(ulid.timestamp_ms(), 1_469_922_850_259);
+        assert_eq!(Ulid::from_bytes(ulid.to_bytes()), ulid);
+        assert_eq!(
+            Ulid::from_parts(ulid.timestamp_ms(), ulid.random()),
+            Ok(ulid)
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical() {
+        assert_eq!("01ARZ3NDEK".parse::<Ulid>(), Err(UlidError::Length));
+        // Lowercase and the excluded letters I, L, O, U.
+        assert_eq!(
+            "01arz3ndektsv4rrffq69g5fav".parse::<Ulid>(),
+            Err(UlidError::InvalidChar(2))
+        );
+        assert_eq!(
+            "01ARZ3NDEKTSV4RRFFQ69G5FAU".parse::<Ulid>(),
+            Err(UlidError::InvalidChar(25))
+        );
+        assert_eq!(
+            "8ZZZZZZZZZZZZZZZZZZZZZZZZZ".parse::<Ulid>(),
+            Err(UlidError::Overflow)
+        );
+        assert_eq!(
+            "7ZZZZZZZZZZZZZZZZZZZZZZZZZ".parse::<Ulid>(),
+            Ok(Ulid::from_u128(u128::MAX))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn generator_is_monotonic_with_injected_sources() {
+        struct Fixed(u8);
+        impl Entropy for Fixed {
+            fn fill(&mut self, buf: &mut [u8; 10]) -> Result<(), UlidError> {
+                *buf = [self.0; 10];
+                Ok(())
+            }
+        }
+        let ticks = std::cell::Cell::new(1_000u64);
+        let mut gen = UlidGenerator::with_sources(|| ticks.get(), Fixed(0xff));
+
+        let a = gen.generate().unwrap();
+        assert_eq!(a.timestamp_ms(), 1_000);
+        assert_eq!(a.random(), RANDOM_MASK);
+        // Same millisecond with a saturated random part cannot advance.
+        assert_eq!(gen.generate(), Err(UlidError::RandomExhausted));
+
+        ticks.set(1_001);
+        let mut gen = UlidGenerator::with_sources(|| ticks.get(), Fixed(0x01));
+        let b = gen.generate().unwrap();
+        let c = gen.generate().unwrap();
+        assert_eq!(c.to_u128(), b.to_u128() + 1);
+        // A clock step backwards still yields an increasing id.
+        ticks.set(900);
+        let d = gen.generate().unwrap();
+        assert!(d > c);
+        assert_eq!(d.timestamp_ms(), 1_001);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn generator_reports_entropy_failure() {
+        struct Broken;
+        impl Entropy for Broken {
+            fn fill(&mut self, _: &mut [u8; 10]) -> Result<(), UlidError> {
+                Err(UlidError::EntropyUnavailable)
+            }
+        }
+        let mut gen = UlidGenerator::with_sources(|| 1_000, Broken);
+        assert_eq!(gen.generate(), Err(UlidError::EntropyUnavailable));
+    }
+}
@@ -0,0 +1,288 @@
+//! Format versioning for [`CommitCore`].
+//!
+//! A content id is `blake3` over the canonical bincode bytes of a core, so any
+//! change to the layout of [`CommitCore`] would silently change every id. To
+//! keep old ids verifiable, each released layout is frozen here as its own
+//! type (`CommitCoreV1`, ...) and never edited again. A
+//! [`VersionedCommitCore`] pairs a core with its format version:
+//!
+//! ```text
+//! versioned bytes = varint(version) || canonical bincode of that version's core
+//! content id      = blake3(canonical bincode of that version's core)
+//! ```
+//!
+//! The version prefix is not part of the id, so version 1 ids are exactly the
+//! ADR-0001 ids produced by [`compute_content_id`](crate::compute_content_id).
+//! Persisted commit objects (see [`codec`](crate::codec)) carry the versioned
+//! form, so every stored core records the layout it was written in.
+//!
+//! Evolving the layout: add `CommitCoreV2` and a `V2` variant, bump
+//! [`CURRENT_COMMIT_CORE_VERSION`], migrate in [`VersionedCommitCore::into_current`],
+//! and add the new golden fingerprint. The fingerprint test fails until then.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{AllowedEnumVariants, DecodeError, EncodeError};
+use bincode::{config, decode_from_slice, encode_to_vec, Decode, Encode};
+
+use crate::{decode_config, CommitCore, Hash};
+
+/// Format version produced for [`CommitCore`] by this crate.
+pub const CURRENT_COMMIT_CORE_VERSION: u32 = 1;
+
+/// Errors produced while decoding a versioned core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionError {
+    /// The version prefix names a format this crate does not know.
+    UnknownVersion(u32),
+    /// The bytes are not the canonical encoding of the declared version.
+    Malformed,
+}
+
+/// Frozen layout of format version 1 (ADR-0001). Do not edit.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct CommitCoreV1 {
+    pub parent: Option<Hash>,
+    pub tree: Hash,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+/// A commit core tagged with its format version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionedCommitCore {
+    V1(CommitCoreV1),
+}
+
+impl VersionedCommitCore {
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        match self {
+            Self::V1(_) => 1,
+        }
+    }
+
+    /// Canonical bytes of the core in its own version's layout, without the
+    /// version prefix.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn core_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        match self {
+            Self::V1(core) => encode_to_vec(core, config::standard()),
+        }
+    }
+
+    /// Content id under the rules of this core's version.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn content_id(&self) -> Result<Hash, EncodeError> {
+        Ok(blake3::hash(&self.core_bytes()?).into())
+    }
+
+    /// Versioned wire form: `varint(version) || core bytes`.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        encode_to_vec(self, config::standard())
+    }
+
+    /// Decode the versioned wire form, dispatching on the version prefix.
+    ///
+    /// # Errors
+    /// Returns [`VersionError::UnknownVersion`] for versions newer than this
+    /// crate understands and [`VersionError::Malformed`] for non-canonical or
+    /// truncated input, or a message length beyond
+    /// [`DECODE_LIMIT`](crate::DECODE_LIMIT).
+    pub fn decode(bytes: &[u8]) -> Result<Self, VersionError> {
+        let (decoded, read): (Self, usize) =
+            decode_from_slice(bytes, decode_config()).map_err(|e| match e {
+                DecodeError::UnexpectedVariant { found, .. } => VersionError::UnknownVersion(found),
+                _ => VersionError::Malformed,
+            })?;
+        // Pin the single valid byte form (minimal varints, no trailing data).
+        if read != bytes.len() || decoded.encode().map_err(|_| VersionError::Malformed)? != bytes {
+            return Err(VersionError::Malformed);
+        }
+        Ok(decoded)
+    }
+
+    /// Migrate to the current [`CommitCore`] layout.
+    ///
+    /// Note that the migrated core's own content id may differ from
+    /// [`Self::content_id`] once the layout has evolved; the versioned id
+    /// remains the authoritative id for the original commit.
+    #[must_use]
+    pub fn into_current(self) -> CommitCore {
+        match self {
+            Self::V1(c) => CommitCore {
+                parent: c.parent,
+                tree: c.tree,
+                message: c.message,
+                timestamp: c.timestamp,
+            },
+        }
+    }
+}
+
+impl From<CommitCore> for VersionedCommitCore {
+    /// Tag a current core with [`CURRENT_COMMIT_CORE_VERSION`].
+    fn from(core: CommitCore) -> Self {
+        let CommitCore {
+            parent,
+            tree,
+            message,
+            timestamp,
+        } = core;
+        Self::V1(CommitCoreV1 {
+            parent,
+            tree,
+            message,
+            timestamp,
+        })
+    }
+}
+
+/// Every format version this crate can decode.
+static KNOWN_VERSIONS: AllowedEnumVariants = AllowedEnumVariants::Range { min: 1, max: 1 };
+
+impl Encode for VersionedCommitCore {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.version().encode(encoder)?;
+        match self {
+            Self::V1(core) => core.encode(encoder),
+        }
+    }
+}
+
+impl<Context> Decode<Context> for VersionedCommitCore {
+    /// Unknown versions fail with [`DecodeError::UnexpectedVariant`] carrying
+    /// the version found.
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        match u32::decode(decoder)? {
+            1 => Ok(Self::V1(CommitCoreV1::decode(decoder)?)),
+            found => Err(DecodeError::UnexpectedVariant {
+                type_name: "VersionedCommitCore",
+                allowed: &KNOWN_VERSIONS,
+                found,
+            }),
+        }
+    }
+}
+
+bincode::impl_borrow_decode!(VersionedCommitCore);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_content_id;
+    use std::string::ToString;
+
+    /// Exercises every field, including the `Some` arm of `parent`.
+    fn sample() -> CommitCore {
+        CommitCore {
+            parent: Some([0x11; 32]),
+            tree: [0x22; 32],
+            message: "fingerprint".to_string(),
+            timestamp: 1_725_000_000,
+        }
+    }
+
+    /// Golden `blake3` of each released layout's encoding of [`sample`].
+    const FINGERPRINTS: &[(u32, &str)] = &[(
+        1,
+        "8babca58f39fd260a34896320fccfcacdc194a0a6737d4e7001dcc25fae562bb",
+    )];
+
+    fn hex(bytes: &[u8]) -> std::string::String {
+        bytes.iter().map(|b| std::format!("{b:02x}")).collect()
+    }
+
+    /// Fails when `CommitCore`'s canonical layout changes without a new
+    /// frozen version and fingerprint.
+    #[test]
+    fn commit_core_layout_fingerprint() {
+        // Exhaustive destructuring: adding a field breaks compilation here.
+        let CommitCore {
+            parent: _,
+            tree: _,
+            message: _,
+            timestamp: _,
+        } = sample();
+
+        // Hand-derived v1 bytes: Some tag, parent, tree, varint length +
+        // UTF-8 message, then the timestamp as a 0xfc-prefixed u32 varint.
+        let v1_bytes = [
+            &[0x01][..],
+            &[0x11; 32],
+            &[0x22; 32],
+            &[0x0b],
+            b"fingerprint",
+            &[0xfc, 0x40, 0x69, 0xd1, 0x66],
+        ]
+        .concat();
+        let encoded = VersionedCommitCore::from(sample()).core_bytes().unwrap();
+        if CURRENT_COMMIT_CORE_VERSION == 1 {
+            assert_eq!(encoded, v1_bytes);
+        }
+
+        let current = compute_content_id(&sample()).unwrap();
+        let (version, golden) = FINGERPRINTS
+            .iter()
+            .find(|(v, _)| *v == CURRENT_COMMIT_CORE_VERSION)
+            .expect("every format version needs a golden fingerprint");
+        assert_eq!(
+            hex(&current),
+            *golden,
+            "CommitCore layout changed; freeze a new version (current is v{version})"
+        );
+    }
+
+    #[test]
+    fn v1_ids_match_adr_0001_ids_and_roundtrip() {
+        let versioned = VersionedCommitCore::from(sample());
+        assert_eq!(versioned.version(), 1);
+        assert_eq!(
+            versioned.content_id().unwrap(),
+            compute_content_id(&sample()).unwrap()
+        );
+        let bytes = versioned.encode().unwrap();
+        assert_eq!(bytes[0], 1);
+        let decoded = VersionedCommitCore::decode(&bytes).unwrap();
+        assert_eq!(decoded, versioned);
+        assert_eq!(decoded.into_current(), sample());
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed() {
+        let mut bytes = VersionedCommitCore::from(sample()).encode().unwrap();
+        bytes[0] = 2;
+        assert_eq!(
+            VersionedCommitCore::decode(&bytes),
+            Err(VersionError::UnknownVersion(2))
+        );
+        bytes[0] = 1;
+        bytes.push(0);
+        assert_eq!(
+            VersionedCommitCore::decode(&bytes),
+            Err(VersionError::Malformed)
+        );
+        assert_eq!(
+            VersionedCommitCore::decode(&[]),
+            Err(VersionError::Malformed)
+        );
+        for len in [u64::MAX / 2, 1 << 40] {
+            // version 1, no parent, tree, then an oversized message length.
+            let forged = [&[0x01, 0x00][..], &[0x22; 32], &[0xfd], &len.to_le_bytes()].concat();
+            assert_eq!(
+                VersionedCommitCore::decode(&forged),
+                Err(VersionError::Malformed)
+            );
+        }
+    }
+}
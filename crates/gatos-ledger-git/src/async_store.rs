@@ -0,0 +1,377 @@
+//! Async object stores for tokio runtimes (feature `tokio`).
+//!
+//! [`ObjectStore`] is synchronous and [`GitStore`](crate::GitStore) blocks in
+//! libgit2, which would stall a runtime worker for the length of every call.
+//! This module provides:
+//!
+//! - [`AsyncObjectStore`]: the async counterpart of [`ObjectStore`]. Methods
+//!   take `&self` and owned buffers so stores can be shared across tasks.
+//! - [`BlockingStore`]: runs any sync store on tokio's blocking pool, with at
+//!   most `max_concurrency` calls in flight. Each call checks out one store
+//!   instance from a small pool, opening new ones on demand.
+//! - [`FsObjectStore`]: an async-native store over the `gatos/objects/`
+//!   directory (SPEC §7), laying blobs out as `<root>/blake3/<hex>`.
+
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::sync::Semaphore;
+
+use crate::{
+    fs_error, verify_object_id, BackendError, BackendErrorKind, Hash, ObjectStore, StoreError,
+};
+
+/// Async content-addressed object storage; see [`ObjectStore`] for the
+/// id/content invariant every implementation must enforce.
+pub trait AsyncObjectStore: Send + Sync {
+    /// Persist `data` under the content id `id`.
+    ///
+    /// # Errors
+    /// Returns a [`StoreError`] if the backend fails to persist the bytes or
+    /// `data` may not be stored under `id`.
+    fn put_object(
+        &self,
+        id: Hash,
+        data: Vec<u8>,
+    ) -> impl Future<Output = Result<(), StoreError>> + Send;
+
+    /// Retrieve bytes by content id; `Ok(None)` if absent.
+    ///
+    /// # Errors
+    /// Returns a [`StoreError`] if the backend fails to read the object.
+    fn get_object(
+        &self,
+        id: Hash,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, StoreError>> + Send;
+
+    /// Whether an object is stored under `id`. Defaults to
+    /// [`get_object`](Self::get_object).
+    ///
+    /// # Errors
+    /// Returns a [`StoreError`] if the backend cannot be queried.
+    fn has_object(&self, id: Hash) -> impl Future<Output = Result<bool, StoreError>> + Send {
+        async move { Ok(self.get_object(id).await?.is_some()) }
+    }
+}
+
+type Opener<S> = Box<dyn Fn() -> Result<S, StoreError> + Send + Sync>;
+
+struct Pool<S> {
+    idle: Mutex<Vec<S>>,
+    open: Opener<S>,
+    /// Held by the blocking task itself, so a cancelled caller does not free
+    /// its slot while the call is still running.
+    permits: Arc<Semaphore>,
+}
+
+/// Adapter running a synchronous [`ObjectStore`] on tokio's blocking pool.
+///
+/// Cloning is cheap and shares the pool.
+pub struct BlockingStore<S> {
+    pool: Arc<Pool<S>>,
+}
+
+impl<S> Clone for BlockingStore<S> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: Arc::clone(&self.pool),
+        }
+    }
+}
+
+impl<S: ObjectStore + Send + 'static> BlockingStore<S> {
+    /// Pool of stores created by `open`, with at most `max_concurrency`
+    /// blocking calls in flight (at least one).
+    ///
+    /// For [`GitStore`](crate::GitStore), `open` typically reopens the same
+    /// repository path so that calls proceed in parallel.
+    pub fn new<F>(max_concurrency: usize, open: F) -> Self
+    where
+        F: Fn() -> Result<S, StoreError> + Send + Sync + 'static,
+    {
+        Self {
+            pool: Arc::new(Pool {
+                idle: Mutex::new(Vec::new()),
+                open: Box::new(open),
+                permits: Arc::new(Semaphore::new(max_concurrency.max(1))),
+            }),
+        }
+    }
+
+    /// Wrap a single store; calls are serialized.
+    pub fn from_store(store: S) -> Self {
+        let this = Self::new(1, || {
+            Err(StoreError::Invariant(
+                "single-store pool gets its store back, even after a panic",
+            ))
+        });
+        this.pool.checkin(store);
+        this
+    }
+
+    /// Run `f` against a pooled store on the blocking pool.
+    ///
+    /// Dropping the returned future does not stop a call that has started;
+    /// the call keeps its concurrency slot until it returns. If `f` panics,
+    /// the store still goes back to the pool.
+    ///
+    /// # Errors
+    /// Returns the error from `f` or from opening a store, or a
+    /// [`StoreError::Backend`] if the blocking task panicked.
+    pub async fn run<T, F>(&self, f: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut S) -> Result<T, StoreError> + Send + 'static,
+    {
+        let permit = Arc::clone(&self.pool.permits)
+            .acquire_owned()
+            .await
+            .map_err(|_| StoreError::Invariant("store semaphore is never closed"))?;
+        let pool = Arc::clone(&self.pool);
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let mut store = Checkout {
+                store: Some(pool.checkout()?),
+                pool: &pool,
+            };
+            f(store.get())
+        })
+        .await
+        .map_err(|e| BackendError::from_source(BackendErrorKind::Other, e))?
+    }
+}
+
+/// A store checked out of a [`Pool`]; returned on drop, including while
+/// unwinding from a panic.
+struct Checkout<'p, S> {
+    pool: &'p Pool<S>,
+    store: Option<S>,
+}
+
+impl<S> Checkout<'_, S> {
+    fn get(&mut self) -> &mut S {
+        self.store
+            .as_mut()
+            .expect("checked-out store is present until drop")
+    }
+}
+
+impl<S> Drop for Checkout<'_, S> {
+    fn drop(&mut self) {
+        if let Some(store) = self.store.take() {
+            self.pool.checkin(store);
+        }
+    }
+}
+
+impl<S> Pool<S> {
+    fn checkout(&self) -> Result<S, StoreError> {
+        let idle = self
+            .idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        idle.map_or_else(|| (self.open)(), Ok)
+    }
+
+    fn checkin(&self, store: S) {
+        self.idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(store);
+    }
+}
+
+impl<S: ObjectStore + Send + 'static> AsyncObjectStore for BlockingStore<S> {
+    async fn put_object(&self, id: Hash, data: Vec<u8>) -> Result<(), StoreError> {
+        self.run(move |s| s.put_object(&id, &data)).await
+    }
+
+    async fn get_object(&self, id: Hash) -> Result<Option<Vec<u8>>, StoreError> {
+        self.run(move |s| s.get_object(&id)).await
+    }
+
+    async fn has_object(&self, id: Hash) -> Result<bool, StoreError> {
+        self.run(move |s| s.has_object(&id)).await
+    }
+}
+
+/// Objects larger than this are hashed on the blocking pool.
+const INLINE_VERIFY_LIMIT: usize = 64 * 1024;
+
+/// Async store over a filesystem object directory.
+///
+/// Writes go to a temporary file that is renamed into place, so readers
+/// never observe a partial object.
+#[derive(Debug, Clone)]
+pub struct FsObjectStore {
+    root: PathBuf,
+}
+
+impl FsObjectStore {
+    /// Store rooted at `root`, usually `<repo>/gatos/objects`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Location of the object `id`.
+    #[must_use]
+    pub fn path(&self, id: &Hash) -> PathBuf {
+        self.root.join("blake3").join(hex::encode(id))
+    }
+}
+
+impl AsyncObjectStore for FsObjectStore {
+    async fn put_object(&self, id: Hash, data: Vec<u8>) -> Result<(), StoreError> {
+        let data = if data.len() > INLINE_VERIFY_LIMIT {
+            tokio::task::spawn_blocking(move || verify_object_id(&id, &data).map(|()| data))
+                .await
+                .map_err(|e| BackendError::from_source(BackendErrorKind::Other, e))??
+        } else {
+            verify_object_id(&id, &data)?;
+            data
+        };
+        let path = self.path(&id);
+        if tokio::fs::try_exists(&path).await.map_err(fs_error)? {
+            return Ok(());
+        }
+        let dir = path
+            .parent()
+            .ok_or(StoreError::Invariant("object path has a parent"))?;
+        tokio::fs::create_dir_all(dir).await.map_err(fs_error)?;
+        static TMP: AtomicU64 = AtomicU64::new(0);
+        let tmp = dir.join(format!(
+            ".{}.{}.{}.tmp",
+            hex::encode(id),
+            std::process::id(),
+            TMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        if let Err(e) = tokio::fs::write(&tmp, &data).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(fs_error(e));
+        }
+        tokio::fs::rename(&tmp, &path).await.map_err(fs_error)
+    }
+
+    async fn get_object(&self, id: Hash) -> Result<Option<Vec<u8>>, StoreError> {
+        match tokio::fs::read(self.path(&id)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(fs_error(e)),
+        }
+    }
+
+    async fn has_object(&self, id: Hash) -> Result<bool, StoreError> {
+        tokio::fs::try_exists(self.path(&id))
+            .await
+            .map_err(fs_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempRepo;
+    use crate::GitStore;
+
+    fn id(data: &[u8]) -> Hash {
+        blake3::hash(data).into()
+    }
+
+    async fn exercise(store: &impl AsyncObjectStore) {
+        let big = vec![7u8; 3 * INLINE_VERIFY_LIMIT];
+        assert!(matches!(
+            store.put_object([0; 32], big.clone()).await,
+            Err(StoreError::Corruption { .. })
+        ));
+        store.put_object(id(&big), big.clone()).await.unwrap();
+        store.put_object(id(&big), big.clone()).await.unwrap(); // idempotent
+        assert_eq!(store.get_object(id(&big)).await.unwrap(), Some(big));
+        assert!(!store.has_object([1; 32]).await.unwrap());
+        assert_eq!(store.get_object([1; 32]).await.unwrap(), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn blocking_git_store_runs_calls_concurrently() {
+        let tmp = TempRepo::new();
+        let path = tmp.path.clone();
+        let store = BlockingStore::new(4, move || {
+            git2::Repository::open(&path)
+                .map(GitStore::new)
+                .map_err(|e| BackendError::from_source(BackendErrorKind::Io, e).into())
+        });
+        exercise(&store).await;
+
+        let tasks: Vec<_> = (0..16u8)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move { store.put_object(id(&[i]), vec![i]).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        for i in 0..16u8 {
+            assert!(store.has_object(id(&[i])).await.unwrap());
+        }
+        assert!(store.pool.idle.lock().unwrap().len() <= 4);
+    }
+
+    #[tokio::test]
+    async fn fs_store_lays_out_objects_by_algorithm() {
+        let tmp = TempRepo::new();
+        let store = FsObjectStore::new(tmp.path.join("gatos/objects"));
+        exercise(&store).await;
+        let data = b"hello".to_vec();
+        store.put_object(id(&data), data.clone()).await.unwrap();
+        let on_disk = std::fs::read(
+            tmp.path
+                .join("gatos/objects/blake3")
+                .join(hex::encode(id(&data))),
+        );
+        assert_eq!(on_disk.unwrap(), data);
+
+        let single = BlockingStore::from_store(crate::MemoryStore::new());
+        exercise(&single).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn cancelled_calls_keep_their_slot_and_panics_keep_the_store() {
+        let store = BlockingStore::from_store(crate::MemoryStore::new());
+        let (started_tx, started) = std::sync::mpsc::channel();
+        let (release, release_rx) = std::sync::mpsc::channel::<()>();
+        let call = tokio::spawn({
+            let store = store.clone();
+            async move {
+                store
+                    .run(move |_| {
+                        started_tx.send(()).unwrap();
+                        release_rx.recv().unwrap();
+                        Ok(())
+                    })
+                    .await
+            }
+        });
+        tokio::task::spawn_blocking(move || started.recv().unwrap())
+            .await
+            .unwrap();
+        // The caller goes away; the running call still holds the only slot.
+        call.abort();
+        assert!(call.await.unwrap_err().is_cancelled());
+        assert_eq!(store.pool.permits.available_permits(), 0);
+        release.send(()).unwrap();
+        exercise(&store).await;
+
+        let panicked = store.run(|_| -> Result<(), StoreError> { panic!("boom") });
+        assert!(matches!(panicked.await, Err(StoreError::Backend(_))));
+        exercise(&store).await;
+    }
+}
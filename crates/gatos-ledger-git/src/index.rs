@@ -0,0 +1,502 @@
+//! Content-address → git OID index kept under a single ref.
+//!
+//! The index is a commit on [`INDEX_REF`] whose tree is sharded by the first
+//! two digest bytes:
+//!
+//! ```text
+//! <alg>/<byte0 hex>/<byte1 hex>  blob of sorted records: digest[32] || oid[20]
+//! ```
+//!
+//! A lookup resolves one tree path and binary-searches one small shard, so it
+//! stays O(log n) however many objects are mapped. Every update commits a new
+//! index state on top of the previous one (append-only history) and moves the
+//! ref with compare-and-swap, retrying on a lost race. Resolved entries are
+//! cached in-process. When the ref moves, cached entries in shards the new
+//! commits touched are evicted, since a mapping can be replaced (a commit
+//! address re-pointed at a copy with more signatures); the cache is dropped
+//! whenever a removal lands.
+//!
+//! Replaces the earlier one-ref-per-object layout
+//! (`refs/gatos/<alg>-map/<hex>`), which is still read as a fallback until
+//! [`GitStore::migrate_legacy_refs`](crate::GitStore::migrate_legacy_refs)
+//! folds it into the index.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use git2::{ErrorCode, Oid, Repository, Signature, Tree};
+
+use crate::{
+    cas_ref, io, BackendError, BackendErrorKind, Backoff, CommitOid, HashAlg, Multihash, StoreError,
+};
+
+/// Ref holding the index commit.
+pub const INDEX_REF: &str = "refs/gatos/object-index";
+
+const OID_LEN: usize = 20;
+const RECORD: usize = 32 + OID_LEN;
+/// Index updates are small and frequent, so retry sooner and more often
+/// than the SPEC §4.2 journal schedule.
+const CAS_BACKOFF: Backoff = Backoff {
+    base: Duration::from_millis(2),
+    max: Duration::from_millis(250),
+    attempts: 10,
+};
+/// Cached entries before the cache is reset.
+const CACHE_CAPACITY: usize = 1 << 16;
+
+/// Pending edits: shard path → digest → new oid (`None` removes).
+type Edits = BTreeMap<String, BTreeMap<[u8; 32], Option<Oid>>>;
+
+const MAP_MESSAGE: &str = "gatos: map content addresses";
+const UNMAP_MESSAGE: &str = "gatos: unmap content addresses";
+/// Index commits walked when revalidating the cache after the ref moved.
+const CACHE_REVALIDATE_DEPTH: usize = 32;
+
+/// Resolved entries, valid as of index commit `head`.
+#[derive(Default)]
+struct Cache {
+    head: Option<Oid>,
+    entries: HashMap<Multihash, Oid>,
+}
+
+#[derive(Default)]
+pub(crate) struct OidIndex {
+    cache: RefCell<Cache>,
+}
+
+impl OidIndex {
+    pub(crate) fn lookup(
+        &self,
+        repo: &Repository,
+        id: &Multihash,
+    ) -> Result<Option<Oid>, StoreError> {
+        let Some(head) = self.sync(repo)? else {
+            return Ok(None);
+        };
+        if let Some(oid) = self.cache.borrow().entries.get(id) {
+            return Ok(Some(*oid));
+        }
+        let tree = repo.find_commit(head).and_then(|c| c.tree()).map_err(io)?;
+        let found = match tree.get_path(shard_path(id).as_ref()) {
+            Ok(entry) => {
+                let blob = repo.find_blob(entry.id()).map_err(io)?;
+                find_record(shard_records(blob.content())?, id.digest())
+            }
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(io(e)),
+        };
+        if let Some(oid) = found {
+            let mut cache = self.cache.borrow_mut();
+            if cache.entries.len() >= CACHE_CAPACITY {
+                cache.entries.clear();
+            }
+            cache.entries.insert(*id, oid);
+        }
+        Ok(found)
+    }
+
+    /// Bring the cache up to the current index head and return that head.
+    ///
+    /// Cached entries survive the ref moving only if every commit since the
+    /// cached head merely added or replaced mappings, and only outside the
+    /// shards those commits touched; any removal (by this or another
+    /// process) drops the cache.
+    fn sync(&self, repo: &Repository) -> Result<Option<Oid>, StoreError> {
+        let head = match repo.refname_to_id(INDEX_REF) {
+            Ok(oid) => Some(oid),
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(io(e)),
+        };
+        let mut cache = self.cache.borrow_mut();
+        if cache.head != head {
+            let touched = match (cache.head, head) {
+                (Some(old), Some(new)) => shards_mapped_since(repo, old, new)?,
+                _ => None,
+            };
+            match touched {
+                Some(shards) => cache
+                    .entries
+                    .retain(|id, _| !shards.contains(&shard_path(id))),
+                None => cache.entries.clear(),
+            }
+            cache.head = head;
+        }
+        Ok(head)
+    }
+
+    /// Map every `(id, oid)` pair in one index commit.
+    pub(crate) fn insert(
+        &self,
+        repo: &Repository,
+        entries: &[(Multihash, Oid)],
+    ) -> Result<(), StoreError> {
+        let mut edits = Edits::new();
+        for (id, oid) in entries {
+            edits
+                .entry(shard_path(id))
+                .or_default()
+                .insert(*id.digest(), Some(*oid));
+        }
+        commit_edits(repo, &edits, MAP_MESSAGE)?;
+        self.sync(repo)?;
+        let mut cache = self.cache.borrow_mut();
+        for (id, oid) in entries {
+            cache.entries.insert(*id, *oid);
+        }
+        Ok(())
+    }
+
+    /// Unmap `ids`; returns the subset that was mapped.
+    pub(crate) fn remove(
+        &self,
+        repo: &Repository,
+        ids: &[Multihash],
+    ) -> Result<Vec<Multihash>, StoreError> {
+        let mut removed = Vec::new();
+        let mut edits = Edits::new();
+        for id in ids {
+            if self.lookup(repo, id)?.is_some() {
+                removed.push(*id);
+                edits
+                    .entry(shard_path(id))
+                    .or_default()
+                    .insert(*id.digest(), None);
+            }
+        }
+        if !edits.is_empty() {
+            commit_edits(repo, &edits, UNMAP_MESSAGE)?;
+        }
+        *self.cache.borrow_mut() = Cache::default();
+        Ok(removed)
+    }
+
+    /// Every mapped `(id, oid)` for `alg`, in digest order.
+    pub(crate) fn entries(
+        &self,
+        repo: &Repository,
+        alg: HashAlg,
+    ) -> Result<Vec<(Multihash, Oid)>, StoreError> {
+        let Some(tree) = head_tree(repo)? else {
+            return Ok(Vec::new());
+        };
+        let root = match tree.get_name(alg.prefix()) {
+            Some(entry) => repo.find_tree(entry.id()).map_err(io)?,
+            None => return Ok(Vec::new()),
+        };
+        let mut out = Vec::new();
+        for level1 in &root {
+            let level1 = repo.find_tree(level1.id()).map_err(io)?;
+            for shard in &level1 {
+                let blob = repo.find_blob(shard.id()).map_err(io)?;
+                for record in shard_records(blob.content())?.chunks_exact(RECORD) {
+                    let (digest, oid) = split_record(record);
+                    out.push((Multihash::new(alg, digest), oid));
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn shard_path(id: &Multihash) -> String {
+    let d = id.digest();
+    format!("{}/{:02x}/{:02x}", id.alg().prefix(), d[0], d[1])
+}
+
+/// Shard paths changed between `old` and `new`, if `new` descends from
+/// `old` through at most [`CACHE_REVALIDATE_DEPTH`] commits that only mapped
+/// ids; `None` otherwise.
+fn shards_mapped_since(
+    repo: &Repository,
+    old: Oid,
+    new: Oid,
+) -> Result<Option<HashSet<String>>, StoreError> {
+    let mut current = new;
+    let mut descends = false;
+    for _ in 0..CACHE_REVALIDATE_DEPTH {
+        let commit = repo.find_commit(current).map_err(io)?;
+        if commit.message() != Some(MAP_MESSAGE) {
+            return Ok(None);
+        }
+        match commit.parent_ids().next() {
+            Some(parent) if parent == old => {
+                descends = true;
+                break;
+            }
+            Some(parent) => current = parent,
+            None => return Ok(None),
+        }
+    }
+    if !descends {
+        return Ok(None);
+    }
+    let tree = |oid| repo.find_commit(oid).and_then(|c| c.tree()).map_err(io);
+    let diff = repo
+        .diff_tree_to_tree(Some(&tree(old)?), Some(&tree(new)?), None)
+        .map_err(io)?;
+    Ok(Some(
+        diff.deltas()
+            .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+            .filter_map(|p| p.to_str().map(str::to_string))
+            .collect(),
+    ))
+}
+
+fn head_tree(repo: &Repository) -> Result<Option<Tree<'_>>, StoreError> {
+    match repo.find_reference(INDEX_REF) {
+        Ok(r) => r.peel_to_tree().map(Some).map_err(io),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(io(e)),
+    }
+}
+
+fn malformed(what: &str) -> StoreError {
+    BackendError::new(BackendErrorKind::Other, format!("malformed index: {what}")).into()
+}
+
+fn shard_records(bytes: &[u8]) -> Result<&[u8], StoreError> {
+    if bytes.chunks_exact(RECORD).remainder().is_empty() {
+        Ok(bytes)
+    } else {
+        Err(malformed("truncated shard"))
+    }
+}
+
+fn split_record(record: &[u8]) -> ([u8; 32], Oid) {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&record[..32]);
+    let oid = Oid::from_bytes(&record[32..]).expect("record holds a 20-byte oid");
+    (digest, oid)
+}
+
+/// Binary search over a shard's sorted fixed-size records.
+fn find_record(records: &[u8], digest: &[u8; 32]) -> Option<Oid> {
+    let (mut lo, mut hi) = (0, records.len() / RECORD);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let record = &records[mid * RECORD..(mid + 1) * RECORD];
+        match record[..32].cmp(&digest[..]) {
+            std::cmp::Ordering::Less => lo = mid + 1,
+            std::cmp::Ordering::Greater => hi = mid,
+            std::cmp::Ordering::Equal => return Some(split_record(record).1),
+        }
+    }
+    None
+}
+
+/// Apply `edits` on top of the current index and move [`INDEX_REF`] with
+/// compare-and-swap, rebuilding from the new head after a lost race (with
+/// jittered backoff, see [`CAS_BACKOFF`]).
+fn commit_edits(repo: &Repository, edits: &Edits, message: &str) -> Result<(), StoreError> {
+    let sig = Signature::now("gatos", "gatos@localhost").map_err(io)?;
+    let mut last = None;
+    for attempt in 0..CAS_BACKOFF.attempts {
+        if attempt > 0 {
+            std::thread::sleep(CAS_BACKOFF.delay(attempt - 1));
+        }
+        let head = match repo.find_reference(INDEX_REF) {
+            Ok(r) => Some(r.peel_to_commit().map_err(io)?),
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(io(e)),
+        };
+        let base = head
+            .as_ref()
+            .map(git2::Commit::tree)
+            .transpose()
+            .map_err(io)?;
+        let mut root = base.as_ref().map(Tree::id);
+        for (path, changes) in edits {
+            root = apply_shard(repo, root, path, changes)?;
+        }
+        let root = match root {
+            Some(oid) => oid,
+            None => repo.treebuilder(None).and_then(|b| b.write()).map_err(io)?,
+        };
+        if base.as_ref().map(Tree::id) == Some(root) {
+            return Ok(());
+        }
+        let tree = repo.find_tree(root).map_err(io)?;
+        let parents: Vec<&git2::Commit<'_>> = head.iter().collect();
+        let new = repo
+            .commit(None, &sig, &sig, message, &tree, &parents)
+            .map_err(io)?;
+        let old = head.as_ref().map(git2::Commit::id);
+        if cas_ref(repo, INDEX_REF, new, old, message)? {
+            return Ok(());
+        }
+        last = Some((old, new));
+    }
+    let (old, new) = last.ok_or(StoreError::Invariant("CAS loop runs at least once"))?;
+    let oid = |o: Oid| o.to_string().parse::<CommitOid>().ok();
+    Err(StoreError::Conflict {
+        reference: INDEX_REF.to_string(),
+        old: old.and_then(oid),
+        new: oid(new).ok_or(StoreError::Invariant("git oids are hex"))?,
+    })
+}
+
+/// Rewrite one shard under `root`, returning the new root (`None` if empty).
+fn apply_shard(
+    repo: &Repository,
+    root: Option<Oid>,
+    path: &str,
+    changes: &BTreeMap<[u8; 32], Option<Oid>>,
+) -> Result<Option<Oid>, StoreError> {
+    let root = root
+        .map(|oid| repo.find_tree(oid))
+        .transpose()
+        .map_err(io)?;
+    let mut records: BTreeMap<[u8; 32], Oid> = BTreeMap::new();
+    if let Some(entry) = root.as_ref().and_then(|t| t.get_path(path.as_ref()).ok()) {
+        let blob = repo.find_blob(entry.id()).map_err(io)?;
+        for record in shard_records(blob.content())?.chunks_exact(RECORD) {
+            let (digest, oid) = split_record(record);
+            records.insert(digest, oid);
+        }
+    }
+    for (digest, change) in changes {
+        match change {
+            Some(oid) => records.insert(*digest, *oid),
+            None => records.remove(digest),
+        };
+    }
+    let shard = if records.is_empty() {
+        None
+    } else {
+        let mut bytes = Vec::with_capacity(records.len() * RECORD);
+        for (digest, oid) in &records {
+            bytes.extend_from_slice(digest);
+            bytes.extend_from_slice(oid.as_bytes());
+        }
+        Some(repo.blob(&bytes).map_err(io)?)
+    };
+    let segments: Vec<&str> = path.split('/').collect();
+    set_path(repo, root.as_ref(), &segments, shard).map_err(io)
+}
+
+/// Set (or with `None`, remove) the blob at `path`, pruning empty trees.
+fn set_path(
+    repo: &Repository,
+    tree: Option<&Tree<'_>>,
+    path: &[&str],
+    blob: Option<Oid>,
+) -> Result<Option<Oid>, git2::Error> {
+    let (name, rest) = path.split_first().expect("shard paths are non-empty");
+    let mut builder = repo.treebuilder(tree)?;
+    let child = if rest.is_empty() {
+        blob.map(|oid| (oid, 0o100_644))
+    } else {
+        let sub = match tree.and_then(|t| t.get_name(name)) {
+            Some(entry) => Some(repo.find_tree(entry.id())?),
+            None => None,
+        };
+        set_path(repo, sub.as_ref(), rest, blob)?.map(|oid| (oid, 0o040_000))
+    };
+    match child {
+        Some((oid, mode)) => {
+            builder.insert(name, oid, mode)?;
+        }
+        None if builder.get(name)?.is_some() => builder.remove(name)?,
+        None => {}
+    }
+    if builder.is_empty() {
+        Ok(None)
+    } else {
+        builder.write().map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempRepo;
+
+    fn mh(alg: HashAlg, n: u32) -> Multihash {
+        Multihash::of(alg, &n.to_le_bytes())
+    }
+
+    #[test]
+    fn insert_lookup_remove_across_shards() {
+        let tmp = TempRepo::new();
+        let repo = tmp.open();
+        let index = OidIndex::default();
+        let blob = repo.blob(b"x").unwrap();
+        let entries: Vec<_> = (0..300).map(|n| (mh(HashAlg::Blake3, n), blob)).collect();
+        index.insert(&repo, &entries).unwrap();
+        index
+            .insert(&repo, &[(mh(HashAlg::Sha256, 0), blob)])
+            .unwrap();
+
+        let fresh = OidIndex::default();
+        for (id, _) in &entries {
+            assert_eq!(fresh.lookup(&repo, id).unwrap(), Some(blob));
+        }
+        assert_eq!(
+            fresh.lookup(&repo, &mh(HashAlg::Blake3, 999)).unwrap(),
+            None
+        );
+        assert_eq!(fresh.entries(&repo, HashAlg::Blake3).unwrap().len(), 300);
+        assert_eq!(fresh.entries(&repo, HashAlg::Sha256).unwrap().len(), 1);
+
+        let gone = [mh(HashAlg::Blake3, 7), mh(HashAlg::Blake3, 1000)];
+        assert_eq!(fresh.remove(&repo, &gone).unwrap(), [gone[0]]);
+        assert_eq!(index.lookup(&repo, &gone[0]).unwrap(), None);
+        assert_eq!(index.entries(&repo, HashAlg::Blake3).unwrap().len(), 299);
+
+        // Every update is a new commit on top of the previous index state.
+        let head = repo
+            .find_reference(INDEX_REF)
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(head.parent_count(), 1);
+    }
+
+    #[test]
+    fn remapped_ids_are_evicted_from_other_caches() {
+        let tmp = TempRepo::new();
+        let repo = tmp.open();
+        let (a, b) = (OidIndex::default(), OidIndex::default());
+        let (old, new) = (repo.blob(b"old").unwrap(), repo.blob(b"new").unwrap());
+        let (id, other) = (mh(HashAlg::Blake3, 1), mh(HashAlg::Blake3, 2));
+        a.insert(&repo, &[(id, old), (other, old)]).unwrap();
+        assert_eq!(b.lookup(&repo, &id).unwrap(), Some(old));
+        assert_eq!(b.lookup(&repo, &other).unwrap(), Some(old));
+
+        a.insert(&repo, &[(id, new)]).unwrap();
+        assert_eq!(b.lookup(&repo, &id).unwrap(), Some(new));
+        // Entries in untouched shards stay cached.
+        assert!(shard_path(&id) != shard_path(&other));
+        assert!(b.cache.borrow().entries.contains_key(&other));
+    }
+
+    #[test]
+    fn concurrent_writers_both_land() {
+        let tmp = TempRepo::new();
+        let blob = tmp.open().blob(b"x").unwrap();
+        std::thread::scope(|s| {
+            for t in 0..4u32 {
+                let path = &tmp.path;
+                s.spawn(move || {
+                    let repo = Repository::open(path).unwrap();
+                    let index = OidIndex::default();
+                    for n in 0..10 {
+                        index
+                            .insert(&repo, &[(mh(HashAlg::Blake3, t * 100 + n), blob)])
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        let repo = tmp.open();
+        assert_eq!(
+            OidIndex::default()
+                .entries(&repo, HashAlg::Blake3)
+                .unwrap()
+                .len(),
+            40
+        );
+    }
+}
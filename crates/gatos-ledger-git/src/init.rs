@@ -0,0 +1,280 @@
+//! `gatos init`: create the normative GATOS layout (SPEC §2) in a repository.
+//!
+//! Steps, in order:
+//!
+//! 1. create `gatos/{policies,schema,folds,trust,objects,config}` at the
+//!    work tree root (the git directory for bare repositories) and claim the
+//!    layout by creating `gatos/config/profile.yaml`, which fails if it
+//!    already exists;
+//! 2. commit an empty policy bundle on `refs/gatos/policies/<bundle-id>` and
+//!    point `refs/gatos/policies/active` at it;
+//! 3. enable `fetch.writeCommitGraph` and `repack.writeBitmaps` (SPEC §8).
+//!
+//! Existing files under `gatos/` are left alone, but a repository that
+//! already has a profile or an active policy is refused rather than
+//! overwritten. Of two concurrent runs only one claims the profile, and a
+//! run that fails after claiming it removes it again, so it can be retried.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use git2::{Oid, Repository, Signature};
+
+use crate::{cas_ref, fs_error, io, HashAlg, Multihash, StoreError};
+
+/// Workspace directories under `gatos/`.
+pub const LAYOUT_DIRS: [&str; 6] = ["policies", "schema", "folds", "trust", "objects", "config"];
+/// Ref naming the effective policy bundle.
+pub const ACTIVE_POLICY_REF: &str = "refs/gatos/policies/active";
+const PROFILE_PATH: &str = "gatos/config/profile.yaml";
+/// Canonical policy tree of the empty bundle: the DAG-CBOR empty map.
+const EMPTY_POLICY_TREE: &[u8] = &[0xa0];
+
+/// Options for [`init`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitOptions {
+    /// Written to `profile.yaml` (SPEC §12), e.g. `local` or `research`.
+    pub profile: String,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            profile: "local".to_string(),
+        }
+    }
+}
+
+/// What [`init`] created.
+///
+/// SPEC §2 says bundle commits MUST carry a `Policy-Root: <commit-oid>`
+/// trailer. The empty bundle deliberately leaves it out: a commit cannot
+/// name its own oid, so it carries only `Policy-Code-Root`, and
+/// [`InitReport::bundle`] is the policy root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitReport {
+    /// Directory holding `gatos/`.
+    pub root: PathBuf,
+    /// `refs/gatos/policies/<bundle-id>`.
+    pub bundle_ref: String,
+    /// Commit of the empty policy bundle, now active.
+    pub bundle: Oid,
+}
+
+/// Errors produced by [`init`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitError {
+    /// The repository already has a GATOS layout; names what was found.
+    AlreadyInitialized(String),
+    /// The profile name cannot be written as a plain YAML scalar.
+    InvalidProfile(String),
+    Store(StoreError),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyInitialized(what) => write!(f, "already initialized: {what} exists"),
+            Self::InvalidProfile(name) => write!(f, "invalid profile name `{name}`"),
+            Self::Store(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Store(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StoreError> for InitError {
+    fn from(e: StoreError) -> Self {
+        Self::Store(e)
+    }
+}
+
+/// Initialize the GATOS layout in the repository at `path`.
+///
+/// # Errors
+/// Returns [`InitError::AlreadyInitialized`] if `profile.yaml` or
+/// [`ACTIVE_POLICY_REF`] already exists, [`InitError::InvalidProfile`] for a
+/// profile name outside `[a-z0-9-]`, and store errors for git or filesystem
+/// failures.
+pub fn init(path: &Path, options: &InitOptions) -> Result<InitReport, InitError> {
+    let valid = !options.profile.is_empty()
+        && options
+            .profile
+            .bytes()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-');
+    if !valid {
+        return Err(InitError::InvalidProfile(options.profile.clone()));
+    }
+    let repo = Repository::open(path).map_err(io)?;
+    let root = repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf();
+    for dir in LAYOUT_DIRS {
+        std::fs::create_dir_all(root.join("gatos").join(dir)).map_err(fs_error)?;
+    }
+    let profile = root.join(PROFILE_PATH);
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&profile)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(InitError::AlreadyInitialized(PROFILE_PATH.to_string()))
+        }
+        Err(e) => return Err(fs_error(e).into()),
+    };
+    let written = file
+        .write_all(format!("profile: {}\n", options.profile).as_bytes())
+        .map_err(|e| InitError::from(fs_error(e)))
+        .and_then(|()| write_empty_bundle(&repo));
+    drop(file);
+    let (bundle_ref, bundle) = match written {
+        Ok(written) => written,
+        Err(e) => {
+            // Release the claim; the error is what the caller needs to see.
+            let _ = std::fs::remove_file(&profile);
+            return Err(e);
+        }
+    };
+
+    let mut config = repo.config().map_err(io)?;
+    config
+        .set_bool("fetch.writeCommitGraph", true)
+        .map_err(io)?;
+    config.set_bool("repack.writeBitmaps", true).map_err(io)?;
+
+    Ok(InitReport {
+        root,
+        bundle_ref,
+        bundle,
+    })
+}
+
+/// Commit the empty bundle and make it active.
+///
+/// The bundle id is the BLAKE3 hash of the canonical (empty) policy tree.
+/// The commit carries `Policy-Code-Root`; it cannot carry `Policy-Root`,
+/// which names the bundle commit itself.
+fn write_empty_bundle(repo: &Repository) -> Result<(String, Oid), InitError> {
+    match repo.find_reference(ACTIVE_POLICY_REF) {
+        Ok(_) => return Err(InitError::AlreadyInitialized(ACTIVE_POLICY_REF.to_string())),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+        Err(e) => return Err(io(e).into()),
+    }
+    let bundle_id = Multihash::of(HashAlg::Blake3, EMPTY_POLICY_TREE);
+    let code_root = Multihash::of(HashAlg::Sha256, b"");
+    let bundle_ref = format!("refs/gatos/policies/{}", bundle_id.to_hex());
+    let tree = repo
+        .treebuilder(None)
+        .and_then(|b| b.write())
+        .and_then(|oid| repo.find_tree(oid))
+        .map_err(io)?;
+    let sig = Signature::now("gatos", "gatos@localhost").map_err(io)?;
+    let message = format!("gatos: empty policy bundle\n\nPolicy-Code-Root: {code_root}\n");
+    let bundle = repo
+        .commit(None, &sig, &sig, &message, &tree, &[])
+        .map_err(io)?;
+    for name in [bundle_ref.as_str(), ACTIVE_POLICY_REF] {
+        if !cas_ref(repo, name, bundle, None, "gatos: init")? {
+            return Err(InitError::AlreadyInitialized(name.to_string()));
+        }
+    }
+    Ok((bundle_ref, bundle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempRepo;
+
+    #[test]
+    fn creates_layout_once() {
+        let tmp = TempRepo::new();
+        let report = init(&tmp.path, &InitOptions::default()).unwrap();
+        assert_eq!(report.root, tmp.path);
+        for dir in LAYOUT_DIRS {
+            assert!(tmp.path.join("gatos").join(dir).is_dir(), "{dir}");
+        }
+        assert_eq!(
+            std::fs::read_to_string(tmp.path.join(PROFILE_PATH)).unwrap(),
+            "profile: local\n"
+        );
+
+        let repo = tmp.open();
+        assert_eq!(
+            repo.refname_to_id(ACTIVE_POLICY_REF).unwrap(),
+            report.bundle
+        );
+        assert_eq!(
+            repo.refname_to_id(&report.bundle_ref).unwrap(),
+            report.bundle
+        );
+        let bundle = repo.find_commit(report.bundle).unwrap();
+        assert_eq!(bundle.tree().unwrap().len(), 0);
+        assert!(bundle.message().unwrap().contains(
+            "Policy-Code-Root: sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        ));
+        let config = repo.config().unwrap();
+        assert!(config.get_bool("fetch.writeCommitGraph").unwrap());
+        assert!(config.get_bool("repack.writeBitmaps").unwrap());
+
+        assert_eq!(
+            init(&tmp.path, &InitOptions::default()),
+            Err(InitError::AlreadyInitialized(PROFILE_PATH.to_string()))
+        );
+        std::fs::remove_file(tmp.path.join(PROFILE_PATH)).unwrap();
+        assert_eq!(
+            init(&tmp.path, &InitOptions::default()),
+            Err(InitError::AlreadyInitialized(ACTIVE_POLICY_REF.to_string()))
+        );
+        assert_eq!(
+            repo.refname_to_id(ACTIVE_POLICY_REF).unwrap(),
+            report.bundle
+        );
+        // The refused run gives back the profile it claimed.
+        assert!(!tmp.path.join(PROFILE_PATH).exists());
+    }
+
+    #[test]
+    fn failed_init_can_be_retried() {
+        let tmp = TempRepo::new();
+        let repo = tmp.open();
+        // A ref file in the way makes the bundle refs unwritable.
+        let refs = repo.path().join("refs/gatos/policies");
+        std::fs::create_dir_all(refs.parent().unwrap()).unwrap();
+        std::fs::write(&refs, "").unwrap();
+        assert!(matches!(
+            init(&tmp.path, &InitOptions::default()),
+            Err(InitError::Store(_))
+        ));
+        assert!(!tmp.path.join(PROFILE_PATH).exists());
+
+        std::fs::remove_file(&refs).unwrap();
+        let report = init(&tmp.path, &InitOptions::default()).unwrap();
+        assert_eq!(
+            repo.refname_to_id(ACTIVE_POLICY_REF).unwrap(),
+            report.bundle
+        );
+    }
+
+    #[test]
+    fn rejects_odd_profiles() {
+        let tmp = TempRepo::new();
+        let options = InitOptions {
+            profile: "a: b".to_string(),
+        };
+        assert!(matches!(
+            init(&tmp.path, &options),
+            Err(InitError::InvalidProfile(_))
+        ));
+        assert!(!tmp.path.join("gatos").exists());
+    }
+}
@@ -0,0 +1,621 @@
+//! Append-only event journals (SPEC §4.2).
+//!
+//! Each `(namespace, actor)` pair owns one linear history on
+//! `refs/gatos/journal/<ns>/<actor>`. Actors take the SPEC §3.1 form
+//! `<kind>:<name>` (`user:alice`); `:` cannot appear in a ref name, so the
+//! actor is written as the path `<kind>/<name>`, which maps back one to one
+//! (see [`journal_ref`]). An append writes an event commit whose
+//! only parent is the current head and then moves the ref with
+//! compare-and-swap. A writer that loses the race rebuilds the commit on the
+//! new head and retries after a jittered exponential delay ([`Backoff`]);
+//! merges are never created, and a journal whose head is already a merge is
+//! refused.
+//!
+//! Event commits are native ledger commits (see
+//! [`GitStore::read_native_commit`](crate::GitStore::read_native_commit)),
+//! so the ledger verifier and the journal reader agree on one format:
+//!
+//! ```text
+//! tree     event.cbor                     canonical DAG-CBOR envelope bytes
+//! message  <type> <ulid>
+//!
+//!          Content-Id: blake3:<content id>
+//!          Event-CID: <cid>
+//!          Sig-Alg: <alg>                 (signed envelopes only)
+//!          Signer: <alg>:<signer hex>     (signed envelopes only)
+//!          Sig: <alg>:<sig hex>           (signed envelopes only)
+//! ```
+//!
+//! The core parent is the content id of the previous event commit and the
+//! core timestamp is the commit time.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+use git2::{ErrorCode, Oid, Repository};
+
+use crate::native::{self, NativeCommitError};
+use crate::{
+    cas_ref, compute_content_id, event_cid, io, rfc3339_utc_seconds, CborError, CommitCore,
+    CommitOid, EnvelopeError, EnvelopeSig, EventEnvelope, EventTrailers, SigAlg, StoreError,
+    TrailerSig,
+};
+
+/// Namespace shared by every journal ref.
+pub const JOURNAL_PREFIX: &str = "refs/gatos/journal/";
+/// Tree entry holding the canonical envelope bytes.
+pub const EVENT_BLOB: &str = "event.cbor";
+
+/// Errors produced while appending to a journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JournalError {
+    /// The namespace or actor cannot form a journal ref name.
+    InvalidName(String),
+    /// The envelope has no canonical encoding.
+    Envelope(CborError),
+    /// The envelope signature names a key whose algorithm cannot be inferred
+    /// or disagrees with the declared `sig_alg`.
+    Signature(EnvelopeError),
+    /// The envelope's `actor` is not the actor whose journal it was
+    /// appended to.
+    ActorMismatch { journal: String, event: String },
+    /// The envelope's `ts` is not a real `YYYY-MM-DDTHH:MM:SSZ` instant (see
+    /// [`rfc3339_utc_seconds`]), the only form envelopes are decoded from.
+    Timestamp(String),
+    /// The journal head is a merge commit; journals must stay linear.
+    NonLinear { reference: String, head: Oid },
+    /// A read was asked to resume after a commit that is not in its
+    /// journal's history.
+    NotInJournal(Oid),
+    /// A followed journal no longer descends from the head last read.
+    Rewritten { reference: String, last_seen: Oid },
+    /// The commit is not a journal event; names the problem.
+    Malformed { oid: Oid, what: &'static str },
+    /// A journal commit is not a valid native ledger commit.
+    Native(NativeCommitError),
+    /// Storage failure, including [`StoreError::Conflict`] once every
+    /// attempt lost the compare-and-swap race.
+    Store(StoreError),
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName(name) => write!(f, "invalid journal name `{name}`"),
+            Self::Envelope(e) => write!(f, "event has no canonical encoding: {e:?}"),
+            Self::Signature(e) => write!(f, "event signature is unusable: {e:?}"),
+            Self::ActorMismatch { journal, event } => {
+                write!(
+                    f,
+                    "event by `{event}` cannot go in the journal of `{journal}`"
+                )
+            }
+            Self::Timestamp(ts) => write!(f, "event timestamp `{ts}` is not RFC 3339 UTC"),
+            Self::NonLinear { reference, head } => {
+                write!(f, "{reference} is not linear: head {head} is a merge")
+            }
+            Self::NotInJournal(oid) => write!(f, "{oid} is not in its journal's history"),
+            Self::Rewritten {
+                reference,
+                last_seen,
+            } => write!(f, "{reference} was rewritten: {last_seen} is gone"),
+            Self::Malformed { oid, what } => write!(f, "{oid} is not a journal event: {what}"),
+            Self::Native(e) => fmt::Display::fmt(e, f),
+            Self::Store(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for JournalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Store(e) => Some(e),
+            Self::Native(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StoreError> for JournalError {
+    fn from(e: StoreError) -> Self {
+        Self::Store(e)
+    }
+}
+
+impl From<NativeCommitError> for JournalError {
+    fn from(e: NativeCommitError) -> Self {
+        match e {
+            NativeCommitError::Store(e) => Self::Store(e),
+            e => Self::Native(e),
+        }
+    }
+}
+
+impl From<CborError> for JournalError {
+    fn from(e: CborError) -> Self {
+        Self::Envelope(e)
+    }
+}
+
+/// Retry schedule for lost compare-and-swap races.
+///
+/// The delay before retry `n` (0-based) is drawn uniformly from the upper
+/// half of `min(max, base * 2^n)`, so writers that collided once spread out
+/// instead of colliding again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub base: Duration,
+    pub max: Duration,
+    /// Total attempts, including the first.
+    pub attempts: u32,
+}
+
+impl Default for Backoff {
+    /// The SPEC §4.2 schedule: base 25 ms, max 500 ms, 5 attempts.
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(25),
+            max: Duration::from_millis(500),
+            attempts: 5,
+        }
+    }
+}
+
+impl Backoff {
+    /// Jittered delay to wait after losing attempt `retry`.
+    #[must_use]
+    pub fn delay(&self, retry: u32) -> Duration {
+        let cap = self
+            .base
+            .checked_mul(1 << retry.min(16))
+            .map_or(self.max, |d| d.min(self.max));
+        let half = cap / 2;
+        let span = u64::try_from((cap - half).as_nanos()).unwrap_or(u64::MAX);
+        half + Duration::from_nanos(jitter() % span.saturating_add(1))
+    }
+}
+
+/// Per-call random bits from std's hasher keys; ample for spreading retries.
+fn jitter() -> u64 {
+    let mut h = RandomState::new().build_hasher();
+    h.write_u64(0);
+    h.finish()
+}
+
+/// `refs/gatos/journal/<ns>/<kind>/<name>` for actor `<kind>:<name>`.
+///
+/// # Errors
+/// Returns [`JournalError::InvalidName`] unless `ns`, `kind` and `name` are
+/// single, valid ref-name components and the actor has exactly one `:`.
+pub fn journal_ref(ns: &str, actor: &str) -> Result<String, JournalError> {
+    let invalid = || JournalError::InvalidName(format!("{ns}/{actor}"));
+    let (kind, name) = actor.split_once(':').ok_or_else(invalid)?;
+    for part in [ns, kind, name] {
+        if part.is_empty() || part.contains(['/', ':']) {
+            return Err(invalid());
+        }
+    }
+    let reference = format!("{JOURNAL_PREFIX}{ns}/{kind}/{name}");
+    if git2::Reference::is_valid_name(&reference) {
+        Ok(reference)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// The actor whose journal path below `refs/gatos/journal/<ns>/` is `path`;
+/// the inverse of [`journal_ref`].
+pub(crate) fn journal_actor(path: &str) -> Option<String> {
+    let (kind, name) = path.split_once('/')?;
+    let valid = |p: &str| !p.is_empty() && !p.contains(['/', ':']);
+    (valid(kind) && valid(name)).then(|| format!("{kind}:{name}"))
+}
+
+/// Writer for the journals of one repository.
+pub struct Journal {
+    repo: Repository,
+    backoff: Backoff,
+}
+
+impl Journal {
+    #[must_use]
+    pub fn new(repo: Repository) -> Self {
+        Self {
+            repo,
+            backoff: Backoff::default(),
+        }
+    }
+
+    /// Replace the default SPEC §4.2 retry schedule.
+    #[must_use]
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    #[must_use]
+    pub fn repo(&self) -> &Repository {
+        &self.repo
+    }
+
+    /// Current head of the journal, or `None` if nothing was appended yet.
+    ///
+    /// # Errors
+    /// Returns [`JournalError::InvalidName`] for bad names or a
+    /// [`StoreError`] if git fails.
+    pub fn head(&self, ns: &str, actor: &str) -> Result<Option<Oid>, JournalError> {
+        match self.repo.refname_to_id(&journal_ref(ns, actor)?) {
+            Ok(oid) => Ok(Some(oid)),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(io(e).into()),
+        }
+    }
+
+    /// Append `event` to `refs/gatos/journal/<ns>/<actor>` and return the new
+    /// head. `event.actor` must be `actor`. The commit time is the envelope's
+    /// `ts` when present, otherwise the current time; readers order and
+    /// filter events by it.
+    ///
+    /// # Errors
+    /// - [`JournalError::NonLinear`] if the current head is a merge commit.
+    /// - [`JournalError::Store`] with [`StoreError::Conflict`] if every
+    ///   attempt lost the race to another writer.
+    /// - [`JournalError::Native`] if the current head is not a native ledger
+    ///   commit.
+    /// - [`JournalError::InvalidName`], [`JournalError::ActorMismatch`],
+    ///   [`JournalError::Timestamp`], [`JournalError::Signature`] or
+    ///   [`JournalError::Envelope`] for bad input, and other [`StoreError`]s
+    ///   if git fails.
+    pub fn append(
+        &self,
+        ns: &str,
+        actor: &str,
+        event: &EventEnvelope,
+    ) -> Result<Oid, JournalError> {
+        let reference = journal_ref(ns, actor)?;
+        if event.actor != actor {
+            return Err(JournalError::ActorMismatch {
+                journal: actor.to_string(),
+                event: event.actor.clone(),
+            });
+        }
+        let timestamp = match event.ts.as_deref() {
+            Some(ts) => rfc3339_utc_seconds(ts)
+                .and_then(|secs| u64::try_from(secs).ok())
+                .ok_or_else(|| JournalError::Timestamp(ts.to_string()))?,
+            None => SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+        let sig = event
+            .sig
+            .as_ref()
+            .map(|sig| envelope_sig(event.sig_alg, sig))
+            .transpose()?;
+        let canonical = event.canonical_bytes()?;
+        let tree = self.event_tree(&canonical)?;
+        let core_tree = native::core_tree_id(&self.repo, &tree)?;
+        let mut last = None;
+        for attempt in 0..self.backoff.attempts {
+            if attempt > 0 {
+                std::thread::sleep(self.backoff.delay(attempt - 1));
+            }
+            let head = match self.repo.find_reference(&reference) {
+                Ok(r) => Some(r.peel_to_commit().map_err(io)?),
+                Err(e) if e.code() == ErrorCode::NotFound => None,
+                Err(e) => return Err(io(e).into()),
+            };
+            if let Some(h) = head.as_ref().filter(|h| h.parent_count() > 1) {
+                return Err(JournalError::NonLinear {
+                    reference,
+                    head: h.id(),
+                });
+            }
+            let parent = head
+                .as_ref()
+                .map(|h| native::native_content_id(&self.repo, h.id()))
+                .transpose()?;
+            let core = CommitCore {
+                parent,
+                tree: core_tree,
+                message: format!("{} {}", event.event_type, event.ulid),
+                timestamp,
+            };
+            let mut trailers = EventTrailers {
+                content_id: compute_content_id(&core)
+                    .map_err(|_| StoreError::Invariant("commit serializes"))?
+                    .into(),
+                event_cid: Some(event_cid(&canonical)),
+                sig_alg: None,
+                signer: None,
+                sig: None,
+                commit_sigs: Vec::new(),
+            };
+            trailers.set_envelope_sig(sig.as_ref());
+            let new = native::write(&self.repo, &core, &trailers, &tree, head.as_ref())?;
+            let old = head.as_ref().map(git2::Commit::id);
+            if cas_ref(&self.repo, &reference, new, old, "gatos: append")? {
+                return Ok(new);
+            }
+            last = Some((old, new));
+        }
+        let (old, new) = last.ok_or(StoreError::Invariant("journal appends at least once"))?;
+        let oid = |o: Oid| o.to_string().parse::<CommitOid>().ok();
+        Err(StoreError::Conflict {
+            reference,
+            old: old.and_then(oid),
+            new: oid(new).ok_or(StoreError::Invariant("git oids are hex"))?,
+        }
+        .into())
+    }
+
+    fn event_tree(&self, canonical: &[u8]) -> Result<git2::Tree<'_>, StoreError> {
+        let blob = self.repo.blob(canonical).map_err(io)?;
+        let mut builder = self.repo.treebuilder(None).map_err(io)?;
+        builder.insert(EVENT_BLOB, blob, 0o100_644).map_err(io)?;
+        let oid = builder.write().map_err(io)?;
+        self.repo.find_tree(oid).map_err(io)
+    }
+}
+
+/// The envelope signature as a trailer value; `declared` is the envelope's
+/// `sig_alg`, which must agree with the key material.
+fn envelope_sig(declared: Option<SigAlg>, sig: &EnvelopeSig) -> Result<TrailerSig, JournalError> {
+    let alg = SigAlg::infer(&sig.signer)
+        .ok_or(JournalError::Signature(EnvelopeError::UnrecognizedKey))?;
+    if let Some(declared) = declared.filter(|d| *d != alg) {
+        return Err(JournalError::Signature(EnvelopeError::AlgorithmMismatch {
+            declared,
+            inferred: alg,
+        }));
+    }
+    Ok(TrailerSig {
+        alg,
+        signer: sig.signer.clone(),
+        sig: sig.sig.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::*;
+    use crate::dag_cbor::Value;
+    use crate::testing::TempRepo;
+    use crate::{parse_trailers, GitStore, Ulid};
+
+    fn event(n: u64) -> EventEnvelope {
+        let mut payload = BTreeMap::new();
+        payload.insert("n".to_string(), Value::from(n));
+        EventEnvelope {
+            event_type: "event.append".to_string(),
+            ulid: Ulid::from_parts(1_700_000_000_000 + n, u128::from(n)).unwrap(),
+            actor: "user:alice".to_string(),
+            caps: None,
+            payload,
+            policy_root: format!("sha256:{}", "0".repeat(64)),
+            sig_alg: None,
+            ts: None,
+            sig: None,
+        }
+    }
+
+    /// Event numbers from head to root, asserting the chain is linear.
+    fn history(repo: &Repository, head: Oid) -> Vec<u64> {
+        let mut out = Vec::new();
+        let mut next = Some(head);
+        while let Some(oid) = next {
+            let commit = repo.find_commit(oid).unwrap();
+            assert!(commit.parent_count() <= 1);
+            let blob = commit.tree().unwrap().get_name(EVENT_BLOB).unwrap().id();
+            let bytes = repo.find_blob(blob).unwrap().content().to_vec();
+            let ev = EventEnvelope::from_canonical_bytes(&bytes).unwrap();
+            let Some(Value::Integer(n)) = ev.payload.get("n") else {
+                panic!("event without n");
+            };
+            out.push(u64::try_from(*n).unwrap());
+            next = commit.parent_ids().next();
+        }
+        out
+    }
+
+    #[test]
+    fn appends_are_linear_and_carry_event_cid() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        assert_eq!(journal.head("finance", "user:alice"), Ok(None));
+        journal.append("finance", "user:alice", &event(1)).unwrap();
+        let head = journal.append("finance", "user:alice", &event(2)).unwrap();
+        assert_eq!(journal.head("finance", "user:alice"), Ok(Some(head)));
+        assert!(journal
+            .repo()
+            .find_reference("refs/gatos/journal/finance/user/alice")
+            .is_ok());
+        assert_eq!(history(journal.repo(), head), [2, 1]);
+
+        let commit = journal.repo().find_commit(head).unwrap();
+        let cid = event(2).event_cid().unwrap().to_string();
+        assert!(parse_trailers(commit.message().unwrap()).contains(&("Event-CID".to_string(), cid)));
+
+        for (ns, actor) in [
+            ("", "user:a"),
+            ("a/b", "user:c"),
+            ("ns", "alice"),
+            ("ns", "user:a:b"),
+            ("ns", "user/a"),
+            ("ns", ":a"),
+            ("ns", "user:.."),
+        ] {
+            assert!(matches!(
+                journal.append(ns, actor, &event(3)),
+                Err(JournalError::InvalidName(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn actor_paths_roundtrip() {
+        for actor in ["user:alice", "agent:indexer-7", "service:ci.bot"] {
+            let reference = journal_ref("ns", actor).unwrap();
+            let path = reference.strip_prefix("refs/gatos/journal/ns/").unwrap();
+            assert_eq!(journal_actor(path).as_deref(), Some(actor));
+        }
+        for path in ["alice", "user/a/b", "user/", "/a", "user/a:b"] {
+            assert_eq!(journal_actor(path), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_actors_and_odd_timestamps() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        assert_eq!(
+            journal.append("ns", "user:bob", &event(1)),
+            Err(JournalError::ActorMismatch {
+                journal: "user:bob".to_string(),
+                event: "user:alice".to_string(),
+            })
+        );
+        for ts in [
+            "2024-01-02T03:04:05.250Z",
+            "2024-01-02T03:04:05+01:00",
+            "2024-02-31T00:00:00Z",
+            "9999-99-99T99:99:99Z",
+            "yesterday",
+        ] {
+            let stamped = EventEnvelope {
+                ts: Some(ts.to_string()),
+                ..event(1)
+            };
+            assert_eq!(
+                journal.append("ns", "user:alice", &stamped),
+                Err(JournalError::Timestamp(ts.to_string()))
+            );
+        }
+        assert_eq!(journal.head("ns", "user:alice"), Ok(None));
+
+        let stamped = EventEnvelope {
+            ts: Some("2024-01-02T03:04:05Z".to_string()),
+            ..event(1)
+        };
+        let oid = journal.append("ns", "user:alice", &stamped).unwrap();
+        let time = journal.repo().find_commit(oid).unwrap().time();
+        assert_eq!((time.seconds(), time.offset_minutes()), (1_704_164_645, 0));
+    }
+
+    #[test]
+    fn events_are_native_commits() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        let first = journal.append("ns", "user:alice", &event(1)).unwrap();
+        let mut signed = event(2);
+        signed.sign_ed25519(&[9; 32]).unwrap();
+        let head = journal.append("ns", "user:alice", &signed).unwrap();
+
+        let store = GitStore::new(tmp.open());
+        let parent = store.read_native_commit(first).unwrap();
+        let native = store.read_native_commit(head).unwrap();
+        assert_eq!(native.commit.core.parent, Some(parent.content_id));
+        assert_eq!(native.event_cid, Some(signed.event_cid().unwrap()));
+        let sig = native.event_sig.unwrap();
+        assert_eq!(sig.alg, SigAlg::Ed25519);
+        assert_eq!(
+            Some(sig.signer),
+            signed.sig.as_ref().map(|s| s.signer.clone())
+        );
+        let message = journal.repo().find_commit(head).unwrap();
+        let message = message.message().unwrap();
+        assert!(message.contains("\nSig-Alg: ed25519\nSigner: ed25519:"));
+        assert!(message.contains("\nSig: ed25519:"));
+
+        let mismatched = EventEnvelope {
+            sig_alg: Some(SigAlg::EcdsaP256),
+            ..signed
+        };
+        assert_eq!(
+            journal.append("ns", "user:alice", &mismatched),
+            Err(JournalError::Signature(EnvelopeError::AlgorithmMismatch {
+                declared: SigAlg::EcdsaP256,
+                inferred: SigAlg::Ed25519,
+            }))
+        );
+    }
+
+    #[test]
+    fn concurrent_writers_never_lose_or_merge_events() {
+        const WRITERS: u64 = 4;
+        const EACH: u64 = 5;
+        let tmp = TempRepo::new();
+        std::thread::scope(|s| {
+            for w in 0..WRITERS {
+                let tmp = &tmp;
+                s.spawn(move || {
+                    let journal = Journal::new(tmp.open());
+                    for i in 0..EACH {
+                        journal
+                            .append("ns", "user:alice", &event(w * EACH + i))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        let journal = Journal::new(tmp.open());
+        let head = journal.head("ns", "user:alice").unwrap().unwrap();
+        let seen = history(journal.repo(), head);
+        assert_eq!(seen.len() as u64, WRITERS * EACH);
+        assert_eq!(
+            seen.iter().copied().collect::<BTreeSet<_>>(),
+            (0..WRITERS * EACH).collect()
+        );
+    }
+
+    #[test]
+    fn refuses_to_extend_a_merge() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        let a = journal.append("ns", "user:alice", &event(1)).unwrap();
+        let bob = EventEnvelope {
+            actor: "user:bob".to_string(),
+            ..event(2)
+        };
+        let b = journal.append("ns", "user:bob", &bob).unwrap();
+        let repo = journal.repo();
+        let (a, b) = (repo.find_commit(a).unwrap(), repo.find_commit(b).unwrap());
+        let sig = git2::Signature::now("x", "x@localhost").unwrap();
+        let merge = repo
+            .commit(None, &sig, &sig, "merge", &a.tree().unwrap(), &[&a, &b])
+            .unwrap();
+        repo.reference("refs/gatos/journal/ns/user/alice", merge, true, "force")
+            .unwrap();
+        assert_eq!(
+            journal.append("ns", "user:alice", &event(3)),
+            Err(JournalError::NonLinear {
+                reference: "refs/gatos/journal/ns/user/alice".to_string(),
+                head: merge,
+            })
+        );
+    }
+
+    #[test]
+    fn backoff_follows_the_spec_schedule() {
+        let backoff = Backoff::default();
+        for (retry, cap) in [
+            (0, 25),
+            (1, 50),
+            (2, 100),
+            (3, 200),
+            (4, 400),
+            (5, 500),
+            (9, 500),
+        ] {
+            let cap = Duration::from_millis(cap);
+            for _ in 0..32 {
+                let d = backoff.delay(retry);
+                assert!(d >= cap / 2 && d <= cap, "retry {retry}: {d:?}");
+            }
+        }
+    }
+}
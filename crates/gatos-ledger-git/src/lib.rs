@@ -1,61 +1,599 @@
 pub use gatos_ledger_core::*; // Re-export core API surface for facade users
-use git2::Repository;
+use git2::{Oid, Repository};
 
+#[cfg(feature = "tokio")]
+pub mod async_store;
+#[cfg(feature = "tokio")]
+pub use async_store::{AsyncObjectStore, BlockingStore, FsObjectStore};
+
+mod index;
+pub mod init;
+pub use init::{init, InitError, InitOptions, InitReport, ACTIVE_POLICY_REF};
+pub mod journal;
+use index::OidIndex;
+pub use index::INDEX_REF;
+pub use journal::{journal_ref, Backoff, Journal, JournalError};
+pub mod native;
+pub mod reader;
+pub use native::{NativeCommit, NativeCommitError};
+pub use reader::{Cursor, Entries, Follow, JournalEntry, Order, Since};
+
+pub mod scrub;
+pub use scrub::{MappingSource, ScrubIssue, ScrubReport};
+
+/// Legacy mapping refs are `refs/gatos/<alg>-map/<hex>`, one per object.
+const LEGACY_PREFIX: &str = "refs/gatos/";
+const LEGACY_INFIX: &str = "-map/";
+/// Legacy refs deleted per transaction. libgit2 keeps a lock file open for
+/// every ref locked in a transaction, so this bounds the descriptors in use.
+const MIGRATE_BATCH: usize = 256;
+
+/// [`ObjectStore`] over a git repository: objects are blobs, found through
+/// the content-address index on [`INDEX_REF`].
+///
+/// Every index update is a commit, so each [`put_object`](ObjectStore::put_object)
+/// call writes one index commit plus a new shard blob and the three trees
+/// above it (two of them up to 256 entries): on the order of 16 KB of loose
+/// objects, and one more commit of index history for `git fetch` to move.
+/// Bulk writers should use [`put_objects`](ObjectStore::put_objects), which
+/// maps a whole batch in a single index commit.
 pub struct GitStore {
     repo: Repository,
+    allow_delete: bool,
+    index: OidIndex,
 }
 
 impl GitStore {
     #[must_use]
     pub fn new(repo: Repository) -> Self {
-        Self { repo }
+        Self {
+            repo,
+            allow_delete: false,
+            index: OidIndex::default(),
+        }
+    }
+
+    /// Enable [`ObjectStore::delete_object`]. Off by default: deleting drops
+    /// the index entries so the blob becomes eligible for `git gc`.
+    #[must_use]
+    pub fn with_delete(mut self, allow: bool) -> Self {
+        self.allow_delete = allow;
+        self
+    }
+
+    fn legacy_ref(id: &Multihash) -> String {
+        format!(
+            "{LEGACY_PREFIX}{}{LEGACY_INFIX}{}",
+            id.alg().prefix(),
+            id.to_hex()
+        )
+    }
+
+    fn lookup(&self, id: &Multihash) -> Result<Option<Oid>, StoreError> {
+        if let Some(oid) = self.index.lookup(&self.repo, id)? {
+            return Ok(Some(oid));
+        }
+        // Repositories not yet migrated still map with one ref per object.
+        match self.repo.find_reference(&Self::legacy_ref(id)) {
+            Ok(r) => r
+                .target()
+                .map(Some)
+                .ok_or(StoreError::Invariant("legacy map refs are direct")),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(io(e)),
+        }
+    }
+
+    fn write_blob(&self, data: &[u8]) -> Result<Oid, StoreError> {
+        self.repo
+            .odb()
+            .and_then(|odb| odb.write(git2::ObjectType::Blob, data))
+            .map_err(io)
+    }
+
+    /// Read and verify the object mapped to `id`.
+    ///
+    /// A mapping whose git object is missing yields [`StoreError::NotFound`];
+    /// a non-blob target or bytes that do not hash to `id` yield
+    /// [`StoreError::Corruption`].
+    fn read(&self, id: &Multihash) -> Result<Option<Vec<u8>>, StoreError> {
+        let Some(git_oid) = self.lookup(id)? else {
+            return Ok(None);
+        };
+        let odb = self.repo.odb().map_err(io)?;
+        let object = match odb.read(git_oid) {
+            Ok(object) => object,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                return Err(StoreError::NotFound(*id.digest()));
+            }
+            Err(e) => return Err(io(e)),
+        };
+        let bytes = object.data();
+        if object.kind() != git2::ObjectType::Blob {
+            return Err(StoreError::Corruption {
+                expected: *id.digest(),
+                actual: id.alg().digest(bytes),
+            });
+        }
+        verify_object_address(id, bytes)?;
+        Ok(Some(bytes.to_vec()))
+    }
+
+    /// Every legacy `refs/gatos/<alg>-map/<hex>` ref with its target.
+    fn legacy_refs(&self) -> Result<Vec<(Multihash, Oid, String)>, StoreError> {
+        let refs = self
+            .repo
+            .references_glob(&format!("{LEGACY_PREFIX}*{LEGACY_INFIX}*"))
+            .map_err(io)?;
+        let mut out = Vec::new();
+        for r in refs {
+            let r = r.map_err(io)?;
+            let name = String::from_utf8_lossy(r.name_bytes()).into_owned();
+            let malformed = || {
+                StoreError::from(BackendError::new(
+                    BackendErrorKind::Other,
+                    format!("malformed map ref {name}"),
+                ))
+            };
+            let (alg, hex_id) = name
+                .strip_prefix(LEGACY_PREFIX)
+                .and_then(|rest| rest.split_once(LEGACY_INFIX))
+                .ok_or_else(malformed)?;
+            let id = format!("{alg}:{hex_id}")
+                .parse::<Multihash>()
+                .map_err(|_| malformed())?;
+            let target = r.target().ok_or_else(malformed)?;
+            out.push((id, target, name));
+        }
+        Ok(out)
+    }
+
+    /// Fold legacy one-ref-per-object mappings into the index in a single
+    /// index commit, then delete the legacy refs in batches of
+    /// [`MIGRATE_BATCH`]. Entries already in the index win. Returns the number
+    /// of legacy refs removed.
+    ///
+    /// # Errors
+    /// Returns a [`StoreError`] if a legacy ref is malformed or git fails;
+    /// the legacy refs are kept unless the index commit succeeded. A failure
+    /// while deleting leaves the remaining refs in place, already mirrored in
+    /// the index, so rerunning the migration finishes the job.
+    pub fn migrate_legacy_refs(&mut self) -> Result<usize, StoreError> {
+        let legacy = self.legacy_refs()?;
+        let mut entries = Vec::new();
+        for (id, oid, _) in &legacy {
+            if self.index.lookup(&self.repo, id)?.is_none() {
+                entries.push((*id, *oid));
+            }
+        }
+        if !entries.is_empty() {
+            self.index.insert(&self.repo, &entries)?;
+        }
+        if legacy.is_empty() {
+            return Ok(0);
+        }
+        for batch in legacy.chunks(MIGRATE_BATCH) {
+            let mut tx = self.repo.transaction().map_err(io)?;
+            for (_, _, name) in batch {
+                tx.lock_ref(name).map_err(io)?;
+                tx.remove(name).map_err(io)?;
+            }
+            tx.commit().map_err(io)?;
+        }
+        Ok(legacy.len())
+    }
+}
+
+/// Classify a libgit2 failure, keeping it as the error source.
+fn io(e: git2::Error) -> StoreError {
+    use git2::{ErrorClass, ErrorCode};
+    let os = matches!(e.class(), ErrorClass::Os | ErrorClass::Filesystem);
+    if os
+        && e.message()
+            .to_ascii_lowercase()
+            .contains("permission denied")
+    {
+        return StoreError::PermissionDenied(e.message().to_string());
+    }
+    let kind = match e.code() {
+        ErrorCode::Locked => BackendErrorKind::Locked,
+        _ if os => BackendErrorKind::Io,
+        _ => BackendErrorKind::Other,
+    };
+    BackendError::from_source(kind, e).into()
+}
+
+/// Classify a filesystem failure, keeping it as the error source.
+fn fs_error(e: std::io::Error) -> StoreError {
+    use std::io::ErrorKind;
+    let kind = match e.kind() {
+        ErrorKind::PermissionDenied => return StoreError::PermissionDenied(e.to_string()),
+        ErrorKind::Interrupted | ErrorKind::TimedOut | ErrorKind::WouldBlock => {
+            BackendErrorKind::Interrupted
+        }
+        _ => BackendErrorKind::Io,
+    };
+    BackendError::from_source(kind, e).into()
+}
+
+/// Compare-and-swap `name` from `old` (`None`: must not exist) to `new`.
+///
+/// Both cases are checked under the ref lock; plain `reference(.., false, ..)`
+/// tests existence before locking, so two creators could both succeed.
+/// Returns `false` if another writer got there first.
+fn cas_ref(
+    repo: &Repository,
+    name: &str,
+    new: Oid,
+    old: Option<Oid>,
+    message: &str,
+) -> Result<bool, StoreError> {
+    use git2::ErrorCode;
+    match repo.reference_matching(name, new, true, old.unwrap_or_else(Oid::zero), message) {
+        Ok(_) => Ok(true),
+        Err(e)
+            if matches!(
+                e.code(),
+                ErrorCode::Modified | ErrorCode::Exists | ErrorCode::Locked
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(io(e)),
     }
 }
 
 impl ObjectStore for GitStore {
     fn put_object(&mut self, id: &Hash, data: &[u8]) -> Result<(), StoreError> {
-        let calculated_hash = blake3::hash(data);
-        if calculated_hash.as_bytes() != id {
-            return Err(StoreError::Corruption);
+        verify_object_id(id, data)?;
+        let git_oid = self.write_blob(data)?;
+        self.index.insert(
+            &self.repo,
+            &[(Multihash::new(HashAlg::Blake3, *id), git_oid)],
+        )
+    }
+
+    /// Verifies the bytes against `id` before returning them.
+    fn get_object(&self, id: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
+        self.read(&Multihash::new(HashAlg::Blake3, *id))
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            enumerate: true,
+            delete: self.allow_delete,
+            multihash: true,
         }
+    }
 
-        let odb = self.repo.odb().map_err(|e| StoreError::Io(e.to_string()))?;
-        let git_oid = odb
-            .write(git2::ObjectType::Blob, data)
-            .map_err(|e| StoreError::Io(e.to_string()))?;
+    fn has_object(&self, id: &Hash) -> Result<bool, StoreError> {
+        self.lookup(&Multihash::new(HashAlg::Blake3, *id))
+            .map(|o| o.is_some())
+    }
 
-        let ref_name = format!("refs/gatos/blake3-map/{}", hex::encode(id));
-        self.repo
-            .reference(
-                &ref_name,
-                git_oid,
-                true,
-                "gatos: map blake3 hash to git oid",
-            )
-            .map_err(|e| StoreError::Io(e.to_string()))?;
-        Ok(())
+    /// Writes all blobs first, then maps every id in one index commit, so
+    /// either all ids become visible or none do.
+    fn put_objects(&mut self, objects: &[(Hash, &[u8])]) -> Result<(), StoreError> {
+        for (id, data) in objects {
+            verify_object_id(id, data)?;
+        }
+        let mut entries = Vec::with_capacity(objects.len());
+        for (id, data) in objects {
+            entries.push((Multihash::new(HashAlg::Blake3, *id), self.write_blob(data)?));
+        }
+        self.index.insert(&self.repo, &entries)
     }
 
-    fn get_object(&self, id: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
-        let ref_name = format!("refs/gatos/blake3-map/{}", hex::encode(id));
-        let reference = match self.repo.find_reference(&ref_name) {
-            Ok(r) => r,
-            Err(e) => {
-                // If reference does not exist, treat as not found; other errors as IO
-                if e.code() == git2::ErrorCode::NotFound {
-                    return Ok(None);
+    fn object_ids(&self) -> Result<ObjectIds<'_>, StoreError> {
+        let mut ids = std::collections::BTreeSet::new();
+        for (id, _) in self.index.entries(&self.repo, HashAlg::Blake3)? {
+            ids.insert(*id.digest());
+        }
+        for (id, _, _) in self.legacy_refs()? {
+            if id.alg() == HashAlg::Blake3 {
+                ids.insert(*id.digest());
+            }
+        }
+        Ok(Box::new(ids.into_iter().map(Ok)))
+    }
+
+    /// Also drops any SHA-256 aliases of the object.
+    fn delete_object(&mut self, id: &Hash) -> Result<bool, StoreError> {
+        if !self.allow_delete {
+            return Err(StoreError::Unsupported);
+        }
+        let id = Multihash::new(HashAlg::Blake3, *id);
+        let Some(target) = self.lookup(&id)? else {
+            return Ok(false);
+        };
+        let mut doomed = vec![id];
+        for (alias, oid) in self.index.entries(&self.repo, HashAlg::Sha256)? {
+            if oid == target {
+                doomed.push(alias);
+            }
+        }
+        self.index.remove(&self.repo, &doomed)?;
+        for (legacy, oid, name) in self.legacy_refs()? {
+            if legacy == id || (legacy.alg() != HashAlg::Blake3 && oid == target) {
+                match self.repo.find_reference(&name) {
+                    Ok(mut r) => r.delete().map_err(io)?,
+                    Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+                    Err(e) => return Err(io(e)),
                 }
-                return Err(StoreError::Io(e.to_string()));
             }
+        }
+        Ok(true)
+    }
+
+    /// Non-BLAKE3 addresses are indexed next to the BLAKE3 id, in the same
+    /// index commit.
+    fn put_object_by(&mut self, id: &Multihash, data: &[u8]) -> Result<Hash, StoreError> {
+        if id.alg() == HashAlg::Blake3 {
+            return self.put_object(id.digest(), data).map(|()| *id.digest());
+        }
+        verify_object_address(id, data)?;
+        let blake3_id: Hash = blake3::hash(data).into();
+        let git_oid = self.write_blob(data)?;
+        self.index.insert(
+            &self.repo,
+            &[
+                (Multihash::new(HashAlg::Blake3, blake3_id), git_oid),
+                (*id, git_oid),
+            ],
+        )?;
+        Ok(blake3_id)
+    }
+
+    fn get_object_by(&self, id: &Multihash) -> Result<Option<Vec<u8>>, StoreError> {
+        self.read(id)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A bare repository in a fresh temp directory, removed on drop.
+    pub struct TempRepo {
+        pub path: PathBuf,
+    }
+
+    impl TempRepo {
+        pub fn new() -> Self {
+            static N: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "gatos-git-test-{}-{}",
+                std::process::id(),
+                N.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            git2::Repository::init_bare(&path).expect("init temp repo");
+            Self { path }
+        }
+
+        pub fn open(&self) -> git2::Repository {
+            git2::Repository::open(&self.path).expect("open temp repo")
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::TempRepo;
+    use super::*;
+
+    fn id(data: &[u8]) -> Hash {
+        blake3::hash(data).into()
+    }
+
+    #[test]
+    fn batch_enumerate_and_delete() {
+        let tmp = TempRepo::new();
+        let mut store = GitStore::new(tmp.open());
+        let (a, b) = (&b"alpha"[..], &b"beta"[..]);
+        store.put_objects(&[(id(a), a), (id(b), b)]).unwrap();
+        assert!(store.has_object(&id(a)).unwrap());
+        assert_eq!(
+            store.get_objects(&[id(b), [0; 32]]).unwrap(),
+            [Some(b.to_vec()), None]
+        );
+
+        let mut ids: Vec<Hash> = store.object_ids().unwrap().map(Result::unwrap).collect();
+        ids.sort_unstable();
+        let mut expected = vec![id(a), id(b)];
+        expected.sort_unstable();
+        assert_eq!(ids, expected);
+
+        // A bad pair rejects the whole batch before anything is mapped.
+        let c = &b"gamma"[..];
+        assert_eq!(
+            store.put_objects(&[(id(c), c), ([0; 32], c)]),
+            Err(StoreError::corruption(&[0; 32], c))
+        );
+        assert!(!store.has_object(&id(c)).unwrap());
+
+        assert!(!store.capabilities().delete);
+        assert_eq!(store.delete_object(&id(a)), Err(StoreError::Unsupported));
+        let mut store = store.with_delete(true);
+        assert!(store.delete_object(&id(a)).unwrap());
+        assert!(!store.delete_object(&id(a)).unwrap());
+        assert_eq!(store.get_object(&id(a)).unwrap(), None);
+    }
+
+    #[test]
+    fn sha256_addresses_map_to_the_same_blob() {
+        let tmp = TempRepo::new();
+        let mut store = GitStore::new(tmp.open());
+        let data = &b"policy code"[..];
+        let sha = Multihash::of(HashAlg::Sha256, data);
+        assert_eq!(store.put_object_by(&sha, data).unwrap(), id(data));
+        assert_eq!(store.get_object_by(&sha).unwrap().as_deref(), Some(data));
+        assert_eq!(store.get_object(&id(data)).unwrap().as_deref(), Some(data));
+        let other = Multihash::of(HashAlg::Sha256, b"other");
+        assert_eq!(store.get_object_by(&other).unwrap(), None);
+
+        // Repoint the alias at a different blob: the read is rejected.
+        let repo = tmp.open();
+        let bogus = repo.blob(b"tampered").unwrap();
+        OidIndex::default().insert(&repo, &[(sha, bogus)]).unwrap();
+        let mut store = GitStore::new(tmp.open()).with_delete(true);
+        assert!(matches!(
+            store.get_object_by(&sha),
+            Err(StoreError::Corruption { expected, .. }) if expected == *sha.digest()
+        ));
+
+        // Deleting the object drops aliases that still point at its blob.
+        let alias = Multihash::of(HashAlg::Sha256, b"other");
+        store.put_object_by(&alias, b"other").unwrap();
+        assert!(store.delete_object(&id(b"other")).unwrap());
+        assert_eq!(store.get_object_by(&alias).unwrap(), None);
+    }
+
+    #[test]
+    fn commits_keep_signatures_and_their_address() {
+        let tmp = TempRepo::new();
+        let mut store = GitStore::new(tmp.open());
+        let mut commit = Commit {
+            core: CommitCore {
+                parent: None,
+                tree: [0x22; 32],
+                message: "init".to_string(),
+                timestamp: 1_725_000_000,
+            },
+            sigs: Default::default(),
         };
-        let Some(git_oid) = reference.target() else {
-            return Err(StoreError::Invariant);
+        let key: SecretKey = [7; 32];
+        commit.sign(&key).unwrap();
+        let id = put_commit(&mut store, &commit).unwrap();
+        let unsigned = Commit {
+            core: commit.core.clone(),
+            sigs: Default::default(),
         };
-        let blob = self
-            .repo
-            .find_blob(git_oid)
-            .map_err(|e| StoreError::Io(e.to_string()))?;
-        let bytes = blob.content().to_vec();
-        Ok(Some(bytes))
+        put_commit(&mut store, &unsigned).unwrap();
+        assert_eq!(get_commit(&store, &id).unwrap().unwrap().sigs.len(), 1);
+
+        // The bare core hashes to the content id but cannot take the
+        // commit's place.
+        let core = VersionedCommitCore::from(unsigned.core)
+            .core_bytes()
+            .unwrap();
+        assert_eq!(id, *blake3::hash(&core).as_bytes());
+        assert!(store.put_object(&commit_object_id(&id), &core).is_err());
+        assert_eq!(get_commit(&store, &id).unwrap(), Some(commit));
+    }
+
+    #[test]
+    fn stores_sharing_a_repo_see_each_others_signatures() {
+        let tmp = TempRepo::new();
+        let (mut a, mut b) = (GitStore::new(tmp.open()), GitStore::new(tmp.open()));
+        let unsigned = Commit {
+            core: CommitCore {
+                parent: None,
+                tree: [0x22; 32],
+                message: "shared".to_string(),
+                timestamp: 1_725_000_000,
+            },
+            sigs: Default::default(),
+        };
+        let signed = |key: SecretKey| {
+            let mut commit = unsigned.clone();
+            commit.sign(&key).unwrap();
+            commit
+        };
+        let id = put_commit(&mut a, &signed([1; 32])).unwrap();
+        assert_eq!(get_commit(&b, &id).unwrap().unwrap().sigs.len(), 1);
+
+        // A re-points the commit address; B must not keep the old copy.
+        put_commit(&mut a, &signed([2; 32])).unwrap();
+        assert_eq!(get_commit(&b, &id).unwrap().unwrap().sigs.len(), 2);
+        put_commit(&mut b, &signed([3; 32])).unwrap();
+        let signers: Vec<_> = get_commit(&a, &id)
+            .unwrap()
+            .unwrap()
+            .sigs
+            .iter()
+            .map(|s| s.signer)
+            .collect();
+        let expected: Vec<_> = [[1; 32], [2; 32], [3; 32]]
+            .iter()
+            .map(ed25519_public_key)
+            .collect();
+        assert_eq!(signers, expected);
+    }
+
+    #[test]
+    fn migrates_legacy_per_object_refs() {
+        let tmp = TempRepo::new();
+        let repo = tmp.open();
+        let (a, b) = (&b"alpha"[..], &b"beta"[..]);
+        for data in [a, b] {
+            let oid = repo.blob(data).unwrap();
+            let name = GitStore::legacy_ref(&Multihash::of(HashAlg::Blake3, data));
+            repo.reference(&name, oid, false, "legacy").unwrap();
+        }
+        let mut store = GitStore::new(tmp.open());
+        // Legacy refs are still readable before migration.
+        assert_eq!(store.get_object(&id(a)).unwrap().as_deref(), Some(a));
+        assert_eq!(store.object_ids().unwrap().count(), 2);
+
+        assert_eq!(store.migrate_legacy_refs().unwrap(), 2);
+        assert!(repo
+            .references_glob("refs/gatos/*-map/*")
+            .unwrap()
+            .next()
+            .is_none());
+        let store = GitStore::new(tmp.open());
+        assert_eq!(store.get_object(&id(b)).unwrap().as_deref(), Some(b));
+        assert_eq!(store.object_ids().unwrap().count(), 2);
+        let mut store = store;
+        assert_eq!(store.migrate_legacy_refs().unwrap(), 0);
+    }
+
+    #[test]
+    fn migrates_more_legacy_refs_than_one_batch() {
+        let tmp = TempRepo::new();
+        let repo = tmp.open();
+        let count = MIGRATE_BATCH * 2 + 7;
+        let data: Vec<Vec<u8>> = (0..count)
+            .map(|i| format!("object {i}").into_bytes())
+            .collect();
+        for d in &data {
+            let oid = repo.blob(d).unwrap();
+            let name = GitStore::legacy_ref(&Multihash::of(HashAlg::Blake3, d));
+            repo.reference(&name, oid, false, "legacy").unwrap();
+        }
+        let mut store = GitStore::new(tmp.open());
+        assert_eq!(store.migrate_legacy_refs().unwrap(), count);
+        assert!(store.legacy_refs().unwrap().is_empty());
+        assert_eq!(store.object_ids().unwrap().count(), count);
+        for d in [&data[0], &data[MIGRATE_BATCH], &data[count - 1]] {
+            assert_eq!(store.get_object(&id(d)).unwrap().as_ref(), Some(d));
+        }
+    }
+
+    #[test]
+    fn libgit2_errors_are_classified() {
+        use git2::{ErrorClass, ErrorCode};
+        let locked = io(git2::Error::new(
+            ErrorCode::Locked,
+            ErrorClass::Reference,
+            "failed to lock file",
+        ));
+        assert!(locked.is_retryable());
+        assert!(std::error::Error::source(&locked).is_some());
+        let denied = io(git2::Error::new(
+            ErrorCode::GenericError,
+            ErrorClass::Os,
+            "failed to open: Permission denied",
+        ));
+        assert!(matches!(denied, StoreError::PermissionDenied(_)));
+        assert!(!denied.is_retryable());
     }
 }
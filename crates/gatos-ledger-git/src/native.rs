@@ -0,0 +1,537 @@
+//! Ledger commits as native git commits.
+//!
+//! A [`Commit`] is written as an ordinary git commit so `git log` and other
+//! git tooling see the ledger history. Journal events (see
+//! [`Journal`](crate::Journal)) are written the same way, so every commit on a
+//! journal ref is a native ledger commit:
+//!
+//! ```text
+//! tree       CommitCore::tree materialized as a git tree (blobs 100644)
+//! parent     the git commit holding CommitCore::parent, if any
+//! committer  gatos <gatos@localhost>, time = CommitCore::timestamp (UTC)
+//! message    <CommitCore::message>
+//!
+//!            Content-Id: blake3:<content id>
+//!            Event-CID: <cid>                           (events only)
+//!            Sig-Alg: <alg>                             (signed events only)
+//!            Signer: <alg>:<signer hex>                 (signed events only)
+//!            Sig: <alg>:<sig hex>                       (signed events only)
+//!            Commit-Sig: ed25519:<signer hex> <sig hex> (one per signature)
+//! ```
+//!
+//! The trailer block is [`EventTrailers`]. The reader rebuilds the
+//! [`CommitCore`] from the git objects alone (the core tree id is recomputed
+//! from the git tree, with blobs addressed by their BLAKE3 digest), checks
+//! that it hashes to `Content-Id`, that `event.cbor` hashes to `Event-CID`,
+//! and verifies every `Commit-Sig` over the content id. A commit that git
+//! tooling rewrote in any way that changes the ledger content fails the
+//! check. The envelope signature (`Sig-Alg`, `Signer` and `Sig`, SPEC §4.1)
+//! is returned as is; it signs the event bytes and is checked by whoever
+//! verifies the event.
+
+use std::fmt;
+
+use git2::{ObjectType, Oid, Repository, Time};
+use smallvec::SmallVec;
+
+use crate::journal::EVENT_BLOB;
+use crate::{
+    compute_content_id, event_cid, get_tree, io, Blake3Digest, Cid, Commit, CommitCore, Ed25519Key,
+    EntryKind, EventTrailers, GitStore, Hash, HashAlg, Multihash, PubKey, StoreError, TrailerError,
+    TrailerSet, TrailerSig, Tree, TreeEntry, VerifyError,
+};
+
+const CONTENT_ID: &str = "Content-Id";
+const COMMIT_SIG: &str = "Commit-Sig";
+
+/// Errors produced while writing or reading a native ledger commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativeCommitError {
+    /// Storage failure, including [`StoreError::NotFound`] for tree objects
+    /// missing from the store.
+    Store(StoreError),
+    /// The trailer block is missing a field or has a malformed one.
+    Trailer(TrailerError),
+    /// The git commit cannot encode a ledger commit; names the problem.
+    Malformed(&'static str),
+    /// The git parent does not hold `CommitCore::parent`. `found` is the
+    /// content id of the given git parent, if there is one.
+    ParentMismatch {
+        expected: Option<Hash>,
+        found: Option<Hash>,
+    },
+    /// The rebuilt core hashes to `actual`, not to the `Content-Id` trailer.
+    ContentMismatch { expected: Hash, actual: Hash },
+    /// `event.cbor` does not hash to the `Event-CID` trailer; `actual` is
+    /// `None` when the tree has no `event.cbor` blob.
+    EventMismatch { expected: Cid, actual: Option<Cid> },
+    /// A signature does not verify over the content id.
+    Signature { signer: PubKey, error: VerifyError },
+}
+
+impl fmt::Display for NativeCommitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = |h: &Hash| Blake3Digest::new(*h).to_string();
+        let opt = |h: &Option<Hash>| h.as_ref().map_or_else(|| "none".to_string(), id);
+        match self {
+            Self::Store(e) => fmt::Display::fmt(e, f),
+            Self::Trailer(e) => fmt::Display::fmt(e, f),
+            Self::Malformed(what) => write!(f, "not a ledger commit: {what}"),
+            Self::ParentMismatch { expected, found } => write!(
+                f,
+                "parent mismatch: core names {}, git parent holds {}",
+                opt(expected),
+                opt(found)
+            ),
+            Self::ContentMismatch { expected, actual } => write!(
+                f,
+                "content mismatch: trailer says {}, commit hashes to {}",
+                id(expected),
+                id(actual)
+            ),
+            Self::EventMismatch { expected, actual } => match actual {
+                Some(actual) => write!(
+                    f,
+                    "event mismatch: trailer says {expected}, event.cbor is {actual}"
+                ),
+                None => write!(f, "event mismatch: {expected} named but no event.cbor"),
+            },
+            Self::Signature { signer, error } => write!(
+                f,
+                "signature by {} does not verify: {error:?}",
+                Ed25519Key::new(*signer)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NativeCommitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Store(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StoreError> for NativeCommitError {
+    fn from(e: StoreError) -> Self {
+        Self::Store(e)
+    }
+}
+
+impl From<TrailerError> for NativeCommitError {
+    fn from(e: TrailerError) -> Self {
+        Self::Trailer(e)
+    }
+}
+
+/// A verified ledger commit read back from git.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeCommit {
+    pub oid: Oid,
+    pub commit: Commit,
+    /// Canonical content id of `commit.core`.
+    pub content_id: Hash,
+    /// Verified against `event.cbor`.
+    pub event_cid: Option<Cid>,
+    /// Envelope signature from the `Sig-Alg`, `Signer` and `Sig` trailers,
+    /// unverified.
+    pub event_sig: Option<TrailerSig>,
+}
+
+impl GitStore {
+    /// Write `commit` as a native git commit and return its oid. No ref is
+    /// moved; see [`Journal`](crate::Journal) for appending to a ref.
+    ///
+    /// `parent` must be the git commit holding `commit.core.parent` (and
+    /// `None` exactly when the core has no parent). Every tree reachable from
+    /// `commit.core.tree` and every blob they name must be in this store.
+    ///
+    /// With `event_cid`, the tree must hold the event's canonical bytes as
+    /// `event.cbor`.
+    ///
+    /// # Errors
+    /// Returns [`NativeCommitError::ParentMismatch`] for a wrong `parent`,
+    /// [`NativeCommitError::EventMismatch`] if `event.cbor` is missing or
+    /// not addressed by `event_cid`, [`StoreError::NotFound`] for missing tree
+    /// objects, and other store errors if git fails.
+    pub fn write_native_commit(
+        &self,
+        commit: &Commit,
+        parent: Option<Oid>,
+        event_cid: Option<&Cid>,
+    ) -> Result<Oid, NativeCommitError> {
+        let core = &commit.core;
+        let found = parent.map(|p| self.native_content_id(p)).transpose()?;
+        if found != core.parent {
+            return Err(NativeCommitError::ParentMismatch {
+                expected: core.parent,
+                found,
+            });
+        }
+        let tree = self.materialize(&core.tree)?;
+        let tree = self.repo.find_tree(tree).map_err(io)?;
+        if let Some(cid) = event_cid {
+            check_event(&self.repo, &tree, cid)?;
+        }
+        let trailers = EventTrailers {
+            content_id: content_id(core)?.into(),
+            event_cid: event_cid.cloned(),
+            sig_alg: None,
+            signer: None,
+            sig: None,
+            commit_sigs: commit.sigs.iter().map(TrailerSig::from).collect(),
+        };
+        let parent = parent
+            .map(|p| self.repo.find_commit(p))
+            .transpose()
+            .map_err(io)?;
+        write(&self.repo, core, &trailers, &tree, parent.as_ref())
+    }
+
+    /// Rebuild and verify the ledger commit stored as git commit `oid`.
+    ///
+    /// # Errors
+    /// Returns [`NativeCommitError::ContentMismatch`] if the commit does not
+    /// hash to its `Content-Id`, [`NativeCommitError::EventMismatch`] if
+    /// `event.cbor` does not match `Event-CID`,
+    /// [`NativeCommitError::Signature`] for a bad signature,
+    /// [`NativeCommitError::Trailer`] or [`NativeCommitError::Malformed`] if
+    /// it is not a ledger commit, and store errors if git fails.
+    pub fn read_native_commit(&self, oid: Oid) -> Result<NativeCommit, NativeCommitError> {
+        read(&self.repo, oid)
+    }
+
+    /// `Content-Id` of git commit `oid`, unverified.
+    fn native_content_id(&self, oid: Oid) -> Result<Hash, NativeCommitError> {
+        native_content_id(&self.repo, oid)
+    }
+
+    /// Write the git tree for core tree `id`, reusing the stored blobs.
+    fn materialize(&self, id: &Hash) -> Result<Oid, NativeCommitError> {
+        let tree = get_tree(self, id)?.ok_or(StoreError::NotFound(*id))?;
+        let mut builder = self.repo.treebuilder(None).map_err(io)?;
+        for entry in tree.entries() {
+            let (oid, mode) = match entry.kind {
+                EntryKind::Blob => {
+                    let oid = self
+                        .lookup(&Multihash::new(HashAlg::Blake3, entry.id))?
+                        .ok_or(StoreError::NotFound(entry.id))?;
+                    (oid, 0o100_644)
+                }
+                EntryKind::Tree => (self.materialize(&entry.id)?, 0o040_000),
+            };
+            builder.insert(&entry.name, oid, mode).map_err(io)?;
+        }
+        Ok(builder.write().map_err(io)?)
+    }
+}
+
+fn content_id(core: &CommitCore) -> Result<Hash, NativeCommitError> {
+    Ok(compute_content_id(core).map_err(|_| StoreError::Invariant("commit serializes"))?)
+}
+
+/// Write `core` with `trailers` as a git commit over the already written
+/// git `tree`. The caller guarantees `tree` and `parent` match the core.
+pub(crate) fn write(
+    repo: &Repository,
+    core: &CommitCore,
+    trailers: &EventTrailers,
+    tree: &git2::Tree<'_>,
+    parent: Option<&git2::Commit<'_>>,
+) -> Result<Oid, NativeCommitError> {
+    let message = format!("{}\n\n{}", core.message, trailers.emit());
+    let seconds = i64::try_from(core.timestamp)
+        .map_err(|_| NativeCommitError::Malformed("timestamp beyond git range"))?;
+    let who =
+        git2::Signature::new("gatos", "gatos@localhost", &Time::new(seconds, 0)).map_err(io)?;
+    let parents: Vec<&git2::Commit<'_>> = parent.into_iter().collect();
+    Ok(repo
+        .commit(None, &who, &who, &message, tree, &parents)
+        .map_err(io)?)
+}
+
+/// Rebuild and verify the ledger commit stored as git commit `oid`; see
+/// [`GitStore::read_native_commit`].
+pub(crate) fn read(repo: &Repository, oid: Oid) -> Result<NativeCommit, NativeCommitError> {
+    let git_commit = repo.find_commit(oid).map_err(io)?;
+    let (message, trailers) = split_message(&git_commit)?;
+    let event_sig = trailers.envelope_sig()?;
+    let parent = match git_commit.parent_count() {
+        0 => None,
+        1 => Some(native_content_id(
+            repo,
+            git_commit.parent_id(0).map_err(io)?,
+        )?),
+        _ => return Err(NativeCommitError::Malformed("merge commit")),
+    };
+    let timestamp = u64::try_from(git_commit.committer().when().seconds())
+        .map_err(|_| NativeCommitError::Malformed("timestamp before 1970"))?;
+    let tree = git_commit.tree().map_err(io)?;
+    let core = CommitCore {
+        parent,
+        tree: core_tree_id(repo, &tree)?,
+        message: message.to_string(),
+        timestamp,
+    };
+    let content_id = content_id(&core)?;
+    if content_id != trailers.content_id.0 {
+        return Err(NativeCommitError::ContentMismatch {
+            expected: trailers.content_id.0,
+            actual: content_id,
+        });
+    }
+    if let Some(cid) = &trailers.event_cid {
+        check_event(repo, &tree, cid)?;
+    }
+    let mut commit = Commit {
+        core,
+        sigs: SmallVec::new(),
+    };
+    for sig in &trailers.commit_sigs {
+        let sig = sig
+            .to_commit_signature()
+            .ok_or(TrailerError::Invalid(COMMIT_SIG))?;
+        sig.verify(&content_id)
+            .map_err(|error| NativeCommitError::Signature {
+                signer: sig.signer,
+                error,
+            })?;
+        commit.sigs.push(sig);
+    }
+    Ok(NativeCommit {
+        oid,
+        commit,
+        content_id,
+        event_cid: trailers.event_cid,
+        event_sig,
+    })
+}
+
+/// `Content-Id` of git commit `oid`, unverified.
+pub(crate) fn native_content_id(repo: &Repository, oid: Oid) -> Result<Hash, NativeCommitError> {
+    let commit = repo.find_commit(oid).map_err(io)?;
+    Ok(split_message(&commit)?.1.content_id.0)
+}
+
+/// Check that `tree` holds an `event.cbor` blob addressed by `cid`.
+fn check_event(
+    repo: &Repository,
+    tree: &git2::Tree<'_>,
+    cid: &Cid,
+) -> Result<(), NativeCommitError> {
+    let actual = match tree.get_name(EVENT_BLOB) {
+        Some(entry) if entry.kind() == Some(ObjectType::Blob) => {
+            let blob = repo.find_blob(entry.id()).map_err(io)?;
+            Some(event_cid(blob.content()))
+        }
+        _ => None,
+    };
+    if actual.as_ref() == Some(cid) {
+        Ok(())
+    } else {
+        Err(NativeCommitError::EventMismatch {
+            expected: cid.clone(),
+            actual,
+        })
+    }
+}
+
+/// Core tree id of a git tree, recomputed from its contents.
+pub(crate) fn core_tree_id(
+    repo: &Repository,
+    tree: &git2::Tree<'_>,
+) -> Result<Hash, NativeCommitError> {
+    let mut entries = Vec::with_capacity(tree.len());
+    for entry in tree {
+        let name = entry
+            .name()
+            .ok_or(NativeCommitError::Malformed("non-UTF-8 path"))?
+            .to_string();
+        let (kind, id) = match entry.kind() {
+            Some(ObjectType::Blob) => {
+                let blob = repo.find_blob(entry.id()).map_err(io)?;
+                (EntryKind::Blob, blake3::hash(blob.content()).into())
+            }
+            Some(ObjectType::Tree) => {
+                let sub = repo.find_tree(entry.id()).map_err(io)?;
+                (EntryKind::Tree, core_tree_id(repo, &sub)?)
+            }
+            _ => return Err(NativeCommitError::Malformed("submodule in tree")),
+        };
+        entries.push(TreeEntry { name, kind, id });
+    }
+    let tree = Tree::from_entries(entries)
+        .map_err(|_| NativeCommitError::Malformed("invalid tree entry name"))?;
+    Ok(tree
+        .id()
+        .map_err(|_| StoreError::Invariant("tree serializes"))?)
+}
+
+/// Split a commit message into the core message and its [`EventTrailers`].
+fn split_message<'c>(
+    commit: &'c git2::Commit<'_>,
+) -> Result<(&'c str, EventTrailers), NativeCommitError> {
+    let raw = std::str::from_utf8(commit.message_raw_bytes())
+        .map_err(|_| NativeCommitError::Malformed("non-UTF-8 message"))?;
+    let message = raw
+        .rsplit_once("\n\n")
+        .map(|(message, _)| message)
+        .ok_or(TrailerError::Missing(CONTENT_ID))?;
+    Ok((message, EventTrailers::parse(raw)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempRepo;
+    use crate::trailers::TrailerValue;
+    use crate::{build_tree, ObjectStore};
+
+    fn put(store: &mut GitStore, data: &[u8]) -> Hash {
+        let id = blake3::hash(data).into();
+        store.put_object(&id, data).unwrap();
+        id
+    }
+
+    /// Commit `n`; with `event`, the tree also holds it as `event.cbor`.
+    fn commit(store: &mut GitStore, parent: Option<Hash>, n: u8, event: Option<&[u8]>) -> Commit {
+        let a = put(store, &[n]);
+        let b = put(store, b"shared");
+        let mut paths = vec![("a", a), ("dir/b", b)];
+        if let Some(event) = event {
+            paths.push((EVENT_BLOB, put(store, event)));
+        }
+        let tree = build_tree(store, paths).unwrap();
+        let mut commit = Commit {
+            core: CommitCore {
+                parent,
+                tree,
+                message: format!("event {n}\n\nbody line\n"),
+                timestamp: 1_700_000_000 + u64::from(n),
+            },
+            sigs: SmallVec::new(),
+        };
+        commit.sign(&[n; 32]).unwrap();
+        commit
+    }
+
+    #[test]
+    fn roundtrip_through_git() {
+        let tmp = TempRepo::new();
+        let mut store = GitStore::new(tmp.open());
+        let genesis = commit(&mut store, None, 1, None);
+        let g = store.write_native_commit(&genesis, None, None).unwrap();
+        let genesis_id = compute_content_id(&genesis.core).unwrap();
+        let child = commit(&mut store, Some(genesis_id), 2, Some(b"event"));
+        let cid = event_cid(b"event");
+        let c = store
+            .write_native_commit(&child, Some(g), Some(&cid))
+            .unwrap();
+
+        let read = store.read_native_commit(c).unwrap();
+        assert_eq!(read.commit, child);
+        assert_eq!(read.event_cid, Some(cid.clone()));
+        assert_eq!(store.read_native_commit(g).unwrap().commit, genesis);
+
+        // Plain git sees an ordinary history.
+        let repo = tmp.open();
+        let git_commit = repo.find_commit(c).unwrap();
+        assert_eq!(git_commit.parent_ids().collect::<Vec<_>>(), [g]);
+        assert_eq!(git_commit.summary(), Some("event 2"));
+        assert_eq!(git_commit.time().seconds(), 1_700_000_002);
+        let tree = git_commit.tree().unwrap();
+        let blob = tree.get_path("dir/b".as_ref()).unwrap().to_object(&repo);
+        assert_eq!(blob.unwrap().as_blob().unwrap().content(), b"shared");
+
+        assert_eq!(
+            store.write_native_commit(&child, None, None),
+            Err(NativeCommitError::ParentMismatch {
+                expected: Some(genesis_id),
+                found: None,
+            })
+        );
+        let other = event_cid(b"other");
+        assert_eq!(
+            store.write_native_commit(&child, Some(g), Some(&other)),
+            Err(NativeCommitError::EventMismatch {
+                expected: other,
+                actual: Some(cid),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_rewritten_commits() {
+        let tmp = TempRepo::new();
+        let mut store = GitStore::new(tmp.open());
+        let genesis = commit(&mut store, None, 1, Some(b"event"));
+        let oid = store
+            .write_native_commit(&genesis, None, Some(&event_cid(b"event")))
+            .unwrap();
+        let repo = tmp.open();
+        let original = repo.find_commit(oid).unwrap();
+        let rewrite = |message: &str| {
+            let who = original.committer();
+            repo.commit(None, &who, &who, message, &original.tree().unwrap(), &[])
+                .unwrap()
+        };
+        let raw = original.message().unwrap();
+
+        let reworded = rewrite(&raw.replacen("event 1", "event 9", 1));
+        assert!(matches!(
+            store.read_native_commit(reworded),
+            Err(NativeCommitError::ContentMismatch { .. })
+        ));
+
+        let mut forged = genesis.clone();
+        forged.sigs[0].sig[0] ^= 1;
+        let bad_sig = format!(
+            "{COMMIT_SIG}: {}",
+            TrailerSig::from(&forged.sigs[0]).emit_value()
+        );
+        let line = raw.lines().find(|l| l.starts_with(COMMIT_SIG)).unwrap();
+        let resigned = rewrite(&raw.replace(line, &bad_sig));
+        assert!(matches!(
+            store.read_native_commit(resigned),
+            Err(NativeCommitError::Signature { .. })
+        ));
+
+        // Swapping the event under an unchanged Event-CID breaks the content
+        // id; fixing that up still leaves the Event-CID naming other bytes.
+        let forged_event = commit(&mut store, None, 1, Some(b"forged"));
+        let forged_tree = store.materialize(&forged_event.core.tree).unwrap();
+        let forged_id = compute_content_id(&forged_event.core).unwrap();
+        let swapped = raw.replace(
+            &Blake3Digest::new(compute_content_id(&genesis.core).unwrap()).to_string(),
+            &Blake3Digest::new(forged_id).to_string(),
+        );
+        let who = original.committer();
+        let swapped = repo
+            .commit(
+                None,
+                &who,
+                &who,
+                &swapped,
+                &repo.find_tree(forged_tree).unwrap(),
+                &[],
+            )
+            .unwrap();
+        assert_eq!(
+            store.read_native_commit(swapped),
+            Err(NativeCommitError::EventMismatch {
+                expected: event_cid(b"event"),
+                actual: Some(event_cid(b"forged")),
+            })
+        );
+
+        let stripped = rewrite("event 1\n\nbody line\n");
+        assert_eq!(
+            store.read_native_commit(stripped),
+            Err(NativeCommitError::Trailer(TrailerError::Missing(
+                CONTENT_ID
+            )))
+        );
+    }
+}
@@ -0,0 +1,603 @@
+//! Reading events back from journals.
+//!
+//! [`Journal::entries`] reads one or more journals as a single sequence.
+//! Several journals are merged into one deterministic total order: oldest
+//! first, by commit time, then by ref name, never reordering events within
+//! one journal. [`Order::NewestFirst`] is exactly the reverse of that
+//! sequence, and [`Since`] trims its start.
+//!
+//! Commit times come from the writers' clocks, so an event can be appended
+//! after others that it sorts before. Resuming therefore takes a [`Cursor`],
+//! the last event read from each journal, rather than a position in the
+//! merged sequence: each journal is walked back only to its cursor, and
+//! everything after it is read no matter how it is stamped.
+//!
+//! [`Journal::follow`] yields the sequence as of the call, then, on each
+//! poll, the events appended since, merged in the same order among
+//! themselves. Events from different polls are never reordered, so a
+//! follower sees the order of arrival across polls; resume it with
+//! [`Follow::cursor`].
+//!
+//! Each commit is read as a native ledger commit, which checks its
+//! `Content-Id` and that `event.cbor` matches its `Event-CID`; the envelope
+//! signature trailers are re-attached as [`EventEnvelope::sig`] but not
+//! verified here.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use git2::{ErrorCode, Oid};
+
+use crate::journal::{journal_actor, journal_ref, EVENT_BLOB};
+use crate::{io, native, EnvelopeSig, EventEnvelope, Journal, JournalError};
+
+/// Direction of [`Journal::entries`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Order {
+    #[default]
+    OldestFirst,
+    NewestFirst,
+}
+
+/// The last event read from each journal, keyed by full journal ref. A
+/// consumer records `entry.oid` under `entry.reference` after processing an
+/// entry.
+pub type Cursor = BTreeMap<String, Oid>;
+
+/// Where a read starts, in oldest-first merged order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Since {
+    /// Every event.
+    #[default]
+    Start,
+    /// Events appended after the cursor. Journals missing from the cursor are
+    /// read from the start; cursor entries for other journals are ignored.
+    After(Cursor),
+    /// Events with a commit time at or after these Unix seconds.
+    Time(i64),
+}
+
+/// One event read from a journal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    /// Full journal ref, `refs/gatos/journal/<ns>/<actor>`.
+    pub reference: String,
+    pub oid: Oid,
+    /// Commit time, Unix seconds.
+    pub time: i64,
+    pub event: EventEnvelope,
+}
+
+/// Position of an event in the merged sequence, before it is loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Slot {
+    reference: String,
+    oid: Oid,
+    time: i64,
+}
+
+/// Iterator returned by [`Journal::entries`].
+pub struct Entries<'j> {
+    journal: &'j Journal,
+    slots: std::vec::IntoIter<Slot>,
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Result<JournalEntry, JournalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.slots.next().map(|slot| self.journal.load(slot))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+
+/// Journal refs with the head last read from each.
+type Heads = Vec<(String, Option<Oid>)>;
+
+/// Blocking iterator returned by [`Journal::follow`]. It never ends on its
+/// own; it yields an error if a followed journal is rewritten.
+pub struct Follow<'j> {
+    journal: &'j Journal,
+    heads: Heads,
+    pending: VecDeque<Slot>,
+    cursor: Cursor,
+    interval: Duration,
+}
+
+impl Follow<'_> {
+    /// The last event yielded from each journal, including the cursor the
+    /// follow started from; pass it to [`Since::After`] to resume.
+    #[must_use]
+    pub fn cursor(&self) -> &Cursor {
+        &self.cursor
+    }
+
+    /// Pick up events appended since the last call, without blocking.
+    /// Returns how many are now waiting to be yielded.
+    ///
+    /// # Errors
+    /// Returns [`JournalError::Rewritten`] if a followed ref no longer
+    /// descends from the head last read, or a store error if git fails.
+    /// A failed poll takes nothing from any journal, so the next one picks
+    /// up the same events.
+    pub fn poll(&mut self) -> Result<usize, JournalError> {
+        let mut moved = Vec::new();
+        let mut chains = Vec::new();
+        for (i, (reference, seen)) in self.heads.iter().enumerate() {
+            let head = self.journal.ref_head(reference)?;
+            if head == *seen {
+                continue;
+            }
+            let chain = self.journal.chain(reference, head, *seen)?;
+            if let Some(last_seen) = seen.filter(|_| !chain.stopped) {
+                return Err(JournalError::Rewritten {
+                    reference: reference.clone(),
+                    last_seen,
+                });
+            }
+            moved.push((i, head));
+            chains.push(chain.slots);
+        }
+        for (i, head) in moved {
+            self.heads[i].1 = head;
+        }
+        self.pending.extend(merge(chains));
+        Ok(self.pending.len())
+    }
+}
+
+impl Iterator for Follow<'_> {
+    type Item = Result<JournalEntry, JournalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(slot) = self.pending.pop_front() {
+                self.cursor.insert(slot.reference.clone(), slot.oid);
+                return Some(self.journal.load(slot));
+            }
+            match self.poll() {
+                Ok(0) => std::thread::sleep(self.interval),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// A journal's history from its head back to a stop commit.
+struct Chain {
+    /// Oldest first.
+    slots: Vec<Slot>,
+    /// The walk ended on the requested stop commit.
+    stopped: bool,
+}
+
+impl Journal {
+    /// Actors with a journal in namespace `ns`, sorted, in their
+    /// `<kind>:<name>` form.
+    ///
+    /// # Errors
+    /// Returns [`JournalError::InvalidName`] for a bad namespace or a store
+    /// error if git fails.
+    pub fn actors(&self, ns: &str) -> Result<Vec<String>, JournalError> {
+        let probe = journal_ref(ns, "_:_")?;
+        let prefix = &probe[..probe.len() - "_/_".len()];
+        let mut actors = Vec::new();
+        for name in self
+            .repo()
+            .references_glob(&format!("{prefix}*"))
+            .map_err(io)?
+            .names()
+        {
+            let name = name.map_err(io)?;
+            if let Some(actor) = name.strip_prefix(prefix).and_then(journal_actor) {
+                actors.push(actor);
+            }
+        }
+        actors.sort();
+        Ok(actors)
+    }
+
+    /// Events of the `(ns, actor)` journals as one sequence (see the module
+    /// docs for the merge order).
+    ///
+    /// # Errors
+    /// Returns [`JournalError::NotInJournal`] if the cursor in `since` names a
+    /// commit outside its journal's history, [`JournalError::InvalidName`]
+    /// for bad names, and store errors if git fails. Decoding errors surface
+    /// per item.
+    pub fn entries(
+        &self,
+        journals: &[(&str, &str)],
+        since: Since,
+        order: Order,
+    ) -> Result<Entries<'_>, JournalError> {
+        let (slots, _) = self.select(journals, &since)?;
+        let mut slots: Vec<Slot> = slots.into();
+        if order == Order::NewestFirst {
+            slots.reverse();
+        }
+        Ok(Entries {
+            journal: self,
+            slots: slots.into_iter(),
+        })
+    }
+
+    /// Like [`Journal::entries`] in oldest-first order, then keeps yielding
+    /// events as the refs advance, checking every `interval`. Journals that do
+    /// not exist yet are picked up once created.
+    ///
+    /// # Errors
+    /// See [`Journal::entries`].
+    pub fn follow(
+        &self,
+        journals: &[(&str, &str)],
+        since: Since,
+        interval: Duration,
+    ) -> Result<Follow<'_>, JournalError> {
+        let (pending, heads) = self.select(journals, &since)?;
+        let cursor = match since {
+            Since::After(cursor) => cursor,
+            _ => Cursor::new(),
+        };
+        Ok(Follow {
+            journal: self,
+            heads,
+            pending,
+            cursor,
+            interval,
+        })
+    }
+
+    /// The merged oldest-first sequence trimmed by `since`, plus the head
+    /// each journal was read at. With a cursor, each journal is walked only
+    /// back to its cursor.
+    fn select(
+        &self,
+        journals: &[(&str, &str)],
+        since: &Since,
+    ) -> Result<(VecDeque<Slot>, Heads), JournalError> {
+        let mut heads = Vec::with_capacity(journals.len());
+        let mut chains = Vec::with_capacity(journals.len());
+        for (ns, actor) in journals {
+            let reference = journal_ref(ns, actor)?;
+            let head = self.ref_head(&reference)?;
+            let stop = match since {
+                Since::After(cursor) => cursor.get(&reference).copied(),
+                _ => None,
+            };
+            let chain = self.chain(&reference, head, stop)?;
+            if let Some(oid) = stop.filter(|_| !chain.stopped) {
+                return Err(JournalError::NotInJournal(oid));
+            }
+            chains.push(chain.slots);
+            heads.push((reference, head));
+        }
+        let mut slots = merge(chains);
+        if let Since::Time(t) = *since {
+            slots.retain(|s| s.time >= t);
+        }
+        Ok((slots, heads))
+    }
+
+    fn ref_head(&self, reference: &str) -> Result<Option<Oid>, JournalError> {
+        match self.repo().refname_to_id(reference) {
+            Ok(oid) => Ok(Some(oid)),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(io(e).into()),
+        }
+    }
+
+    /// Walk first parents from `head` back to `stop` (exclusive) or the root.
+    fn chain(
+        &self,
+        reference: &str,
+        head: Option<Oid>,
+        stop: Option<Oid>,
+    ) -> Result<Chain, JournalError> {
+        let mut slots = Vec::new();
+        let mut next = head;
+        let mut stopped = false;
+        while let Some(oid) = next {
+            if Some(oid) == stop {
+                stopped = true;
+                break;
+            }
+            let commit = self.repo().find_commit(oid).map_err(io)?;
+            if commit.parent_count() > 1 {
+                return Err(JournalError::NonLinear {
+                    reference: reference.to_string(),
+                    head: oid,
+                });
+            }
+            slots.push(Slot {
+                reference: reference.to_string(),
+                oid,
+                time: commit.time().seconds(),
+            });
+            next = commit.parent_ids().next();
+        }
+        slots.reverse();
+        Ok(Chain { slots, stopped })
+    }
+
+    fn load(&self, slot: Slot) -> Result<JournalEntry, JournalError> {
+        let malformed = |what| JournalError::Malformed {
+            oid: slot.oid,
+            what,
+        };
+        let native = native::read(self.repo(), slot.oid)?;
+        if native.event_cid.is_none() {
+            return Err(malformed("no Event-CID"));
+        }
+        let commit = self.repo().find_commit(slot.oid).map_err(io)?;
+        let tree = commit.tree().map_err(io)?;
+        let entry = tree
+            .get_name(EVENT_BLOB)
+            .ok_or_else(|| malformed("no event.cbor"))?;
+        let blob = self.repo().find_blob(entry.id()).map_err(io)?;
+        let mut event = EventEnvelope::from_canonical_bytes(blob.content())
+            .map_err(|_| malformed("undecodable envelope"))?;
+        event.sig = native.event_sig.map(|s| EnvelopeSig {
+            signer: s.signer,
+            sig: s.sig,
+        });
+        Ok(JournalEntry {
+            reference: slot.reference,
+            oid: slot.oid,
+            time: slot.time,
+            event,
+        })
+    }
+}
+
+/// Merge oldest-first chains by `(time, ref)`, keeping each chain's order.
+fn merge(chains: Vec<Vec<Slot>>) -> VecDeque<Slot> {
+    let mut queues: Vec<VecDeque<Slot>> = chains.into_iter().map(VecDeque::from).collect();
+    let mut out = VecDeque::with_capacity(queues.iter().map(VecDeque::len).sum());
+    while let Some(q) = queues
+        .iter_mut()
+        .filter(|q| !q.is_empty())
+        .min_by(|a, b| (a[0].time, &a[0].reference).cmp(&(b[0].time, &b[0].reference)))
+    {
+        out.extend(q.pop_front());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::dag_cbor::Value;
+    use crate::testing::TempRepo;
+    use crate::Ulid;
+
+    /// Event `n` by `actor` stamped at second `t` past 2023-11-14T22:13:20Z.
+    fn event(actor: &str, n: u64, t: u64) -> EventEnvelope {
+        let mut payload = BTreeMap::new();
+        payload.insert("n".to_string(), Value::from(n));
+        EventEnvelope {
+            event_type: "event.append".to_string(),
+            ulid: Ulid::from_parts(1_700_000_000_000 + n, u128::from(n)).unwrap(),
+            actor: actor.to_string(),
+            caps: None,
+            payload,
+            policy_root: format!("sha256:{}", "0".repeat(64)),
+            sig_alg: None,
+            ts: Some(format!("2023-11-14T22:13:{:02}Z", 20 + t)),
+            sig: None,
+        }
+    }
+
+    fn numbers(entries: impl Iterator<Item = Result<JournalEntry, JournalError>>) -> Vec<u64> {
+        entries
+            .map(|e| match e.unwrap().event.payload.get("n") {
+                Some(Value::Integer(n)) => u64::try_from(*n).unwrap(),
+                _ => panic!("event without n"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_journal_ranges() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        let mut oids = Vec::new();
+        for n in 0..4 {
+            oids.push(
+                journal
+                    .append("ns", "user:alice", &event("user:alice", n, n))
+                    .unwrap(),
+            );
+        }
+        let read = |since, order| {
+            numbers(
+                journal
+                    .entries(&[("ns", "user:alice")], since, order)
+                    .unwrap(),
+            )
+        };
+        assert_eq!(read(Since::Start, Order::OldestFirst), [0, 1, 2, 3]);
+        assert_eq!(read(Since::Start, Order::NewestFirst), [3, 2, 1, 0]);
+        let after = |oid| {
+            Since::After(Cursor::from([(
+                "refs/gatos/journal/ns/user/alice".to_string(),
+                oid,
+            )]))
+        };
+        assert_eq!(read(after(oids[1]), Order::OldestFirst), [2, 3]);
+        assert_eq!(read(after(oids[1]), Order::NewestFirst), [3, 2]);
+        assert!(read(after(oids[3]), Order::OldestFirst).is_empty());
+        assert_eq!(read(Since::Time(1_700_000_002), Order::OldestFirst), [2, 3]);
+
+        let first = journal
+            .entries(&[("ns", "user:alice")], Since::Start, Order::OldestFirst)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.time, 1_700_000_000);
+        assert_eq!(first.reference, "refs/gatos/journal/ns/user/alice");
+        assert_eq!(first.event, event("user:alice", 0, 0));
+
+        let stranger = Oid::from_bytes(&[7; 20]).unwrap();
+        assert!(matches!(
+            journal.entries(&[("ns", "user:alice")], after(stranger), Order::OldestFirst),
+            Err(JournalError::NotInJournal(_))
+        ));
+        assert!(numbers(
+            journal
+                .entries(&[("ns", "user:nobody")], Since::Start, Order::OldestFirst)
+                .unwrap()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn merges_actors_deterministically() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        // bob's clock runs behind: his events keep their own order even
+        // though event 11 is stamped earlier than event 10.
+        journal
+            .append("ns", "user:bob", &event("user:bob", 10, 3))
+            .unwrap();
+        journal
+            .append("ns", "user:bob", &event("user:bob", 11, 1))
+            .unwrap();
+        journal
+            .append("ns", "user:alice", &event("user:alice", 0, 0))
+            .unwrap();
+        journal
+            .append("ns", "user:alice", &event("user:alice", 1, 3))
+            .unwrap();
+        journal
+            .append("other", "user:carol", &event("user:carol", 20, 0))
+            .unwrap();
+
+        assert_eq!(journal.actors("ns").unwrap(), ["user:alice", "user:bob"]);
+        let both = [("ns", "user:bob"), ("ns", "user:alice")];
+        let merged = numbers(
+            journal
+                .entries(&both, Since::Start, Order::OldestFirst)
+                .unwrap(),
+        );
+        // Time ties break on ref name: alice before bob.
+        assert_eq!(merged, [0, 1, 10, 11]);
+        let mut reversed = numbers(
+            journal
+                .entries(&both, Since::Start, Order::NewestFirst)
+                .unwrap(),
+        );
+        reversed.reverse();
+        assert_eq!(reversed, merged);
+    }
+
+    #[test]
+    fn cursors_resume_late_events_stamped_earlier() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        let both = [("ns", "user:alice"), ("ns", "user:bob")];
+        journal
+            .append("ns", "user:alice", &event("user:alice", 0, 30))
+            .unwrap();
+        let mut cursor = Cursor::new();
+        for entry in journal
+            .entries(&both, Since::Start, Order::OldestFirst)
+            .unwrap()
+        {
+            let entry = entry.unwrap();
+            cursor.insert(entry.reference, entry.oid);
+        }
+
+        // bob appends afterwards with an earlier stamp; resuming still
+        // delivers it.
+        journal
+            .append("ns", "user:bob", &event("user:bob", 10, 10))
+            .unwrap();
+        let resumed = |cursor: &Cursor| {
+            numbers(
+                journal
+                    .entries(&both, Since::After(cursor.clone()), Order::OldestFirst)
+                    .unwrap(),
+            )
+        };
+        assert_eq!(resumed(&cursor), [10]);
+
+        let mut follow = journal
+            .follow(&both, Since::After(cursor), Duration::from_millis(5))
+            .unwrap();
+        assert_eq!(numbers(follow.by_ref().take(1)), [10]);
+        assert!(resumed(follow.cursor()).is_empty());
+    }
+
+    #[test]
+    fn follow_yields_new_events() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        let start = journal
+            .append("ns", "user:alice", &event("user:alice", 0, 0))
+            .unwrap();
+        let watched = [("ns", "user:alice"), ("ns", "user:bob")];
+        let cursor = Cursor::from([("refs/gatos/journal/ns/user/alice".to_string(), start)]);
+        let mut follow = journal
+            .follow(&watched, Since::After(cursor), Duration::from_millis(5))
+            .unwrap();
+        assert_eq!(follow.poll().unwrap(), 0);
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let writer = Journal::new(tmp.open());
+                writer
+                    .append("ns", "user:bob", &event("user:bob", 10, 1))
+                    .unwrap();
+                writer
+                    .append("ns", "user:alice", &event("user:alice", 1, 2))
+                    .unwrap();
+            });
+            assert_eq!(numbers(follow.by_ref().take(2)), [10, 1]);
+        });
+
+        // Rewriting a followed journal is reported, not silently skipped.
+        let repo = tmp.open();
+        repo.reference("refs/gatos/journal/ns/user/alice", start, true, "rewind")
+            .unwrap();
+        assert!(matches!(follow.poll(), Err(JournalError::Rewritten { .. })));
+    }
+
+    #[test]
+    fn failed_poll_keeps_earlier_journals() {
+        let tmp = TempRepo::new();
+        let journal = Journal::new(tmp.open());
+        let bob_start = journal
+            .append("ns", "user:bob", &event("user:bob", 10, 0))
+            .unwrap();
+        let bob_head = journal
+            .append("ns", "user:bob", &event("user:bob", 11, 1))
+            .unwrap();
+        let watched = [("ns", "user:alice"), ("ns", "user:bob")];
+        let mut follow = journal
+            .follow(&watched, Since::Start, Duration::from_millis(5))
+            .unwrap();
+        assert_eq!(numbers(follow.by_ref().take(2)), [10, 11]);
+
+        // Alice, polled first, moves on while bob's journal is rewound.
+        journal
+            .append("ns", "user:alice", &event("user:alice", 1, 2))
+            .unwrap();
+        let repo = tmp.open();
+        let bob = "refs/gatos/journal/ns/user/bob";
+        repo.reference(bob, bob_start, true, "rewind").unwrap();
+        assert!(matches!(follow.poll(), Err(JournalError::Rewritten { .. })));
+
+        // Once bob is restored, alice's event is still there to be read.
+        repo.reference(bob, bob_head, true, "restore").unwrap();
+        assert_eq!(follow.poll().unwrap(), 1);
+        assert_eq!(numbers(follow.by_ref().take(1)), [1]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Whole-repository integrity scrub.
+//!
+//! [`GitStore::verify_all`] re-reads every mapped object, from the index and
+//! from any legacy per-object refs, and reports each mapping that no longer
+//! holds. Like [`verify_chain`](crate::verify_chain), findings are collected
+//! rather than returned as errors so one pass shows every problem.
+
+use git2::{ErrorCode, ObjectType, Oid};
+
+use crate::{io, GitStore, Hash, HashAlg, Multihash, StoreError};
+
+/// Where a mapping was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MappingSource {
+    /// The sharded index on [`INDEX_REF`](crate::INDEX_REF).
+    Index,
+    /// A legacy `refs/gatos/<alg>-map/<hex>` ref, by name.
+    LegacyRef(String),
+}
+
+/// A single integrity finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScrubIssue {
+    /// The mapped git object does not exist.
+    Dangling {
+        id: Multihash,
+        oid: Oid,
+        source: MappingSource,
+    },
+    /// The mapped git object is not a blob.
+    WrongType {
+        id: Multihash,
+        oid: Oid,
+        kind: ObjectType,
+        source: MappingSource,
+    },
+    /// The blob does not hash to `id`; `actual` is its digest under the same
+    /// algorithm.
+    Mismatch {
+        id: Multihash,
+        oid: Oid,
+        actual: Hash,
+        source: MappingSource,
+    },
+}
+
+/// Outcome of [`GitStore::verify_all`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrubReport {
+    /// Mappings examined.
+    pub checked: usize,
+    /// Findings in scan order; empty for an intact repository.
+    pub issues: Vec<ScrubIssue>,
+}
+
+impl ScrubReport {
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl GitStore {
+    /// Check every content-address mapping against the object it names.
+    ///
+    /// # Errors
+    /// Returns a [`StoreError`] only if git itself fails or the index is
+    /// unreadable; bad mappings are reported in [`ScrubReport::issues`].
+    pub fn verify_all(&self) -> Result<ScrubReport, StoreError> {
+        let mut mappings = Vec::new();
+        for alg in [HashAlg::Blake3, HashAlg::Sha256] {
+            for (id, oid) in self.index.entries(&self.repo, alg)? {
+                mappings.push((id, oid, MappingSource::Index));
+            }
+        }
+        for (id, oid, name) in self.legacy_refs()? {
+            mappings.push((id, oid, MappingSource::LegacyRef(name)));
+        }
+
+        let odb = self.repo.odb().map_err(io)?;
+        let mut report = ScrubReport::default();
+        for (id, oid, source) in mappings {
+            report.checked += 1;
+            let object = match odb.read(oid) {
+                Ok(object) => object,
+                Err(e) if e.code() == ErrorCode::NotFound => {
+                    report.issues.push(ScrubIssue::Dangling { id, oid, source });
+                    continue;
+                }
+                Err(e) => return Err(io(e)),
+            };
+            if object.kind() != ObjectType::Blob {
+                report.issues.push(ScrubIssue::WrongType {
+                    id,
+                    oid,
+                    kind: object.kind(),
+                    source,
+                });
+            } else if !id.addresses(object.data()) {
+                report.issues.push(ScrubIssue::Mismatch {
+                    id,
+                    oid,
+                    actual: id.alg().digest(object.data()),
+                    source,
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::OidIndex;
+    use crate::testing::TempRepo;
+    use crate::ObjectStore;
+
+    #[test]
+    fn reports_dangling_wrong_type_and_mismatch() {
+        let tmp = TempRepo::new();
+        let mut store = GitStore::new(tmp.open());
+        let good = &b"good"[..];
+        store.put_object(&blake3::hash(good).into(), good).unwrap();
+        assert!(store.verify_all().unwrap().is_clean());
+
+        let repo = tmp.open();
+        let mh = |data: &[u8]| Multihash::of(HashAlg::Blake3, data);
+        let missing = Oid::from_bytes(&[0xab; 20]).unwrap();
+        let tree = repo.treebuilder(None).unwrap().write().unwrap();
+        let wrong = repo.blob(b"swapped").unwrap();
+        OidIndex::default()
+            .insert(
+                &repo,
+                &[(mh(b"a"), missing), (mh(b"b"), tree), (mh(b"c"), wrong)],
+            )
+            .unwrap();
+        let legacy = "refs/gatos/blake3-map/".to_string() + &mh(b"d").to_hex();
+        repo.reference(&legacy, wrong, false, "legacy").unwrap();
+
+        let store = GitStore::new(tmp.open());
+        let report = store.verify_all().unwrap();
+        assert_eq!(report.checked, 5);
+        assert_eq!(report.issues.len(), 4);
+        assert!(report.issues.contains(&ScrubIssue::Dangling {
+            id: mh(b"a"),
+            oid: missing,
+            source: MappingSource::Index,
+        }));
+        assert!(report.issues.contains(&ScrubIssue::WrongType {
+            id: mh(b"b"),
+            oid: tree,
+            kind: ObjectType::Tree,
+            source: MappingSource::Index,
+        }));
+        assert!(report.issues.contains(&ScrubIssue::Mismatch {
+            id: mh(b"d"),
+            oid: wrong,
+            actual: blake3::hash(b"swapped").into(),
+            source: MappingSource::LegacyRef(legacy),
+        }));
+
+        // Reads through the store see the same tampering.
+        let id = |data: &[u8]| -> Hash { blake3::hash(data).into() };
+        assert_eq!(
+            store.get_object(&id(b"a")),
+            Err(StoreError::NotFound(id(b"a")))
+        );
+        assert!(matches!(
+            store.get_object(&id(b"b")),
+            Err(StoreError::Corruption { .. })
+        ));
+        assert_eq!(
+            store.get_object(&id(b"c")),
+            Err(StoreError::Corruption {
+                expected: id(b"c"),
+                actual: id(b"swapped"),
+            })
+        );
+        assert_eq!(
+            store.get_object(&id(b"good")).unwrap().as_deref(),
+            Some(good)
+        );
+    }
+}
@@ -4,8 +4,12 @@
 //! Minimal scaffold: parses CLI flags, initializes logging, and runs
 //! an async loop that waits for shutdown signals. The JSONL RPC server
 //! will be implemented in a subsequent iteration.
+//!
+//! `gatosd init [PATH]` creates the GATOS layout in a repository and exits.
+
+use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use tracing::{error, info};
 
 #[derive(Parser, Debug)]
@@ -14,12 +18,42 @@ struct Args {
     /// Serve JSONL protocol over stdio instead of sockets
     #[arg(long)]
     stdio: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create the GATOS layout (refs, `gatos/` workspace, git settings)
+    Init {
+        /// Repository to initialize
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Profile written to `gatos/config/profile.yaml`
+        #[arg(long, default_value = "local")]
+        profile: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     setup_tracing();
     let args = Args::parse();
+
+    if let Some(Command::Init { path, profile }) = args.command {
+        let options = gatos_ledger::InitOptions { profile };
+        let report =
+            tokio::task::spawn_blocking(move || gatos_ledger::init(&path, &options)).await??;
+        println!(
+            "initialized GATOS layout in {}; active policy {} ({})",
+            report.root.display(),
+            report.bundle,
+            report.bundle_ref
+        );
+        return Ok(());
+    }
+
     info!(?args, "starting gatosd");
 
     // TODO: wire up JSONL RPC server (stdio or TCP) per TECH-SPEC